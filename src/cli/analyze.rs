@@ -0,0 +1,209 @@
+//! `smol-evm analyze`: summarize a piece of bytecode: its jump destinations, which opcodes
+//! it uses, its size against the EIP-170 limit, the compiler metadata, if present, and
+//! stack underflows or overflows found by static validation.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+
+use clap::Args;
+use serde::Serialize;
+use smol_evm::analysis::JumpTable;
+use smol_evm::cfg::Cfg;
+use smol_evm::disassembler::disassemble;
+use smol_evm::frame::MAX_CODE_SIZE;
+use smol_evm::metadata::find_metadata;
+use smol_evm::stack_height::{StackIssue, StackReport};
+use smol_evm::Bytes;
+
+use super::{read_code, Format};
+
+#[derive(Debug, Args)]
+pub struct AnalyzeArgs {
+    /// Bytecode as hex, or a file with raw, hex or solc `.bin` code; stdin if omitted or `-`.
+    code: Option<String>,
+    /// Output format.
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Analysis {
+    size: usize,
+    /// EIP-170 limit on deployed code.
+    size_limit: usize,
+    jumpdests: Vec<usize>,
+    /// Opcode counts, most used first; bytes that are not opcodes count as `UNKNOWN`.
+    opcodes: Vec<OpcodeCount>,
+    metadata: Option<MetadataHash>,
+    /// Stack failures on some path of the control-flow graph.
+    stack_issues: Vec<StackIssue>,
+}
+
+#[derive(Debug, Serialize)]
+struct OpcodeCount {
+    opcode: String,
+    count: usize,
+}
+
+/// Metadata hash and compiler version appended to deployed code.
+#[derive(Debug, Serialize)]
+struct MetadataHash {
+    /// `ipfs`, `bzzr0` or `bzzr1`, or `none` if the trailer has no hash.
+    kind: String,
+    hash: Bytes,
+    /// Compiler name and version, e.g. `solc 0.8.24`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    compiler: Option<String>,
+}
+
+pub fn run(args: AnalyzeArgs) -> Result<(), String> {
+    let code = read_code(args.code.as_deref())?;
+    let analysis = analyze(&code);
+    match args.format {
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&analysis).expect("analyses always serialize")
+        ),
+        Format::Human => print!("{}", render(&analysis)),
+    }
+    Ok(())
+}
+
+fn analyze(code: &[u8]) -> Analysis {
+    let mut counts = BTreeMap::new();
+    for instruction in disassemble(code).instructions {
+        let name = instruction.opcode.map_or("UNKNOWN", |opcode| opcode.name());
+        *counts.entry(name).or_insert(0) += 1;
+    }
+    let mut opcodes: Vec<_> = counts
+        .into_iter()
+        .map(|(opcode, count)| OpcodeCount {
+            opcode: opcode.to_string(),
+            count,
+        })
+        .collect();
+    opcodes.sort_by_key(|entry| std::cmp::Reverse(entry.count));
+
+    Analysis {
+        size: code.len(),
+        size_limit: MAX_CODE_SIZE,
+        jumpdests: JumpTable::analyze(code).destinations(),
+        opcodes,
+        metadata: find_metadata(code).map(|metadata| MetadataHash {
+            kind: metadata
+                .hash
+                .as_ref()
+                .map_or("none", |hash| hash.kind.name())
+                .to_string(),
+            hash: metadata.hash.map_or_else(Bytes::new, |hash| hash.digest),
+            compiler: metadata.compiler.map(|compiler| compiler.to_string()),
+        }),
+        stack_issues: StackReport::analyze(&Cfg::build(code)).issues,
+    }
+}
+
+fn render(analysis: &Analysis) -> String {
+    let mut text = String::new();
+    let status = if analysis.size <= analysis.size_limit {
+        "within"
+    } else {
+        "EXCEEDS"
+    };
+    let _ = writeln!(
+        text,
+        "size:      {} bytes ({status} the EIP-170 limit of {} bytes, {:.1}%)",
+        analysis.size,
+        analysis.size_limit,
+        analysis.size as f64 * 100.0 / analysis.size_limit as f64
+    );
+    match &analysis.metadata {
+        Some(metadata) => {
+            let _ = write!(text, "metadata:  {} {}", metadata.kind, metadata.hash);
+            if let Some(compiler) = &metadata.compiler {
+                let _ = write!(text, " ({compiler})");
+            }
+            let _ = writeln!(text);
+        }
+        None => {
+            let _ = writeln!(text, "metadata:  none");
+        }
+    }
+    match analysis.stack_issues.len() {
+        0 => {
+            let _ = writeln!(text, "stack:     ok");
+        }
+        count => {
+            let noun = if count == 1 { "issue" } else { "issues" };
+            let _ = writeln!(text, "stack:     {count} {noun}");
+            for issue in &analysis.stack_issues {
+                let _ = writeln!(text, "  {issue}");
+            }
+        }
+    }
+    let jumpdests: Vec<_> = analysis
+        .jumpdests
+        .iter()
+        .map(|pc| format!("0x{pc:04x}"))
+        .collect();
+    let _ = writeln!(text, "jumpdests: {}", analysis.jumpdests.len());
+    for row in jumpdests.chunks(8) {
+        let _ = writeln!(text, "  {}", row.join(" "));
+    }
+    let _ = writeln!(text, "opcodes:");
+    for entry in &analysis.opcodes {
+        let _ = writeln!(text, "  {:<14} {:>6}", entry.opcode, entry.count);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze() {
+        // PUSH1 4 JUMP 0x0c JUMPDEST STOP, then a trailer {"ipfs": <34 bytes>, "solc": 0.8.24}
+        let mut code = vec![0x60, 4, 0x56, 0x0c, 0x5b, 0x00];
+        code.extend_from_slice(&[0xa2, 0x64, b'i', b'p', b'f', b's', 0x58, 0x22]);
+        code.extend_from_slice(&[0x12, 0x20]);
+        code.extend_from_slice(&[0xab; 32]);
+        code.extend_from_slice(&[0x64, b's', b'o', b'l', b'c', 0x43, 0x00, 0x08, 0x18]);
+        code.extend_from_slice(&[0x00, 0x33]);
+
+        let analysis = analyze(&code);
+        assert_eq!(analysis.size, code.len());
+        assert_eq!(analysis.jumpdests, [4]);
+        let metadata = analysis.metadata.as_ref().unwrap();
+        assert_eq!(metadata.kind, "ipfs");
+        assert_eq!(metadata.hash.len(), 34);
+        assert_eq!(metadata.hash[..2], [0x12, 0x20]);
+        assert_eq!(metadata.compiler.as_deref(), Some("solc 0.8.24"));
+        assert!(analysis
+            .opcodes
+            .iter()
+            .any(|entry| entry.opcode == "UNKNOWN" && entry.count > 0));
+
+        let text = render(&analysis);
+        assert!(text.contains("within the EIP-170 limit of 24576 bytes"));
+        assert!(text.contains(" (solc 0.8.24)\n"));
+        assert!(text.contains("stack:     ok\n"));
+        assert!(text.contains("jumpdests: 1\n  0x0004\n"));
+        assert!(analyze(&[0x00]).metadata.is_none());
+
+        // ADD with one item on the stack
+        let analysis = analyze(&[0x5f, 0x01, 0x00]);
+        assert_eq!(
+            analysis.stack_issues,
+            [StackIssue::Underflow {
+                pc: 1,
+                block: 0,
+                height: 0,
+                guaranteed: true
+            }]
+        );
+        assert!(render(&analysis).contains(
+            "stack:     1 issue\n  0x0001: guaranteed stack underflow when the block at 0x0000 is entered with 0 items\n"
+        ));
+    }
+}
@@ -0,0 +1,43 @@
+//! `smol-evm asm`: assemble mnemonic source into bytecode.
+
+use std::io::{Read, Write};
+use std::path::PathBuf;
+
+use clap::Args;
+use smol_evm::assembler::assemble;
+
+#[derive(Debug, Args)]
+pub struct AsmArgs {
+    /// Assembly source, e.g. `program.easm`; stdin if omitted or `-`.
+    source: Option<PathBuf>,
+    /// Write raw binary to this file instead of printing hex.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+pub fn run(args: AsmArgs) -> Result<(), String> {
+    let (name, source) = match &args.source {
+        Some(path) if path.as_os_str() != "-" => {
+            let source = std::fs::read_to_string(path)
+                .map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+            (path.display().to_string(), source)
+        }
+        _ => {
+            let mut source = String::new();
+            std::io::stdin()
+                .read_to_string(&mut source)
+                .map_err(|err| format!("cannot read stdin: {err}"))?;
+            ("<stdin>".to_string(), source)
+        }
+    };
+    let code = assemble(&source).map_err(|err| format!("{name}: {err}"))?;
+    match args.output {
+        Some(path) => std::fs::File::create(&path)
+            .and_then(|mut file| file.write_all(&code))
+            .map_err(|err| format!("cannot write {}: {err}", path.display())),
+        None => {
+            println!("0x{}", hex::encode(code));
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,35 @@
+//! `smol-evm cfg`: print the control-flow graph of a piece of bytecode.
+
+use clap::{Args, ValueEnum};
+use smol_evm::cfg::Cfg;
+
+use super::read_code;
+
+#[derive(Debug, Args)]
+pub struct CfgArgs {
+    /// Bytecode as hex, or a file with raw, hex or solc `.bin` code; stdin if omitted or `-`.
+    code: Option<String>,
+    /// Output format.
+    #[arg(long, value_enum, default_value_t)]
+    format: GraphFormat,
+}
+
+/// How the graph is printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+enum GraphFormat {
+    /// Graphviz DOT, e.g. for `dot -Tsvg`.
+    #[default]
+    Dot,
+    /// A single JSON document.
+    Json,
+}
+
+pub fn run(args: CfgArgs) -> Result<(), String> {
+    let code = read_code(args.code.as_deref())?;
+    let cfg = Cfg::build(&code);
+    match args.format {
+        GraphFormat::Dot => print!("{}", cfg.to_dot()),
+        GraphFormat::Json => println!("{}", cfg.to_json()),
+    }
+    Ok(())
+}
@@ -0,0 +1,364 @@
+//! `smol-evm debug`: step through bytecode interactively, reading commands from stdin.
+//!
+//! Execution pauses before the first instruction. An empty line repeats the previous
+//! command; `help` lists the others.
+
+use std::fmt::Write as _;
+use std::io::{BufRead, Write as _};
+
+use clap::Args;
+use smol_evm::debugger::{Breakpoint, Debugger, Pause, DEFAULT_SNAPSHOT_LIMIT};
+use smol_evm::frame::Frame;
+use smol_evm::opcodes::Opcode;
+use smol_evm::Address;
+
+use super::hexdump;
+use super::run::TxArgs;
+
+const HELP: &str = "\
+step, s [N]          execute N instructions (default 1)
+next, n              execute one instruction, running calls it makes to completion
+continue, c          run until a breakpoint or the end
+back [N]             undo N instructions (default 1)
+break, b pc N        pause before the instruction at program counter N
+break, b op NAME     pause before the next NAME instruction
+break, b depth N     pause when execution moves to call depth N
+delete, d N          remove breakpoint N
+breakpoints          list breakpoints
+where, w             show the next instruction
+stack                show the stack, top first
+memory, mem          show memory as a hex dump
+storage [ADDRESS]    show the storage of ADDRESS (default: the executing account)
+gas                  show the gas of the executing frame
+help, h              show this help
+quit, q              exit
+";
+
+#[derive(Debug, Args)]
+pub struct DebugArgs {
+    #[command(flatten)]
+    tx: TxArgs,
+    /// Number of instructions that can be undone with `back`.
+    #[arg(long, default_value_t = DEFAULT_SNAPSHOT_LIMIT)]
+    snapshots: usize,
+}
+
+/// A debugger command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    Step(usize),
+    Next,
+    Continue,
+    Back(usize),
+    Break(Breakpoint),
+    Delete(usize),
+    Breakpoints,
+    Where,
+    Stack,
+    Memory,
+    Storage(Option<Address>),
+    Gas,
+    Help,
+    Quit,
+}
+
+pub fn run(args: DebugArgs) -> Result<(), String> {
+    let (evm, tx) = args.tx.prepare()?;
+    let mut debugger = Debugger::new(evm, tx)
+        .map_err(|err| format!("invalid transaction: {err:?}"))?
+        .with_snapshot_limit(args.snapshots);
+    println!("paused before the first instruction; `help` lists commands");
+    print!("{}", location(&debugger));
+
+    let mut previous = None;
+    let mut lines = std::io::stdin().lock().lines();
+    loop {
+        print!("(smol-evm) ");
+        let _ = std::io::stdout().flush();
+        let Some(line) = lines.next() else {
+            println!();
+            return Ok(());
+        };
+        let line = line.map_err(|err| format!("cannot read stdin: {err}"))?;
+        let command = match (line.trim(), &previous) {
+            ("", Some(command)) => Ok(Command::clone(command)),
+            ("", None) => continue,
+            (line, _) => parse_command(line),
+        };
+        match command {
+            Ok(Command::Quit) => return Ok(()),
+            Ok(command) => {
+                print!("{}", execute(&mut debugger, &command));
+                previous = Some(command);
+            }
+            Err(message) => println!("error: {message}"),
+        }
+    }
+}
+
+fn parse_command(line: &str) -> Result<Command, String> {
+    let words: Vec<_> = line.split_whitespace().collect();
+    let count = |index: usize| words.get(index).map_or(Ok(1), |word| parse_number(word));
+    let command = match words.as_slice() {
+        ["step" | "s", ..] => Command::Step(count(1)?),
+        ["next" | "n"] => Command::Next,
+        ["continue" | "c"] => Command::Continue,
+        ["back", ..] => Command::Back(count(1)?),
+        ["break" | "b", "pc", pc] => Command::Break(Breakpoint::Pc(parse_number(pc)?)),
+        ["break" | "b", "op", name] => Command::Break(Breakpoint::Opcode(
+            Opcode::from_name(name).ok_or_else(|| format!("unknown opcode `{name}`"))?,
+        )),
+        ["break" | "b", "depth", depth] => Command::Break(Breakpoint::Depth(parse_number(depth)?)),
+        ["break" | "b", ..] => return Err("usage: break pc N | op NAME | depth N".to_string()),
+        ["delete" | "d", index] => Command::Delete(parse_number(index)?),
+        ["breakpoints"] => Command::Breakpoints,
+        ["where" | "w"] => Command::Where,
+        ["stack"] => Command::Stack,
+        ["memory" | "mem"] => Command::Memory,
+        ["storage"] => Command::Storage(None),
+        ["storage", address] => Command::Storage(Some(
+            address
+                .parse()
+                .map_err(|_| format!("invalid address `{address}`"))?,
+        )),
+        ["gas"] => Command::Gas,
+        ["help" | "h"] => Command::Help,
+        ["quit" | "q"] => Command::Quit,
+        _ => return Err(format!("unknown command `{line}`; try `help`")),
+    };
+    Ok(command)
+}
+
+/// Parses a decimal or `0x`-prefixed hex number.
+fn parse_number(text: &str) -> Result<usize, String> {
+    match text.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => text.parse(),
+    }
+    .map_err(|_| format!("invalid number `{text}`"))
+}
+
+/// Runs `command` and returns what to print.
+fn execute(debugger: &mut Debugger, command: &Command) -> String {
+    let mut text = String::new();
+    match *command {
+        Command::Step(count) => {
+            for _ in 0..count {
+                let Some(report) = debugger.step() else { break };
+                if let Some(error) = report.error {
+                    let _ = writeln!(text, "halted at 0x{:04x}: {error:?}", report.pc);
+                }
+            }
+            text.push_str(&location(debugger));
+        }
+        Command::Next => {
+            let pause = debugger.step_over();
+            text.push_str(&paused(debugger, pause));
+        }
+        Command::Continue => {
+            let pause = debugger.resume();
+            text.push_str(&paused(debugger, pause));
+        }
+        Command::Back(count) => {
+            let undone = (0..count).take_while(|_| debugger.step_back()).count();
+            if undone < count {
+                let _ = writeln!(text, "no earlier snapshot; undid {undone} instructions");
+            }
+            text.push_str(&location(debugger));
+        }
+        Command::Break(breakpoint) => {
+            let index = debugger.add_breakpoint(breakpoint);
+            let _ = writeln!(text, "breakpoint {index}: {breakpoint}");
+        }
+        Command::Delete(index) => match debugger.remove_breakpoint(index) {
+            Some(breakpoint) => {
+                let _ = writeln!(text, "deleted breakpoint {index}: {breakpoint}");
+            }
+            None => {
+                let _ = writeln!(text, "no breakpoint {index}");
+            }
+        },
+        Command::Breakpoints => {
+            if debugger.breakpoints().is_empty() {
+                text.push_str("no breakpoints\n");
+            }
+            for (index, breakpoint) in debugger.breakpoints().iter().enumerate() {
+                let _ = writeln!(text, "{index}: {breakpoint}");
+            }
+        }
+        Command::Where => text.push_str(&location(debugger)),
+        Command::Stack => {
+            let Some(frame) = inspected_frame(debugger) else {
+                return "no frame\n".to_string();
+            };
+            let stack = frame.stack().as_slice();
+            let _ = writeln!(text, "{} items, top first", stack.len());
+            for (index, value) in stack.iter().rev().enumerate() {
+                let _ = writeln!(text, "  [{index}] {value:#066x}");
+            }
+        }
+        Command::Memory => {
+            let Some(frame) = inspected_frame(debugger) else {
+                return "no frame\n".to_string();
+            };
+            let memory = frame.memory().to_bytes();
+            let _ = writeln!(text, "{} bytes", memory.len());
+            for line in hexdump(&memory).lines() {
+                let _ = writeln!(text, "  {line}");
+            }
+        }
+        Command::Storage(address) => {
+            let address =
+                address.or_else(|| inspected_frame(debugger).map(|frame| frame.context().address));
+            let Some(address) = address else {
+                return "no frame\n".to_string();
+            };
+            let account = debugger.evm().state().account(&address);
+            let storage = account.map(|account| &account.storage);
+            let slots = storage.map_or(0, |storage| storage.len());
+            let _ = writeln!(text, "storage of {address}: {slots} slots");
+            for (key, value) in storage.into_iter().flatten() {
+                let _ = writeln!(text, "  {key} = {value:#066x}");
+            }
+        }
+        Command::Gas => match debugger.frame() {
+            Some(frame) => {
+                let gas = frame.gas();
+                let _ = writeln!(text, "remaining: {}", gas.remaining_gas());
+                let _ = writeln!(
+                    text,
+                    "used:      {} of {}",
+                    gas.total_gas_used(),
+                    gas.gas_limit()
+                );
+                let _ = writeln!(text, "memory:    {}", gas.memory_gas_cost());
+                let _ = writeln!(text, "refund:    {}", gas.gas_refund());
+            }
+            None => text.push_str(&location(debugger)),
+        },
+        Command::Help => text.push_str(HELP),
+        Command::Quit => {}
+    }
+    text
+}
+
+/// The frame inspection commands look at: the executing one, or the outermost frame as
+/// it exited once the transaction finished.
+fn inspected_frame(debugger: &Debugger) -> Option<&Frame> {
+    debugger.frame().or_else(|| debugger.evm().last_frame())
+}
+
+/// Describes why execution paused, then where.
+fn paused(debugger: &Debugger, pause: Pause) -> String {
+    let mut text = String::new();
+    if let Pause::Breakpoint(index) = pause {
+        let breakpoint = debugger.breakpoints()[index];
+        let _ = writeln!(text, "breakpoint {index}: {breakpoint}");
+    }
+    text.push_str(&location(debugger));
+    text
+}
+
+/// Describes the next instruction, or the result once the transaction finished.
+fn location(debugger: &Debugger) -> String {
+    let Some(frame) = debugger.frame() else {
+        let Some(result) = debugger.result() else {
+            return "finished\n".to_string();
+        };
+        let mut text = format!(
+            "finished: {:?}, gas used {}\n",
+            result.exit_reason, result.gas.gas_used
+        );
+        if let Some(reason) = result.revert_reason() {
+            let _ = writeln!(text, "revert: {reason}");
+        }
+        if !result.output.is_empty() {
+            let _ = writeln!(text, "output: {}", result.output);
+        }
+        return text;
+    };
+    let instruction = match debugger.next_instruction() {
+        Some(instruction) => instruction.to_string(),
+        None => format!("0x{:04x}: end of code (STOP)", frame.pc()),
+    };
+    format!(
+        "[depth {}] {instruction}  (gas {})\n",
+        frame.depth(),
+        frame.gas().remaining_gas()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn debugger(args: &[&str]) -> Debugger {
+        #[derive(Parser)]
+        struct Wrapper {
+            #[command(flatten)]
+            args: TxArgs,
+        }
+        let args = std::iter::once("debug").chain(args.iter().copied());
+        let (evm, tx) = Wrapper::parse_from(args).args.prepare().unwrap();
+        Debugger::new(evm, tx).unwrap()
+    }
+
+    fn run(debugger: &mut Debugger, line: &str) -> String {
+        execute(debugger, &parse_command(line).unwrap())
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command("s 0x10"), Ok(Command::Step(16)));
+        assert_eq!(parse_command("back"), Ok(Command::Back(1)));
+        assert_eq!(
+            parse_command("b op sstore"),
+            Ok(Command::Break(Breakpoint::Opcode(Opcode::Sstore)))
+        );
+        assert_eq!(
+            parse_command("storage 0x00000000000000000000000000000000000000c0"),
+            Ok(Command::Storage(Some(Address::with_last_byte(0xc0))))
+        );
+        assert!(parse_command("b op FOO").unwrap_err().contains("FOO"));
+        assert!(parse_command("break").is_err());
+        assert!(parse_command("jump").is_err());
+    }
+
+    #[test]
+    fn test_session() {
+        // PUSH1 0x2a PUSH1 0 MSTORE PUSH1 7 PUSH1 1 SSTORE STOP
+        let mut debugger = debugger(&["0x602a60005260076001555f00"]);
+        assert_eq!(
+            location(&debugger),
+            "[depth 0] 0x0000: PUSH1 0x2a  (gas 29979000)\n"
+        );
+        assert_eq!(
+            run(&mut debugger, "b op SSTORE"),
+            "breakpoint 0: opcode SSTORE\n"
+        );
+        assert!(run(&mut debugger, "c").starts_with("breakpoint 0: opcode SSTORE\n"));
+        let stack = run(&mut debugger, "stack");
+        assert!(stack.starts_with("2 items, top first\n  [0] 0x00"));
+        assert!(stack.ends_with(
+            "01\n  [1] 0x0000000000000000000000000000000000000000000000000000000000000007\n"
+        ));
+        assert!(run(&mut debugger, "mem").starts_with("32 bytes\n  0000: 00"));
+        assert!(run(&mut debugger, "gas").contains("memory:    3\n"));
+
+        assert!(run(&mut debugger, "n").contains("0x000a: PUSH0"));
+        assert!(run(&mut debugger, "storage").contains(": 1 slots\n"));
+        assert!(run(&mut debugger, "back").contains("0x0009: SSTORE"));
+        assert!(run(&mut debugger, "storage").contains(": 0 slots\n"));
+
+        assert!(run(&mut debugger, "s 10").starts_with("finished: Stop, gas used"));
+        // The outermost frame stays inspectable
+        assert!(run(&mut debugger, "stack").starts_with("1 items"));
+        assert!(run(&mut debugger, "back 100").starts_with("no earlier snapshot; undid 8"));
+        assert_eq!(
+            run(&mut debugger, "d 0"),
+            "deleted breakpoint 0: opcode SSTORE\n"
+        );
+        assert_eq!(run(&mut debugger, "breakpoints"), "no breakpoints\n");
+    }
+}
@@ -0,0 +1,28 @@
+//! `smol-evm disasm`: print the instructions of a piece of bytecode.
+
+use clap::Args;
+use smol_evm::disassembler::disassemble;
+
+use super::{read_code, Format};
+
+#[derive(Debug, Args)]
+pub struct DisasmArgs {
+    /// Bytecode as hex, or a file with raw, hex or solc `.bin` code; stdin if omitted or `-`.
+    code: Option<String>,
+    /// Output format.
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+}
+
+pub fn run(args: DisasmArgs) -> Result<(), String> {
+    let code = read_code(args.code.as_deref())?;
+    let disassembly = disassemble(&code);
+    match args.format {
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&disassembly).expect("disassemblies always serialize")
+        ),
+        Format::Human => print!("{disassembly}"),
+    }
+    Ok(())
+}
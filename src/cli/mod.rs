@@ -0,0 +1,178 @@
+//! Command-Line Interface
+//!
+//! Subcommands of the `smol-evm` binary and the helpers they share: reading bytecode
+//! from the command line, files or stdin, and rendering output for people or as JSON.
+//!
+//! # Design
+//! - Each subcommand lives in its own module with a clap `Args` struct and a `run`
+//!   function that prints to stdout and returns an error message on failure
+//! - Bytecode inputs accept raw binary, `0x`-prefixed or bare hex, and solc `.bin` files;
+//!   see [`decode_code`]
+
+use std::fmt::Write as _;
+use std::io::Read;
+use std::path::Path;
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use smol_evm::sourcemap::{decode_bytecode, BytecodeError};
+
+mod analyze;
+mod asm;
+mod cfg;
+mod debug;
+mod disasm;
+mod run;
+
+/// A small, readable Ethereum Virtual Machine.
+#[derive(Debug, Parser)]
+#[command(name = "smol-evm", version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Execute bytecode and print the result.
+    Run(Box<run::RunArgs>),
+    /// Step through bytecode interactively.
+    Debug(Box<debug::DebugArgs>),
+    /// Print the instructions of bytecode.
+    Disasm(disasm::DisasmArgs),
+    /// Assemble mnemonic source into bytecode.
+    Asm(asm::AsmArgs),
+    /// Summarize bytecode: jump destinations, opcode usage, size and metadata.
+    Analyze(analyze::AnalyzeArgs),
+    /// Print the control-flow graph of bytecode as Graphviz DOT or JSON.
+    Cfg(cfg::CfgArgs),
+}
+
+impl Cli {
+    /// Runs the selected subcommand.
+    pub fn run(self) -> ExitCode {
+        let result = match self.command {
+            Command::Run(args) => run::run(*args),
+            Command::Debug(args) => debug::run(*args),
+            Command::Disasm(args) => disasm::run(args),
+            Command::Asm(args) => asm::run(args),
+            Command::Analyze(args) => analyze::run(args),
+            Command::Cfg(args) => cfg::run(args),
+        };
+        match result {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(message) => {
+                eprintln!("error: {message}");
+                ExitCode::FAILURE
+            }
+        }
+    }
+}
+
+/// How results are printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Aligned text for people.
+    #[default]
+    Human,
+    /// A single JSON document.
+    Json,
+}
+
+/// Reads bytecode given on the command line: a file path, `-` or nothing for stdin, or
+/// the code itself as hex.
+pub fn read_code(input: Option<&str>) -> Result<Vec<u8>, String> {
+    let bytes = match input {
+        None | Some("-") => {
+            let mut bytes = Vec::new();
+            std::io::stdin()
+                .read_to_end(&mut bytes)
+                .map_err(|err| format!("cannot read stdin: {err}"))?;
+            bytes
+        }
+        Some(path) if Path::new(path).is_file() => {
+            std::fs::read(path).map_err(|err| format!("cannot read {path}: {err}"))?
+        }
+        Some(hex) => return decode_hex(hex),
+    };
+    Ok(decode_code(&bytes))
+}
+
+/// Decodes the contents of a bytecode file.
+///
+/// Text consisting only of hex digits, optionally prefixed with `0x` and surrounded by
+/// whitespace, is decoded as hex; this covers solc `.bin` output, whose unlinked library
+/// placeholders (`__$...$__`) are filled with the zero address. Anything else is taken as
+/// raw binary.
+pub fn decode_code(bytes: &[u8]) -> Vec<u8> {
+    std::str::from_utf8(bytes)
+        .ok()
+        .and_then(|text| decode_hex(text).ok())
+        .unwrap_or_else(|| bytes.to_vec())
+}
+
+/// Decodes a hex string, with or without `0x`, ignoring surrounding whitespace and
+/// replacing solc library placeholders with zeros.
+pub fn decode_hex(text: &str) -> Result<Vec<u8>, String> {
+    decode_bytecode(text.trim()).map_err(|err| match err {
+        BytecodeError::MalformedPlaceholder => "malformed library placeholder".to_string(),
+        BytecodeError::InvalidHex(err) => format!("invalid hex: {err}"),
+    })
+}
+
+/// Renders bytes as a hex dump, 32 bytes (one word) per line, with printable ASCII on
+/// the right.
+pub fn hexdump(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+    for (index, row) in bytes.chunks(32).enumerate() {
+        let ascii: String = row
+            .iter()
+            .map(|byte| match byte {
+                0x20..=0x7e => *byte as char,
+                _ => '.',
+            })
+            .collect();
+        let _ = writeln!(
+            dump,
+            "{:04x}: {:<64} |{ascii}|",
+            index * 32,
+            hex::encode(row)
+        );
+    }
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_code() {
+        assert_eq!(decode_code(b"0x6080\n"), [0x60, 0x80]);
+        assert_eq!(decode_code(b"6080604052"), [0x60, 0x80, 0x60, 0x40, 0x52]);
+        // Not hex: raw binary
+        assert_eq!(decode_code(&[0x60, 0x80, 0x00]), [0x60, 0x80, 0x00]);
+
+        let linked = format!("73{}3f", "__$0123456789abcdef0123456789abcdef01$__");
+        let code = decode_hex(&linked).unwrap();
+        assert_eq!(code.len(), 22);
+        assert_eq!((code[0], code[1], code[21]), (0x73, 0, 0x3f));
+        assert!(decode_hex("0x6g").is_err());
+        assert_eq!(
+            decode_hex(&format!("__${}", "é".repeat(20))),
+            Err("malformed library placeholder".to_string())
+        );
+    }
+
+    #[test]
+    fn test_hexdump() {
+        let mut bytes = vec![0u8; 33];
+        bytes[..5].copy_from_slice(b"hello");
+        let dump = hexdump(&bytes);
+        let lines: Vec<_> = dump.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("0000: 68656c6c6f00"));
+        assert!(lines[0].ends_with("|hello...........................|"));
+        assert!(lines[1].starts_with("0020: 00 "));
+    }
+}
@@ -0,0 +1,290 @@
+//! `smol-evm run`: execute bytecode as the code of a contract and report the outcome,
+//! including the outermost frame's final stack and memory.
+
+use std::fmt::Write as _;
+
+use clap::Args;
+use serde::Serialize;
+use smol_evm::eip3155::Eip3155Tracer;
+use smol_evm::env::{BlockEnv, TxEnv};
+use smol_evm::execution::{Evm, ExecutionResult};
+use smol_evm::spec::SpecId;
+use smol_evm::storage::{Account, WorldState};
+use smol_evm::{Address, Bytes, B256, U256};
+
+use super::{decode_hex, hexdump, read_code, Format};
+
+/// Address the code runs at unless `--address` is given.
+const DEFAULT_ADDRESS: Address = Address::with_last_byte(0xc0);
+/// Sender of the transaction unless `--caller` is given.
+const DEFAULT_CALLER: Address = Address::with_last_byte(0xca);
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    #[command(flatten)]
+    tx: TxArgs,
+    /// Print an EIP-3155 trace to stderr: one JSON line per instruction, then a summary.
+    #[arg(long)]
+    trace: bool,
+    /// Output format.
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+}
+
+/// Code, transaction and block flags shared by the commands that execute code.
+#[derive(Debug, Args)]
+pub struct TxArgs {
+    /// Bytecode as hex, or a file with raw, hex or solc `.bin` code; stdin if omitted or `-`.
+    code: Option<String>,
+    /// Calldata as hex.
+    #[arg(long, short = 'd', default_value = "")]
+    calldata: String,
+    /// Gas limit of the transaction.
+    #[arg(long, default_value_t = 30_000_000)]
+    gas_limit: u64,
+    /// Value sent with the call, in wei.
+    #[arg(long, default_value_t = U256::ZERO)]
+    value: U256,
+    /// Sender of the transaction.
+    #[arg(long, default_value_t = DEFAULT_CALLER)]
+    caller: Address,
+    /// Address the code runs at.
+    #[arg(long, default_value_t = DEFAULT_ADDRESS)]
+    address: Address,
+    /// Gas price, in wei.
+    #[arg(long, default_value_t = U256::ZERO)]
+    gas_price: U256,
+    /// Fork whose rules apply, e.g. `cancun`.
+    #[arg(long, default_value = "latest")]
+    spec: SpecId,
+    #[command(flatten)]
+    block: BlockArgs,
+}
+
+/// Block environment flags.
+#[derive(Debug, Args)]
+pub struct BlockArgs {
+    /// Block number.
+    #[arg(long, default_value_t = 0)]
+    number: u64,
+    /// Block timestamp, in seconds.
+    #[arg(long, default_value_t = 0)]
+    timestamp: u64,
+    /// Block beneficiary.
+    #[arg(long, default_value_t = Address::ZERO)]
+    coinbase: Address,
+    /// Base fee per gas, in wei.
+    #[arg(long, default_value_t = U256::ZERO)]
+    basefee: U256,
+    /// Block gas limit.
+    #[arg(long, default_value_t = 30_000_000)]
+    block_gas_limit: u64,
+    /// Difficulty, before the Merge.
+    #[arg(long, default_value_t = U256::ZERO)]
+    difficulty: U256,
+    /// Beacon chain randomness, from the Merge.
+    #[arg(long, default_value_t = B256::ZERO)]
+    prevrandao: B256,
+    /// Chain identifier.
+    #[arg(long, default_value_t = 1)]
+    chain_id: u64,
+}
+
+impl From<BlockArgs> for BlockEnv {
+    fn from(args: BlockArgs) -> Self {
+        BlockEnv {
+            number: args.number,
+            timestamp: args.timestamp,
+            coinbase: args.coinbase,
+            basefee: args.basefee,
+            gas_limit: args.block_gas_limit,
+            difficulty: args.difficulty,
+            prevrandao: args.prevrandao,
+            chain_id: args.chain_id,
+            ..BlockEnv::default()
+        }
+    }
+}
+
+/// Everything `run` reports.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RunOutput {
+    success: bool,
+    #[serde(flatten)]
+    result: ExecutionResult,
+    revert_reason: Option<String>,
+    /// Final stack of the outermost frame, bottom first.
+    stack: Vec<U256>,
+    /// Final memory of the outermost frame.
+    memory: Bytes,
+}
+
+pub fn run(args: RunArgs) -> Result<(), String> {
+    let format = args.format;
+    let output = execute(args)?;
+    match format {
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(&output).expect("run output always serializes")
+        ),
+        Format::Human => print!("{}", render(&output)),
+    }
+    Ok(())
+}
+
+impl TxArgs {
+    /// Builds an interpreter whose state holds the code at `--address` and a caller that
+    /// can afford the transaction, and the transaction calling it.
+    pub fn prepare(self) -> Result<(Evm, TxEnv), String> {
+        let code = read_code(self.code.as_deref())?;
+        let calldata = decode_hex(&self.calldata).map_err(|err| format!("calldata: {err}"))?;
+
+        // The caller can always afford the transaction
+        let balance = U256::from(self.gas_limit)
+            .saturating_mul(self.gas_price)
+            .saturating_add(self.value);
+        let mut state = WorldState::new();
+        state.insert_account(self.caller, Account::with_balance(balance));
+        // Code run as its own sender must keep the caller's balance
+        let contract_balance = if self.address == self.caller {
+            balance
+        } else {
+            U256::ZERO
+        };
+        state.insert_account(
+            self.address,
+            Account {
+                balance: contract_balance,
+                ..Account::with_code(code.into())
+            },
+        );
+
+        let evm = Evm::with_spec(self.spec)
+            .with_state(state)
+            .with_block(self.block.into());
+        let tx = TxEnv {
+            caller: self.caller,
+            to: Some(self.address),
+            value: self.value,
+            data: calldata.into(),
+            gas_limit: self.gas_limit,
+            gas_price: self.gas_price,
+            ..TxEnv::default()
+        };
+        Ok((evm, tx))
+    }
+}
+
+fn execute(args: RunArgs) -> Result<RunOutput, String> {
+    let (mut evm, tx) = args.tx.prepare()?;
+    let result = if args.trace {
+        let mut tracer = Eip3155Tracer::new();
+        let result = tracer.transact(&mut evm, tx);
+        eprint!("{tracer}");
+        result
+    } else {
+        evm.transact(tx)
+    }
+    .map_err(|err| format!("invalid transaction: {err:?}"))?;
+    let (stack, memory) = match evm.last_frame() {
+        Some(frame) => (frame.stack().as_slice().to_vec(), frame.memory().to_bytes()),
+        None => (Vec::new(), Vec::new()),
+    };
+    Ok(RunOutput {
+        success: result.is_success(),
+        revert_reason: result.revert_reason(),
+        result,
+        stack,
+        memory: memory.into(),
+    })
+}
+
+fn render(output: &RunOutput) -> String {
+    let result = &output.result;
+    let mut text = String::new();
+    let status = if output.success { "success" } else { "failure" };
+    let _ = writeln!(text, "result:      {:?} ({status})", result.exit_reason);
+    if let Some(reason) = &output.revert_reason {
+        let _ = writeln!(text, "revert:      {reason}");
+    }
+    let _ = writeln!(text, "gas used:    {}", result.gas.gas_used);
+    let _ = writeln!(text, "gas refund:  {}", result.gas.gas_refund);
+    let _ = writeln!(text, "return data: {}", result.output);
+
+    let _ = writeln!(text, "logs:        {}", result.logs.len());
+    for (index, log) in result.logs.iter().enumerate() {
+        let _ = writeln!(text, "  [{index}] address {}", log.address);
+        for (topic_index, topic) in log.topics.iter().enumerate() {
+            let _ = writeln!(text, "      topic{topic_index}  {topic}");
+        }
+        let _ = writeln!(text, "      data    {}", log.data);
+    }
+
+    let _ = writeln!(text, "stack:       {} items, top first", output.stack.len());
+    for (index, value) in output.stack.iter().rev().enumerate() {
+        let _ = writeln!(text, "  [{index}] {:#066x}", value);
+    }
+    let _ = writeln!(text, "memory:      {} bytes", output.memory.len());
+    for line in hexdump(&output.memory).lines() {
+        let _ = writeln!(text, "  {line}");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn execute_args(args: &[&str]) -> RunOutput {
+        #[derive(Parser)]
+        struct Wrapper {
+            #[command(flatten)]
+            args: RunArgs,
+        }
+        let args = std::iter::once("run").chain(args.iter().copied());
+        execute(Wrapper::parse_from(args).args).unwrap()
+    }
+
+    #[test]
+    fn test_render() {
+        // CALLDATASIZE PUSH1 7 PUSH1 0x2a PUSH1 0 MSTORE PUSH1 32 PUSH1 0 LOG0 STOP
+        let code = "0x366007602a60005260206000a000";
+        let output = execute_args(&[code, "--calldata", "0xabcd", "--spec", "cancun"]);
+        assert!(output.success);
+        assert_eq!(output.stack, [U256::from(2), U256::from(7)]);
+        assert_eq!(output.result.logs.len(), 1);
+
+        let text = render(&output);
+        assert!(text.starts_with("result:      Stop (success)\n"));
+        assert!(text.contains("stack:       2 items, top first\n  [0] 0x0000"));
+        assert!(text.contains("memory:      32 bytes\n  0000: 00"));
+
+        let json: serde_json::Value = serde_json::to_value(&output).unwrap();
+        assert_eq!(json["exitReason"], "Stop");
+        assert_eq!(json["stack"][1], "0x7");
+        assert_eq!(json["logs"][0]["data"].as_str().unwrap().len(), 66);
+    }
+
+    #[test]
+    fn test_caller_is_address() {
+        // SELFBALANCE PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+        let code = "0x4760005260206000f3";
+        let output = execute_args(&[
+            code,
+            "--caller",
+            "0x00000000000000000000000000000000000000c0",
+            "--value",
+            "5",
+            "--gas-price",
+            "1",
+            "--gas-limit",
+            "100000",
+        ]);
+        assert!(output.success);
+        // The gas is paid up front; the value sent to itself stays
+        let balance = U256::from_be_slice(&output.result.output);
+        assert_eq!(balance, U256::from(5));
+    }
+}
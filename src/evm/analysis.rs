@@ -0,0 +1,166 @@
+//! Bytecode Analysis
+//!
+//! Static analysis performed on code before it is executed. The interpreter uses it to
+//! validate jump targets: a `JUMP` or `JUMPI` may only land on a `JUMPDEST` byte that is an
+//! instruction, not on one that happens to sit inside the immediate data of a `PUSH`.
+//!
+//! It also splits code into basic blocks and sums their base costs, so that the
+//! interpreter can charge a whole block's static gas once on entry instead of once per
+//! instruction.
+//!
+//! # References
+//! - [Ethereum Yellow Paper, Section 9.4.3 (Jump Destination Validity)]
+//! - [evmone: Advanced interpreter](https://github.com/ethereum/evmone/blob/master/docs/efficient_gas_calculation_algorithm.md)
+
+use super::opcodes::Opcode;
+use super::schedule::GasSchedule;
+
+/// The set of valid jump destinations of a piece of code.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JumpTable {
+    /// `valid[pc]` is `true` if `pc` holds a `JUMPDEST` instruction.
+    valid: Vec<bool>,
+}
+
+impl JumpTable {
+    /// Analyzes `code`, skipping over `PUSH` immediates.
+    pub fn analyze(code: &[u8]) -> Self {
+        let mut valid = vec![false; code.len()];
+        let mut pc = 0;
+        while pc < code.len() {
+            match Opcode::from_byte(code[pc]) {
+                Some(Opcode::Jumpdest) => valid[pc] = true,
+                Some(opcode) => pc += opcode.immediate_size(),
+                None => {}
+            }
+            pc += 1;
+        }
+        Self { valid }
+    }
+
+    /// Returns `true` if `pc` is a valid jump destination.
+    pub fn is_valid(&self, pc: usize) -> bool {
+        self.valid.get(pc).copied().unwrap_or(false)
+    }
+
+    /// Returns all valid jump destinations in ascending order.
+    pub fn destinations(&self) -> Vec<usize> {
+        self.valid
+            .iter()
+            .enumerate()
+            .filter_map(|(pc, valid)| valid.then_some(pc))
+            .collect()
+    }
+}
+
+/// The summed base cost of every basic block of a piece of code.
+///
+/// A block starts at the beginning of the code, at every `JUMPDEST`, and after every
+/// instruction that ends one. Blocks end at instructions that leave the straight-line
+/// path (jumps, halts, bytes that are not opcodes) and also at instructions that observe
+/// the remaining gas (`GAS`, `SSTORE`, calls and creations), so that gas charged ahead
+/// for later instructions never changes what they see.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockGasTable {
+    /// `costs[pc]` is the block's static gas if a block starts at `pc`.
+    costs: Vec<Option<u64>>,
+}
+
+impl BlockGasTable {
+    /// Splits `code` into basic blocks and sums their base costs under `schedule`.
+    pub fn analyze(code: &[u8], schedule: &GasSchedule) -> Self {
+        let mut costs = vec![None; code.len()];
+        let mut start = 0;
+        let mut sum = 0u64;
+        let mut pc = 0;
+        while pc < code.len() {
+            let opcode =
+                Opcode::from_byte(code[pc]).filter(|opcode| opcode.is_enabled_in(schedule.spec));
+            if opcode == Some(Opcode::Jumpdest) && pc != start {
+                costs[start] = Some(sum);
+                (start, sum) = (pc, 0);
+            }
+            pc += 1;
+            let ends_block = match opcode {
+                Some(opcode) => {
+                    sum = sum.saturating_add(schedule.opcode_cost(opcode));
+                    pc += opcode.immediate_size();
+                    Self::ends_block(opcode)
+                }
+                None => true,
+            };
+            if ends_block {
+                costs[start] = Some(sum);
+                (start, sum) = (pc, 0);
+            }
+        }
+        if start < code.len() {
+            costs[start] = Some(sum);
+        }
+        Self { costs }
+    }
+
+    /// Returns the static gas of the block starting at `pc`, or `None` if no block
+    /// starts there.
+    pub fn block_cost(&self, pc: usize) -> Option<u64> {
+        self.costs.get(pc).copied().flatten()
+    }
+
+    /// Returns `true` if `opcode` is the last instruction of its block.
+    pub fn ends_block(opcode: Opcode) -> bool {
+        matches!(
+            opcode,
+            Opcode::Stop
+                | Opcode::Jump
+                | Opcode::Jumpi
+                | Opcode::Return
+                | Opcode::Revert
+                | Opcode::Invalid
+                | Opcode::Selfdestruct
+                | Opcode::Gas
+                | Opcode::Sstore
+                | Opcode::Call
+                | Opcode::Callcode
+                | Opcode::Delegatecall
+                | Opcode::Staticcall
+                | Opcode::Create
+                | Opcode::Create2
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::spec::SpecId;
+
+    #[test]
+    fn test_jumpdest_analysis() {
+        // PUSH1 0x5b; JUMPDEST; PUSH2 0x5b5b; JUMPDEST
+        let code = [0x60, 0x5b, 0x5b, 0x61, 0x5b, 0x5b, 0x5b];
+        let table = JumpTable::analyze(&code);
+        assert!(!table.is_valid(1)); // inside PUSH1 data
+        assert!(table.is_valid(2));
+        assert!(!table.is_valid(4));
+        assert!(table.is_valid(6));
+        assert!(!table.is_valid(100));
+        assert_eq!(table.destinations(), vec![2, 6]);
+    }
+
+    #[test]
+    fn test_block_gas() {
+        // PUSH1 4 JUMP | JUMPDEST PUSH1 0 GAS | POP STOP | PUSH0 (unknown in London) | ADD
+        let code = [
+            0x60, 4, 0x56, 0x00, 0x5b, 0x60, 0, 0x5a, 0x50, 0x00, 0x5f, 0x01,
+        ];
+        let table = BlockGasTable::analyze(&code, &GasSchedule::for_spec(SpecId::London));
+        assert_eq!(table.block_cost(0), Some(3 + 8));
+        assert_eq!(table.block_cost(3), Some(0));
+        assert_eq!(table.block_cost(4), Some(1 + 3 + 2));
+        assert_eq!(table.block_cost(8), Some(2));
+        assert_eq!(table.block_cost(10), Some(0));
+        assert_eq!(table.block_cost(11), Some(3));
+        assert_eq!(table.block_cost(5), None);
+        assert_eq!(table.block_cost(100), None);
+    }
+}
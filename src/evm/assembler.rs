@@ -0,0 +1,729 @@
+//! Assembler
+//!
+//! Turns mnemonic text into bytecode, for writing EVM snippets by hand:
+//!
+//! ```text
+//! #define COUNT 3             ; constants and macros
+//! #define DECREMENT PUSH1 1 SWAP1 SUB
+//!
+//!     PUSH COUNT              ; PUSH picks the smallest PUSHn that fits
+//! @loop:                      ; a label
+//!     JUMPDEST
+//!     DECREMENT
+//!     DUP1 PUSH @loop JUMPI   // jump back while non-zero
+//!     PUSH2 (COUNT * 0x100 + 1) POP
+//!     STOP
+//! ```
+//!
+//! # Design
+//! - Instructions are the [`Opcode`] mnemonics, in any case, separated by whitespace or
+//!   newlines. `PUSH1`..`PUSH32` take one operand; `PUSH`, a bare operand or a bare label
+//!   reference (`@loop`) pushes with the smallest size that fits
+//! - Operands are constant expressions over hex and decimal literals, `#define`d
+//!   constants and label references, with `+ - * / % << >> & | ^ ~` and parentheses.
+//!   An operand containing spaces must be parenthesized. Arithmetic wraps at 256 bits
+//! - `#define NAME body` defines a macro: used as an instruction, `NAME` expands to the
+//!   body's instructions; used in an operand, the body is evaluated as an expression.
+//!   A macro cannot take the name of an instruction
+//! - Label references may point forward. Pushes sized automatically start at one byte and
+//!   grow until every label address fits, so the layout is as small as possible
+//! - `;` and `//` start comments
+//! - Errors carry the 1-based line and column of the offending token
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use super::opcodes::Opcode;
+use crate::types::U256;
+
+/// Macros may expand into macros, but no deeper than this.
+const MAX_MACRO_DEPTH: usize = 64;
+
+/// Why assembling failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssembleErrorKind {
+    /// The token is neither a mnemonic, a macro, an operand nor a label.
+    UnknownMnemonic(String),
+    /// A label definition whose name is not an identifier.
+    InvalidLabel(String),
+    /// A label is referenced but never defined.
+    UnknownLabel(String),
+    /// An identifier in an operand is not a `#define`d constant.
+    UnknownConstant(String),
+    /// A label is defined twice.
+    DuplicateLabel(String),
+    /// A macro is defined twice.
+    DuplicateDefinition(String),
+    /// A macro is named after an instruction.
+    ReservedName(String),
+    /// A directive other than `#define`, or a `#define` without a valid name.
+    InvalidDirective(String),
+    /// A `PUSH` without an operand.
+    MissingOperand(String),
+    /// An operand that is not a valid expression.
+    InvalidExpression(String),
+    /// An operand divides by zero.
+    DivisionByZero,
+    /// An operand does not fit the explicit `PUSHn` size.
+    ValueTooLarge { size: usize },
+    /// A macro expands to itself.
+    RecursiveMacro(String),
+}
+
+/// An assembly error and where it occurred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssembleError {
+    /// 1-based line of the offending token.
+    pub line: usize,
+    /// 1-based column of the offending token.
+    pub column: usize,
+    pub kind: AssembleErrorKind,
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            AssembleErrorKind::UnknownMnemonic(name) => write!(f, "unknown mnemonic `{name}`"),
+            AssembleErrorKind::InvalidLabel(text) => write!(f, "invalid label `{text}`"),
+            AssembleErrorKind::UnknownLabel(name) => write!(f, "unknown label `@{name}`"),
+            AssembleErrorKind::UnknownConstant(name) => write!(f, "unknown constant `{name}`"),
+            AssembleErrorKind::DuplicateLabel(name) => write!(f, "label `@{name}` already defined"),
+            AssembleErrorKind::DuplicateDefinition(name) => {
+                write!(f, "macro `{name}` already defined")
+            }
+            AssembleErrorKind::ReservedName(name) => {
+                write!(f, "`{name}` is an instruction and cannot be a macro")
+            }
+            AssembleErrorKind::InvalidDirective(text) => write!(f, "invalid directive `{text}`"),
+            AssembleErrorKind::MissingOperand(name) => write!(f, "`{name}` needs an operand"),
+            AssembleErrorKind::InvalidExpression(reason) => {
+                write!(f, "invalid expression: {reason}")
+            }
+            AssembleErrorKind::DivisionByZero => write!(f, "division by zero"),
+            AssembleErrorKind::ValueTooLarge { size } => {
+                write!(f, "value does not fit in {size} bytes")
+            }
+            AssembleErrorKind::RecursiveMacro(name) => {
+                write!(f, "macro `{name}` expands to itself")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AssembleError {}
+
+/// Assembles `source` into bytecode.
+///
+/// # Errors
+/// Returns the first [`AssembleError`] found, with its line and column.
+///
+/// # Examples
+/// ```
+/// use smol_evm::assembler::assemble;
+///
+/// let code = assemble("PUSH1 0x80 PUSH 64 MSTORE ; comment").unwrap();
+/// assert_eq!(code, [0x60, 0x80, 0x60, 0x40, 0x52]);
+/// ```
+pub fn assemble(source: &str) -> Result<Vec<u8>, AssembleError> {
+    let (tokens, macros) = tokenize(source)?;
+    let items = Parser {
+        macros: &macros,
+        items: Vec::new(),
+    }
+    .parse(tokens)?;
+    layout(&items)
+}
+
+/// A whitespace-separated word of the source, with its position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Token {
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+impl Token {
+    fn error(&self, kind: AssembleErrorKind) -> AssembleError {
+        self.error_at(0, kind)
+    }
+
+    fn error_at(&self, offset: usize, kind: AssembleErrorKind) -> AssembleError {
+        AssembleError {
+            line: self.line,
+            column: self.column + offset,
+            kind,
+        }
+    }
+}
+
+/// `#define`d macros by name, with their body tokens.
+type Macros = HashMap<String, Vec<Token>>;
+
+/// Splits the source into tokens and collects its `#define`s.
+fn tokenize(source: &str) -> Result<(Vec<Token>, Macros), AssembleError> {
+    let mut tokens = Vec::new();
+    let mut macros = HashMap::new();
+    for (index, line) in source.lines().enumerate() {
+        let code = [";", "//"]
+            .iter()
+            .filter_map(|marker| line.find(marker))
+            .min()
+            .map_or(line, |end| &line[..end]);
+        let mut words = split_words(code, index + 1);
+        let Some(first) = words.first() else {
+            continue;
+        };
+        if !first.text.starts_with('#') {
+            tokens.extend(words);
+            continue;
+        }
+        if first.text != "#define" || words.len() < 2 || !is_identifier(&words[1].text) {
+            return Err(first.error(AssembleErrorKind::InvalidDirective(code.trim().to_string())));
+        }
+        let body = words.split_off(2);
+        let name = &words[1];
+        if Opcode::from_name(&name.text).is_some() || name.text.eq_ignore_ascii_case("PUSH") {
+            return Err(name.error(AssembleErrorKind::ReservedName(name.text.clone())));
+        }
+        if macros.insert(name.text.clone(), body).is_some() {
+            return Err(name.error(AssembleErrorKind::DuplicateDefinition(name.text.clone())));
+        }
+    }
+    Ok((tokens, macros))
+}
+
+/// Splits a line into tokens at whitespace outside parentheses.
+fn split_words(line: &str, line_number: usize) -> Vec<Token> {
+    let mut words = Vec::new();
+    let mut current: Option<Token> = None;
+    let mut depth = 0usize;
+    for (index, c) in line.chars().enumerate() {
+        if c.is_whitespace() && depth == 0 {
+            words.extend(current.take());
+            continue;
+        }
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        current
+            .get_or_insert_with(|| Token {
+                text: String::new(),
+                line: line_number,
+                column: index + 1,
+            })
+            .text
+            .push(c);
+    }
+    words.extend(current);
+    words
+}
+
+fn is_identifier(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// One element of the program, in order.
+#[derive(Debug)]
+enum Item {
+    Label(String),
+    Opcode(Opcode),
+    /// A push of `size` bytes, or the smallest size that fits if `None`.
+    Push {
+        size: Option<usize>,
+        value: Expr,
+        token: Token,
+    },
+}
+
+/// Turns tokens into items, expanding macros.
+struct Parser<'a> {
+    macros: &'a Macros,
+    items: Vec<Item>,
+}
+
+impl<'a> Parser<'a> {
+    fn parse(mut self, tokens: Vec<Token>) -> Result<Vec<Item>, AssembleError> {
+        let mut labels = HashSet::new();
+        self.parse_tokens(&tokens, &mut Vec::new(), &mut labels)?;
+        Ok(self.items)
+    }
+
+    fn parse_tokens<'t>(
+        &mut self,
+        tokens: &'t [Token],
+        expanding: &mut Vec<&'t str>,
+        labels: &mut HashSet<String>,
+    ) -> Result<(), AssembleError>
+    where
+        'a: 't,
+    {
+        let mut tokens = tokens.iter();
+        while let Some(token) = tokens.next() {
+            let text = token.text.as_str();
+            if let Some(label) = text.strip_prefix('@').and_then(|t| t.strip_suffix(':')) {
+                if !is_identifier(label) {
+                    return Err(token.error(AssembleErrorKind::InvalidLabel(text.to_string())));
+                }
+                if !labels.insert(label.to_string()) {
+                    return Err(token.error(AssembleErrorKind::DuplicateLabel(label.to_string())));
+                }
+                self.items.push(Item::Label(label.to_string()));
+            } else if let Some(body) = self.macros.get_key_value(text) {
+                let (name, body) = body;
+                if expanding.contains(&name.as_str()) || expanding.len() >= MAX_MACRO_DEPTH {
+                    return Err(token.error(AssembleErrorKind::RecursiveMacro(name.clone())));
+                }
+                expanding.push(name);
+                self.parse_tokens(body, expanding, labels)?;
+                expanding.pop();
+            } else if text.starts_with(['@', '(', '~', '-']) || text.starts_with(char::is_numeric) {
+                self.push(None, token)?;
+            } else if text.eq_ignore_ascii_case("PUSH") {
+                let operand = tokens.next().ok_or_else(|| {
+                    token.error(AssembleErrorKind::MissingOperand(text.to_string()))
+                })?;
+                self.push(None, operand)?;
+            } else {
+                let opcode = Opcode::from_name(text).ok_or_else(|| {
+                    token.error(AssembleErrorKind::UnknownMnemonic(text.to_string()))
+                })?;
+                match opcode.immediate_size() {
+                    0 => self.items.push(Item::Opcode(opcode)),
+                    size => {
+                        let operand = tokens.next().ok_or_else(|| {
+                            token.error(AssembleErrorKind::MissingOperand(text.to_string()))
+                        })?;
+                        self.push(Some(size), operand)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, size: Option<usize>, token: &Token) -> Result<(), AssembleError> {
+        let value = ExprParser::new(token, self.macros, Vec::new()).parse()?;
+        self.items.push(Item::Push {
+            size,
+            value,
+            token: token.clone(),
+        });
+        Ok(())
+    }
+}
+
+/// A constant expression, with macros already inlined.
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(U256),
+    Label(String, Token),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn has_labels(&self) -> bool {
+        match self {
+            Expr::Literal(_) => false,
+            Expr::Label(..) => true,
+            Expr::Negate(expr) | Expr::Not(expr) => expr.has_labels(),
+            Expr::Binary(_, left, right) => left.has_labels() || right.has_labels(),
+        }
+    }
+
+    /// Evaluates the expression; `token` locates errors that have no better position.
+    fn eval(&self, labels: &HashMap<String, usize>, token: &Token) -> Result<U256, AssembleError> {
+        Ok(match self {
+            Expr::Literal(value) => *value,
+            Expr::Label(name, at) => labels
+                .get(name)
+                .map(|pc| U256::from(*pc))
+                .ok_or_else(|| at.error(AssembleErrorKind::UnknownLabel(name.clone())))?,
+            Expr::Negate(expr) => expr.eval(labels, token)?.wrapping_neg(),
+            Expr::Not(expr) => !expr.eval(labels, token)?,
+            Expr::Binary(op, left, right) => {
+                let (left, right) = (left.eval(labels, token)?, right.eval(labels, token)?);
+                let shift = usize::try_from(right).unwrap_or(usize::MAX);
+                match op {
+                    '+' => left.wrapping_add(right),
+                    '-' => left.wrapping_sub(right),
+                    '*' => left.wrapping_mul(right),
+                    '/' | '%' if right.is_zero() => {
+                        return Err(token.error(AssembleErrorKind::DivisionByZero))
+                    }
+                    '/' => left / right,
+                    '%' => left % right,
+                    '<' if shift >= 256 => U256::ZERO,
+                    '>' if shift >= 256 => U256::ZERO,
+                    '<' => left << shift,
+                    '>' => left >> shift,
+                    '&' => left & right,
+                    '|' => left | right,
+                    _ => left ^ right,
+                }
+            }
+        })
+    }
+}
+
+/// Recursive-descent parser for one operand, lowest precedence first:
+/// `|`, `^`, `&`, shifts, `+ -`, `* / %`, then unary `- ~`.
+struct ExprParser<'a> {
+    token: &'a Token,
+    chars: Vec<char>,
+    position: usize,
+    macros: &'a Macros,
+    /// Macros being inlined, to detect recursion.
+    expanding: Vec<String>,
+}
+
+impl<'a> ExprParser<'a> {
+    fn new(token: &'a Token, macros: &'a Macros, expanding: Vec<String>) -> Self {
+        Self {
+            token,
+            chars: token.text.chars().collect(),
+            position: 0,
+            macros,
+            expanding,
+        }
+    }
+
+    fn parse(mut self) -> Result<Expr, AssembleError> {
+        let expr = self.binary(0)?;
+        self.skip_whitespace();
+        if let Some(c) = self.peek() {
+            return Err(self.error(format!("unexpected `{c}`")));
+        }
+        Ok(expr)
+    }
+
+    fn error(&self, reason: String) -> AssembleError {
+        self.token
+            .error_at(self.position, AssembleErrorKind::InvalidExpression(reason))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    /// Parses binary operators of precedence `level` and higher.
+    fn binary(&mut self, level: usize) -> Result<Expr, AssembleError> {
+        const LEVELS: [&[&str]; 6] = [
+            &["|"],
+            &["^"],
+            &["&"],
+            &["<<", ">>"],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+        let Some(operators) = LEVELS.get(level) else {
+            return self.unary();
+        };
+        let mut left = self.binary(level + 1)?;
+        loop {
+            self.skip_whitespace();
+            let rest: String = self.chars[self.position..].iter().collect();
+            let Some(operator) = operators.iter().find(|op| rest.starts_with(**op)) else {
+                return Ok(left);
+            };
+            self.position += operator.len();
+            let right = self.binary(level + 1)?;
+            let op = operator.chars().next().expect("operators are not empty");
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, AssembleError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('-') => {
+                self.position += 1;
+                Ok(Expr::Negate(Box::new(self.unary()?)))
+            }
+            Some('~') => {
+                self.position += 1;
+                Ok(Expr::Not(Box::new(self.unary()?)))
+            }
+            Some('(') => {
+                self.position += 1;
+                let expr = self.binary(0)?;
+                self.skip_whitespace();
+                if self.peek() != Some(')') {
+                    return Err(self.error("expected `)`".to_string()));
+                }
+                self.position += 1;
+                Ok(expr)
+            }
+            Some('@') => {
+                let start = self.position;
+                self.position += 1;
+                let name = self.word();
+                if name.is_empty() {
+                    return Err(self.error("expected a label name".to_string()));
+                }
+                let at = Token {
+                    column: self.token.column + start,
+                    ..self.token.clone()
+                };
+                Ok(Expr::Label(name, at))
+            }
+            Some(c) if c.is_ascii_digit() => self.literal(),
+            Some(c) if c.is_ascii_alphabetic() || c == '_' => self.constant(),
+            Some(c) => Err(self.error(format!("unexpected `{c}`"))),
+            None => Err(self.error("expected a value".to_string())),
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let start = self.position;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+
+    fn literal(&mut self) -> Result<Expr, AssembleError> {
+        let start = self.position;
+        let word = self.word();
+        let parsed = match word.strip_prefix("0x").or_else(|| word.strip_prefix("0X")) {
+            Some(digits) => U256::from_str_radix(digits, 16),
+            None => U256::from_str_radix(&word, 10),
+        };
+        parsed.map(Expr::Literal).map_err(|_| {
+            self.token.error_at(
+                start,
+                AssembleErrorKind::InvalidExpression(format!("invalid literal `{word}`")),
+            )
+        })
+    }
+
+    /// Inlines a `#define`d constant by parsing its body as an expression.
+    fn constant(&mut self) -> Result<Expr, AssembleError> {
+        let start = self.position;
+        let name = self.word();
+        let error = |kind| self.token.error_at(start, kind);
+        let Some(body) = self.macros.get(&name) else {
+            return Err(error(AssembleErrorKind::UnknownConstant(name)));
+        };
+        if self.expanding.contains(&name) || self.expanding.len() >= MAX_MACRO_DEPTH {
+            return Err(error(AssembleErrorKind::RecursiveMacro(name)));
+        }
+        let Some(first) = body.first() else {
+            return Err(error(AssembleErrorKind::InvalidExpression(format!(
+                "`{name}` is empty"
+            ))));
+        };
+        let joined = Token {
+            text: body
+                .iter()
+                .map(|token| token.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+            ..first.clone()
+        };
+        let mut expanding = self.expanding.clone();
+        expanding.push(name);
+        ExprParser::new(&joined, self.macros, expanding).parse()
+    }
+}
+
+/// Returns the number of bytes needed to push `value`, at least one.
+fn push_size(value: U256) -> usize {
+    value.byte_len().max(1)
+}
+
+/// Sizes the pushes, resolves labels and emits the bytecode.
+fn layout(items: &[Item]) -> Result<Vec<u8>, AssembleError> {
+    let no_labels = HashMap::new();
+    let mut sizes = items
+        .iter()
+        .map(|item| match item {
+            Item::Push {
+                size: None,
+                value,
+                token,
+            } if !value.has_labels() => value.eval(&no_labels, token).map(push_size),
+            Item::Push { size, .. } => Ok(size.unwrap_or(1)),
+            Item::Label(_) | Item::Opcode(_) => Ok(0),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Growing a push can move labels and grow other pushes, but sizes only ever grow
+    let labels = loop {
+        let mut labels = HashMap::new();
+        let mut pc = 0;
+        for (item, size) in items.iter().zip(&sizes) {
+            match item {
+                Item::Label(name) => {
+                    labels.insert(name.clone(), pc);
+                }
+                Item::Opcode(_) => pc += 1,
+                Item::Push { .. } => pc += 1 + size,
+            }
+        }
+        let mut changed = false;
+        for (item, size) in items.iter().zip(sizes.iter_mut()) {
+            if let Item::Push {
+                size: None,
+                value,
+                token,
+            } = item
+            {
+                let needed = push_size(value.eval(&labels, token)?);
+                if needed > *size {
+                    *size = needed;
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break labels;
+        }
+    };
+
+    let mut code = Vec::new();
+    for (item, size) in items.iter().zip(sizes) {
+        match item {
+            Item::Label(_) => {}
+            Item::Opcode(opcode) => code.push(opcode.to_byte()),
+            Item::Push { value, token, .. } => {
+                let value = value.eval(&labels, token)?;
+                if value.byte_len() > size {
+                    return Err(token.error(AssembleErrorKind::ValueTooLarge { size }));
+                }
+                code.push(Opcode::Push1.to_byte() + size as u8 - 1);
+                code.extend_from_slice(&value.to_be_bytes::<32>()[32 - size..]);
+            }
+        }
+    }
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::disassembler::disassemble;
+
+    fn error(source: &str) -> (usize, usize, AssembleErrorKind) {
+        let error = assemble(source).unwrap_err();
+        (error.line, error.column, error.kind)
+    }
+
+    #[test]
+    fn test_labels_and_macros() {
+        let source = "
+            #define COUNT 3             ; constants and macros
+            #define DECREMENT PUSH1 1 SWAP1 SUB
+
+                PUSH COUNT
+            @loop:
+                JUMPDEST
+                DECREMENT
+                DUP1 PUSH @loop JUMPI   // jump back while non-zero
+                PUSH2 (COUNT * 0x100 + 1) POP
+                @end STOP
+            @end:
+        ";
+        let code = assemble(source).unwrap();
+        assert_eq!(
+            disassemble(&code).to_string(),
+            "0x0000: PUSH1 0x03\n\
+             0x0002: JUMPDEST\n\
+             0x0003: PUSH1 0x01\n\
+             0x0005: SWAP1\n\
+             0x0006: SUB\n\
+             0x0007: DUP1\n\
+             0x0008: PUSH1 0x02\n\
+             0x000a: JUMPI\n\
+             0x000b: PUSH2 0x0301\n\
+             0x000e: POP\n\
+             0x000f: PUSH1 0x12\n\
+             0x0011: STOP\n"
+        );
+    }
+
+    #[test]
+    fn test_push_sizing() {
+        // A forward label past 255 bytes needs two bytes, moving itself further
+        let mut source = "PUSH @end\n".to_string();
+        source.push_str(&"JUMPDEST\n".repeat(254));
+        source.push_str("@end: STOP");
+        let code = assemble(&source).unwrap();
+        assert_eq!(&code[..3], [0x61, 0x01, 0x01]);
+        assert_eq!(code.len(), 0x102);
+
+        let code = assemble("PUSH 0 PUSH 0x1234 PUSH (1 << 255) PUSH -1 PUSH (~0 ^ 0xff)").unwrap();
+        assert_eq!(&code[..5], [0x60, 0x00, 0x61, 0x12, 0x34]);
+        assert_eq!(&code[5..8], [0x7f, 0x80, 0x00]);
+        assert_eq!(&code[38..40], [0x7f, 0xff]);
+        assert_eq!(&code[70..73], [0xff, 0x7f, 0xff]);
+        assert_eq!(code.last(), Some(&0x00));
+        assert_eq!(code.len(), 104);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            error("PUSH1 1\n  ADD FOO"),
+            (2, 7, AssembleErrorKind::UnknownMnemonic("FOO".to_string()))
+        );
+        assert_eq!(
+            error("PUSH @nowhere"),
+            (1, 6, AssembleErrorKind::UnknownLabel("nowhere".to_string()))
+        );
+        assert_eq!(
+            error("@a: @a:"),
+            (1, 5, AssembleErrorKind::DuplicateLabel("a".to_string()))
+        );
+        assert_eq!(
+            error("PUSH0 @1a:"),
+            (1, 7, AssembleErrorKind::InvalidLabel("@1a:".to_string()))
+        );
+        assert_eq!(
+            error("#define add PUSH1 1"),
+            (1, 9, AssembleErrorKind::ReservedName("add".to_string()))
+        );
+        assert_eq!(
+            error("PUSH1 0x100"),
+            (1, 7, AssembleErrorKind::ValueTooLarge { size: 1 })
+        );
+        assert_eq!(
+            error("PUSH2 (1 / 0)"),
+            (1, 7, AssembleErrorKind::DivisionByZero)
+        );
+        assert_eq!(
+            error("#define A B\n#define B A\nPUSH A"),
+            (2, 11, AssembleErrorKind::RecursiveMacro("A".to_string()))
+        );
+        assert_eq!(
+            error("PUSH2"),
+            (1, 1, AssembleErrorKind::MissingOperand("PUSH2".to_string()))
+        );
+        assert_eq!(
+            error("#include foo"),
+            (
+                1,
+                1,
+                AssembleErrorKind::InvalidDirective("#include foo".to_string())
+            )
+        );
+        assert_eq!(
+            assemble("  BAR").unwrap_err().to_string(),
+            "line 1, column 3: unknown mnemonic `BAR`"
+        );
+    }
+}
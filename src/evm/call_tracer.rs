@@ -0,0 +1,398 @@
+//! Call Tracer
+//!
+//! Builds the tree of message calls a transaction makes, in the JSON shape of geth's
+//! `callTracer`: each call with its type, addresses, value, gas, input and output, and
+//! the calls it made in turn.
+//!
+//! # Design
+//! - An [`Inspector`]: `call`/`create` open a frame, `call_end`/`create_end` close it and
+//!   attach it to its parent, so the tree follows the interpreter's call stack
+//! - The root is the transaction's own message; [`CallTracer::transact`] sets its gas to
+//!   the transaction's gas limit and its gas used to the transaction's, intrinsic gas
+//!   included, as geth does
+//! - `SELFDESTRUCT` appears as a child of the destructed contract's frame, sending its
+//!   balance to the beneficiary
+//! - As in geth, failed frames keep their output only when they revert, failed creations
+//!   have no `to`, and logs of failed frames and their children are dropped
+//! - `error` is the frame's [`ExitReason`] as geth words it, e.g. `execution reverted`
+//!
+//! # References
+//! - [geth built-in tracers](https://geth.ethereum.org/docs/developers/evm-tracing/built-in-tracers)
+
+use serde::Serialize;
+
+use super::eip3155::serialize_quantity;
+use super::env::TxEnv;
+use super::execution::{decode_revert_reason, Evm, ExecutionResult, ExitReason, TransactionError};
+use super::frame::{CallScheme, CreateScheme};
+use super::inspector::{CallInputs, CallOutcome, CreateInputs, CreateOutcome, Inspector};
+use super::opcodes::Opcode;
+use crate::types::{Address, Bytes, Log, B256, U256};
+
+/// How a frame was entered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CallType {
+    Call,
+    Callcode,
+    Delegatecall,
+    Staticcall,
+    Create,
+    Create2,
+    Selfdestruct,
+}
+
+/// A log emitted by a frame.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CallLog {
+    pub address: Address,
+    pub topics: Vec<B256>,
+    pub data: Bytes,
+    /// Number of the frame's calls made before the log.
+    #[serde(serialize_with = "serialize_quantity")]
+    pub position: u64,
+}
+
+/// One message call and the calls it made.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+    #[serde(rename = "type")]
+    pub call_type: CallType,
+    pub from: Address,
+    /// Callee or created contract; `None` for failed creations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<Address>,
+    /// Value sent; `None` for `DELEGATECALL` and `STATICCALL`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<U256>,
+    #[serde(serialize_with = "serialize_quantity")]
+    pub gas: u64,
+    #[serde(serialize_with = "serialize_quantity")]
+    pub gas_used: u64,
+    /// Calldata, or init code for creations.
+    pub input: Bytes,
+    /// Return data, or deployed code for creations.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<Bytes>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub calls: Vec<CallFrame>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub logs: Vec<CallLog>,
+}
+
+impl CallFrame {
+    fn new(call_type: CallType, from: Address, to: Option<Address>, gas: u64) -> Self {
+        Self {
+            call_type,
+            from,
+            to,
+            value: None,
+            gas,
+            gas_used: 0,
+            input: Bytes::new(),
+            output: None,
+            error: None,
+            revert_reason: None,
+            calls: Vec::new(),
+            logs: Vec::new(),
+        }
+    }
+
+    /// Records how the frame ended.
+    fn finish(&mut self, reason: ExitReason, output: &Bytes, gas_remaining: u64) {
+        self.gas_used = self.gas.saturating_sub(gas_remaining);
+        if reason.is_success() {
+            self.output = Some(output.clone());
+            return;
+        }
+        self.error = error_message(reason);
+        self.clear_logs();
+        if reason.is_revert() {
+            self.revert_reason = decode_revert_reason(output);
+            self.output = Some(output.clone());
+        }
+    }
+
+    fn clear_logs(&mut self) {
+        self.logs.clear();
+        for call in &mut self.calls {
+            call.clear_logs();
+        }
+    }
+}
+
+/// Collects the call tree of a transaction.
+#[derive(Debug, Clone, Default)]
+pub struct CallTracer {
+    with_logs: bool,
+    /// Frames in progress, outermost first.
+    stack: Vec<CallFrame>,
+    root: Option<CallFrame>,
+}
+
+impl CallTracer {
+    /// Creates a tracer that leaves out logs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the logs each frame emits.
+    pub fn with_logs(mut self) -> Self {
+        self.with_logs = true;
+        self
+    }
+
+    /// Executes a transaction to completion, tracing its calls.
+    ///
+    /// # Errors
+    /// Returns a [`TransactionError`] if the transaction is invalid.
+    pub fn transact(
+        &mut self,
+        evm: &mut Evm,
+        tx: TxEnv,
+    ) -> Result<ExecutionResult, TransactionError> {
+        self.stack.clear();
+        self.root = None;
+        let gas_limit = tx.gas_limit;
+        let result = evm.inspect_transact(tx, self)?;
+        if let Some(root) = &mut self.root {
+            root.gas = gas_limit;
+            root.gas_used = result.gas.gas_used;
+        }
+        Ok(result)
+    }
+
+    /// Returns the root of the call tree, once a transaction finished.
+    pub fn root(&self) -> Option<&CallFrame> {
+        self.root.as_ref()
+    }
+
+    /// Serializes the call tree as pretty-printed JSON, `null` if nothing was traced.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.root).expect("call frames always serialize")
+    }
+
+    /// Closes the innermost frame and attaches it to its parent, or makes it the root.
+    fn close(&mut self, update: impl FnOnce(&mut CallFrame)) {
+        let Some(mut frame) = self.stack.pop() else {
+            return;
+        };
+        update(&mut frame);
+        match self.stack.last_mut() {
+            Some(parent) => parent.calls.push(frame),
+            None => self.root = Some(frame),
+        }
+    }
+}
+
+impl Inspector for CallTracer {
+    fn call(&mut self, _evm: &Evm, inputs: &CallInputs) -> Option<CallOutcome> {
+        let context = &inputs.context;
+        let (call_type, value) = match inputs.scheme {
+            CallScheme::Call => (CallType::Call, Some(context.value)),
+            CallScheme::Callcode => (CallType::Callcode, Some(context.value)),
+            CallScheme::Delegatecall => (CallType::Delegatecall, None),
+            CallScheme::Staticcall => (CallType::Staticcall, None),
+        };
+        let mut frame = CallFrame::new(
+            call_type,
+            context.caller,
+            Some(context.address),
+            inputs.gas_limit,
+        );
+        frame.value = value;
+        frame.input = context.input.clone();
+        self.stack.push(frame);
+        None
+    }
+
+    fn call_end(&mut self, _evm: &Evm, _inputs: &CallInputs, outcome: &mut CallOutcome) {
+        self.close(|frame| frame.finish(outcome.reason, &outcome.output, outcome.gas_remaining));
+    }
+
+    fn create(&mut self, _evm: &Evm, inputs: &CreateInputs) -> Option<CreateOutcome> {
+        let call_type = match inputs.scheme {
+            CreateScheme::Create => CallType::Create,
+            CreateScheme::Create2 { .. } => CallType::Create2,
+        };
+        let mut frame = CallFrame::new(call_type, inputs.caller, None, inputs.gas_limit);
+        frame.value = Some(inputs.value);
+        frame.input = inputs.init_code.clone();
+        self.stack.push(frame);
+        None
+    }
+
+    fn create_end(&mut self, _evm: &Evm, _inputs: &CreateInputs, outcome: &mut CreateOutcome) {
+        self.close(|frame| {
+            frame.to = outcome.address;
+            frame.finish(outcome.reason, &outcome.output, outcome.gas_remaining);
+        });
+    }
+
+    fn log(&mut self, _evm: &Evm, log: &Log) {
+        if !self.with_logs {
+            return;
+        }
+        if let Some(frame) = self.stack.last_mut() {
+            frame.logs.push(CallLog {
+                address: log.address,
+                topics: log.topics.clone(),
+                data: log.data.clone(),
+                position: frame.calls.len() as u64,
+            });
+        }
+    }
+
+    fn selfdestruct(&mut self, _evm: &Evm, address: Address, target: Address, value: U256) {
+        if let Some(frame) = self.stack.last_mut() {
+            let mut destruct = CallFrame::new(CallType::Selfdestruct, address, Some(target), 0);
+            destruct.value = Some(value);
+            frame.calls.push(destruct);
+        }
+    }
+}
+
+/// Returns geth's error message for a frame that ended with `reason`, or `None` if it
+/// succeeded. Reasons geth has no error for are worded in the same style.
+fn error_message(reason: ExitReason) -> Option<String> {
+    let message = match reason {
+        ExitReason::Stop | ExitReason::Return | ExitReason::SelfDestruct => return None,
+        ExitReason::Revert => "execution reverted",
+        ExitReason::OutOfGas | ExitReason::MemoryLimit => "out of gas",
+        ExitReason::StackUnderflow => "stack underflow",
+        ExitReason::StackOverflow => "stack overflow",
+        ExitReason::InvalidOpcode(byte) => {
+            return Some(match Opcode::from_byte(byte) {
+                Some(opcode) => format!("invalid opcode: {opcode}"),
+                None => format!("invalid opcode: opcode {byte:#x} not defined"),
+            })
+        }
+        ExitReason::InvalidJump => "invalid jump destination",
+        ExitReason::StateChangeDuringStaticCall => "write protection",
+        ExitReason::StepLimit => "step limit reached",
+        ExitReason::ReturnDataOutOfBounds => "return data out of bounds",
+        ExitReason::CreateCollision => "contract address collision",
+        ExitReason::CodeStoreOutOfGas => "contract creation code storage out of gas",
+        ExitReason::CodeSizeLimit => "max code size exceeded",
+        ExitReason::InvalidCodePrefix => "invalid code: must not begin with 0xef",
+        ExitReason::CallTooDeep => "max call depth exceeded",
+        ExitReason::OutOfFunds => "insufficient balance for transfer",
+        ExitReason::NonceOverflow => "nonce uint64 overflow",
+        ExitReason::PrecompileFailure => "precompile failed",
+    };
+    Some(message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::assembler::assemble;
+    use crate::evm::storage::Account;
+    use crate::evm::test_utils::{self, CALLER, CONTRACT};
+
+    const REVERTER: Address = Address::with_last_byte(0xc1);
+    const EMPTY: Address = Address::with_last_byte(0xc2);
+
+    fn trace(tracer: &mut CallTracer) -> ExecutionResult {
+        // Log, send 7 wei to a callee that logs and reverts with 42, static-call an
+        // account without code, create an empty contract, and self-destruct
+        let contract = assemble(
+            "PUSH0 PUSH0 LOG0
+             PUSH0 PUSH0 PUSH0 PUSH0 PUSH1 7 PUSH1 0xc1 GAS CALL POP
+             PUSH0 PUSH0 PUSH0 PUSH0 PUSH1 0xc2 GAS STATICCALL POP
+             PUSH0 PUSH0 PUSH0 CREATE POP
+             CALLER SELFDESTRUCT",
+        )
+        .unwrap();
+        let reverter =
+            assemble("PUSH0 PUSH0 LOG0 PUSH1 0x2a PUSH0 MSTORE PUSH1 32 PUSH0 REVERT").unwrap();
+        let mut account = Account::with_code(contract.into());
+        account.balance = U256::from(10);
+        let mut state = test_utils::state(account);
+        state.insert_account(REVERTER, Account::with_code(reverter.into()));
+        let mut evm = Evm::new().with_state(state);
+        let tx = TxEnv {
+            data: Bytes::from_static(&[0xab]),
+            ..test_utils::call()
+        };
+        tracer.transact(&mut evm, tx).unwrap()
+    }
+
+    #[test]
+    fn test_call_tree() {
+        let mut tracer = CallTracer::new().with_logs();
+        let result = trace(&mut tracer);
+        assert!(result.is_success());
+        let root = tracer.root().unwrap();
+        assert_eq!((root.gas, root.gas_used), (100_000, result.gas.gas_used));
+
+        let types: Vec<_> = root.calls.iter().map(|call| call.call_type).collect();
+        assert_eq!(
+            types,
+            [
+                CallType::Call,
+                CallType::Staticcall,
+                CallType::Create,
+                CallType::Selfdestruct
+            ]
+        );
+        let reverted = &root.calls[0];
+        assert_eq!(reverted.error.as_deref(), Some("execution reverted"));
+        assert_eq!(reverted.output.as_ref().unwrap()[31], 42);
+        assert!(reverted.logs.is_empty());
+        assert!(reverted.gas_used > 0 && reverted.gas_used < reverted.gas);
+        assert_eq!(root.calls[1].to, Some(EMPTY));
+        let created = &root.calls[2];
+        assert_eq!(created.to, Some(CONTRACT.create(0)));
+        let destruct = &root.calls[3];
+        assert_eq!(
+            (destruct.from, destruct.to, destruct.value),
+            (CONTRACT, Some(CALLER), Some(U256::from(10)))
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&tracer.to_json()).unwrap();
+        assert_eq!(json["type"], "CALL");
+        assert_eq!(json["gas"], "0x186a0");
+        assert_eq!(json["input"], "0xab");
+        assert_eq!(json["value"], "0x0");
+        assert_eq!(json["logs"][0]["position"], "0x0");
+        assert_eq!(json["calls"][0]["value"], "0x7");
+        assert!(json["calls"][0].get("logs").is_none());
+        assert!(json["calls"][1].get("value").is_none());
+        assert_eq!(json["calls"][1]["output"], "0x");
+        assert_eq!(json["calls"][3]["type"], "SELFDESTRUCT");
+    }
+
+    #[test]
+    fn test_without_logs() {
+        let mut tracer = CallTracer::new();
+        trace(&mut tracer);
+        assert!(tracer.root().unwrap().logs.is_empty());
+    }
+
+    #[test]
+    fn test_geth_error_messages() {
+        assert_eq!(error_message(ExitReason::Return), None);
+        let messages = [
+            (ExitReason::OutOfGas, "out of gas"),
+            (ExitReason::InvalidOpcode(0xfe), "invalid opcode: INVALID"),
+            (
+                ExitReason::InvalidOpcode(0x0c),
+                "invalid opcode: opcode 0xc not defined",
+            ),
+            (
+                ExitReason::CodeStoreOutOfGas,
+                "contract creation code storage out of gas",
+            ),
+            (ExitReason::OutOfFunds, "insufficient balance for transfer"),
+        ];
+        for (reason, message) in messages {
+            assert_eq!(error_message(reason).as_deref(), Some(message));
+        }
+    }
+}
@@ -0,0 +1,343 @@
+//! Control-Flow Graph
+//!
+//! Splits bytecode into basic blocks and connects them by the ways execution can move
+//! between them, for auditing code without its source. Exports to Graphviz DOT and JSON.
+//!
+//! # Design
+//! - Blocks start at the beginning of the code, at every `JUMPDEST`, and after every
+//!   instruction that ends one: jumps, halts and bytes that are not opcodes. Unlike
+//!   [`BlockGasTable`](super::analysis::BlockGasTable), instructions that only observe
+//!   gas do not split blocks
+//! - A jump is resolved when the instruction before it pushes the target, the pattern
+//!   compilers emit for static jumps. Targets computed any other way are listed as
+//!   unresolved; pushed targets that are not a `JUMPDEST` are listed as invalid
+//! - Code is decoded as the [disassembler](super::disassembler) decodes it, independent
+//!   of the fork, and a metadata trailer is not part of the graph
+//!
+//! # References
+//! - [Ethereum Yellow Paper, Section 9.4.3 (Jump Destination Validity)]
+
+use std::fmt::Write as _;
+
+use serde::Serialize;
+
+use super::analysis::JumpTable;
+use super::disassembler::{disassemble, Instruction};
+use super::opcodes::Opcode;
+use crate::types::U256;
+
+/// A straight-line run of instructions, entered only at its first.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BasicBlock {
+    /// Program counter of the first instruction.
+    pub start: usize,
+    /// Offset just past the last instruction.
+    pub end: usize,
+    pub instructions: Vec<Instruction>,
+}
+
+impl BasicBlock {
+    /// Returns the last instruction.
+    pub fn last(&self) -> &Instruction {
+        self.instructions.last().expect("blocks are never empty")
+    }
+}
+
+/// How execution moves along an edge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EdgeKind {
+    /// Into the next block: after an ordinary instruction, or a `JUMPI` not taken.
+    Fallthrough,
+    /// A `JUMP`.
+    Jump,
+    /// A `JUMPI` taken.
+    ConditionalJump,
+}
+
+impl EdgeKind {
+    fn label(self) -> &'static str {
+        match self {
+            EdgeKind::Fallthrough => "fallthrough",
+            EdgeKind::Jump => "jump",
+            EdgeKind::ConditionalJump => "conditional jump",
+        }
+    }
+}
+
+/// An edge between the blocks starting at `from` and `to`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// A jump to a pushed target that is not a valid jump destination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct InvalidJump {
+    /// Program counter of the `JUMP` or `JUMPI`.
+    pub pc: usize,
+    pub target: U256,
+}
+
+/// The control-flow graph of a piece of code.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cfg {
+    /// Blocks in code order.
+    pub blocks: Vec<BasicBlock>,
+    /// Edges by source block, jumps before fallthroughs.
+    pub edges: Vec<Edge>,
+    /// Program counters of jumps whose target is not a pushed constant.
+    pub unresolved_jumps: Vec<usize>,
+    pub invalid_jumps: Vec<InvalidJump>,
+}
+
+impl Cfg {
+    /// Builds the graph of `code`.
+    pub fn build(code: &[u8]) -> Self {
+        let mut cfg = Self::default();
+        let mut current: Vec<Instruction> = Vec::new();
+        for instruction in disassemble(code).instructions {
+            if instruction.opcode == Some(Opcode::Jumpdest) && !current.is_empty() {
+                cfg.push_block(std::mem::take(&mut current));
+            }
+            let ends_block = Self::ends_block(instruction.opcode);
+            current.push(instruction);
+            if ends_block {
+                cfg.push_block(std::mem::take(&mut current));
+            }
+        }
+        if !current.is_empty() {
+            cfg.push_block(current);
+        }
+
+        let jump_table = JumpTable::analyze(code);
+        for (index, block) in cfg.blocks.iter().enumerate() {
+            let last = block.last();
+            let falls_through = match last.opcode {
+                Some(opcode @ (Opcode::Jump | Opcode::Jumpi)) => {
+                    let kind = if opcode == Opcode::Jump {
+                        EdgeKind::Jump
+                    } else {
+                        EdgeKind::ConditionalJump
+                    };
+                    match pushed_target(block) {
+                        None => cfg.unresolved_jumps.push(last.pc),
+                        Some(target) => match usize::try_from(target) {
+                            Ok(to) if jump_table.is_valid(to) && cfg.block_index(to).is_some() => {
+                                cfg.edges.push(Edge {
+                                    from: block.start,
+                                    to,
+                                    kind,
+                                });
+                            }
+                            _ => cfg.invalid_jumps.push(InvalidJump {
+                                pc: last.pc,
+                                target,
+                            }),
+                        },
+                    }
+                    opcode == Opcode::Jumpi
+                }
+                opcode => !Self::ends_block(opcode),
+            };
+            if let Some(next) = cfg.blocks.get(index + 1).filter(|_| falls_through) {
+                cfg.edges.push(Edge {
+                    from: block.start,
+                    to: next.start,
+                    kind: EdgeKind::Fallthrough,
+                });
+            }
+        }
+        cfg
+    }
+
+    /// Returns `true` if an instruction with `opcode`, `None` for bytes that are not
+    /// opcodes, is the last of its block.
+    pub fn ends_block(opcode: Option<Opcode>) -> bool {
+        matches!(
+            opcode,
+            None | Some(
+                Opcode::Stop
+                    | Opcode::Jump
+                    | Opcode::Jumpi
+                    | Opcode::Return
+                    | Opcode::Revert
+                    | Opcode::Invalid
+                    | Opcode::Selfdestruct
+            )
+        )
+    }
+
+    /// Returns the block starting at `pc`, if one does.
+    pub fn block(&self, pc: usize) -> Option<&BasicBlock> {
+        self.block_index(pc).map(|index| &self.blocks[index])
+    }
+
+    /// Returns the edges leaving the block starting at `pc`.
+    pub fn successors(&self, pc: usize) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |edge| edge.from == pc)
+    }
+
+    /// Returns the edges entering the block starting at `pc`.
+    pub fn predecessors(&self, pc: usize) -> impl Iterator<Item = &Edge> {
+        self.edges.iter().filter(move |edge| edge.to == pc)
+    }
+
+    /// Serializes the graph as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("graphs always serialize")
+    }
+
+    /// Renders the graph in Graphviz DOT. Blocks ending in an unresolved or invalid jump
+    /// are drawn red.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph cfg {\n");
+        dot.push_str("    node [shape=box fontname=\"monospace\"];\n");
+        for block in &self.blocks {
+            let mut label = String::new();
+            for instruction in &block.instructions {
+                let _ = write!(label, "{instruction}\\l");
+            }
+            let pc = block.last().pc;
+            let unresolved = self.unresolved_jumps.contains(&pc)
+                || self.invalid_jumps.iter().any(|jump| jump.pc == pc);
+            let color = if unresolved { " color=red" } else { "" };
+            let _ = writeln!(dot, "    b{} [label=\"{label}\"{color}];", block.start);
+        }
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Fallthrough => " style=dashed",
+                EdgeKind::Jump | EdgeKind::ConditionalJump => "",
+            };
+            let _ = writeln!(
+                dot,
+                "    b{} -> b{} [label=\"{}\"{style}];",
+                edge.from,
+                edge.to,
+                edge.kind.label()
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn push_block(&mut self, instructions: Vec<Instruction>) {
+        let (first, last) = (&instructions[0], &instructions[instructions.len() - 1]);
+        self.blocks.push(BasicBlock {
+            start: first.pc,
+            end: last.pc + last.size(),
+            instructions,
+        });
+    }
+
+    /// Returns the position in `blocks` of the block starting at `pc`.
+    pub(crate) fn block_index(&self, pc: usize) -> Option<usize> {
+        self.blocks
+            .binary_search_by_key(&pc, |block| block.start)
+            .ok()
+    }
+}
+
+/// Returns the target of the block's final jump if the instruction before it pushes it.
+fn pushed_target(block: &BasicBlock) -> Option<U256> {
+    let [.., push, _jump] = block.instructions.as_slice() else {
+        return None;
+    };
+    let opcode = push.opcode?;
+    let is_push = opcode == Opcode::Push0 || opcode.immediate_size() > 0;
+    // A truncated push reads the missing bytes as zeros
+    let mut immediate = push.immediate.clone();
+    immediate.resize(opcode.immediate_size(), 0);
+    is_push.then(|| U256::from_be_slice(&immediate))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::assembler::assemble;
+
+    #[test]
+    fn test_blocks_and_edges() {
+        let code = assemble(
+            "PUSH0 CALLDATALOAD PUSH @set JUMPI
+             PUSH1 1 PUSH @done JUMP
+             @set: JUMPDEST PUSH1 2
+             @done: JUMPDEST PUSH0 SSTORE STOP",
+        )
+        .unwrap();
+        let cfg = Cfg::build(&code);
+        let starts: Vec<_> = cfg.blocks.iter().map(|block| block.start).collect();
+        assert_eq!(starts, [0, 5, 10, 13]);
+        assert_eq!(cfg.blocks[1].end, 10);
+        assert_eq!(cfg.block(10).unwrap().instructions.len(), 2);
+        assert_eq!(
+            cfg.edges,
+            [
+                Edge {
+                    from: 0,
+                    to: 10,
+                    kind: EdgeKind::ConditionalJump
+                },
+                Edge {
+                    from: 0,
+                    to: 5,
+                    kind: EdgeKind::Fallthrough
+                },
+                Edge {
+                    from: 5,
+                    to: 13,
+                    kind: EdgeKind::Jump
+                },
+                Edge {
+                    from: 10,
+                    to: 13,
+                    kind: EdgeKind::Fallthrough
+                },
+            ]
+        );
+        assert_eq!(cfg.predecessors(13).count(), 2);
+        assert_eq!(cfg.successors(13).count(), 0);
+        assert!(cfg.unresolved_jumps.is_empty() && cfg.invalid_jumps.is_empty());
+    }
+
+    #[test]
+    fn test_unresolved_jumps_and_export() {
+        // A computed target, a pushed target inside PUSH data, then unreachable code
+        let code = assemble(
+            "PUSH1 2 PUSH1 2 ADD JUMP
+             PUSH1 0x5b PUSH1 7 JUMP
+             JUMPDEST STOP",
+        )
+        .unwrap();
+        let cfg = Cfg::build(&code);
+        assert_eq!(cfg.unresolved_jumps, [5]);
+        assert_eq!(
+            cfg.invalid_jumps,
+            [InvalidJump {
+                pc: 10,
+                target: U256::from(7)
+            }]
+        );
+        assert!(cfg.edges.is_empty());
+        assert_eq!(cfg.blocks.len(), 3);
+
+        let dot = cfg.to_dot();
+        assert!(dot.starts_with("digraph cfg {\n"));
+        assert!(dot.contains(
+            "    b0 [label=\"0x0000: PUSH1 0x02\\l0x0002: PUSH1 0x02\\l0x0004: ADD\\l0x0005: JUMP\\l\" color=red];\n"
+        ));
+        assert!(dot.contains("    b11 [label=\"0x000b: JUMPDEST\\l0x000c: STOP\\l\"];\n"));
+
+        let code = assemble("PUSH @end JUMP @end: JUMPDEST STOP").unwrap();
+        let dot = Cfg::build(&code).to_dot();
+        assert!(dot.contains("    b0 -> b3 [label=\"jump\"];\n"));
+
+        let json: serde_json::Value = serde_json::from_str(&cfg.to_json()).unwrap();
+        assert_eq!(json["unresolvedJumps"][0], 5);
+        assert_eq!(json["invalidJumps"][0]["target"], "0x7");
+        assert_eq!(json["blocks"][2]["instructions"][0]["opcode"], "JUMPDEST");
+    }
+}
@@ -0,0 +1,401 @@
+//! Bytecode Coverage
+//!
+//! Records which instructions ran and which way each `JUMPI` went, across any number of
+//! transactions, to find the paths a test suite never exercises. Reports list every
+//! instruction of the disassembly with its hit count, as JSON or in the lcov format
+//! coverage tools read.
+//!
+//! # Design
+//! - An [`Inspector`] recording instructions before they run, so an instruction that
+//!   halts still counts as executed; a `JUMPI` counts as taken if its condition is
+//!   non-zero, even if the destination then proves invalid
+//! - Code is keyed by its hash, not its address: the same contract deployed at a fresh
+//!   address in every test shares one record, and init code is kept apart from the
+//!   code it deploys. The addresses code ran at are kept for reference
+//! - Code that never ran can be registered with [`Coverage::add_code`] so it shows up
+//!   as uncovered; records from separate runs combine with [`Coverage::merge`]
+//! - In lcov reports, each code is a source file named by its hash, and line `n` is the
+//!   `n`-th line of its [`disassemble`] listing. Branch 0 of a `JUMPI` is the jump,
+//!   branch 1 the fallthrough
+//!
+//! # References
+//! - [lcov tracefile format](https://manpages.debian.org/unstable/lcov/geninfo.1.en.html#TRACEFILE_FORMAT)
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Write as _};
+
+use alloy_primitives::keccak256;
+use serde::Serialize;
+
+use super::disassembler::disassemble;
+use super::env::TxEnv;
+use super::execution::{Evm, ExecutionResult, TransactionError};
+use super::frame::Frame;
+use super::inspector::Inspector;
+use super::opcodes::Opcode;
+use crate::types::{Address, Bytes, B256};
+
+/// How often each direction of a `JUMPI` was taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BranchHits {
+    /// Times the condition was non-zero and the jump taken.
+    pub taken: u64,
+    /// Times the condition was zero and execution fell through.
+    pub not_taken: u64,
+}
+
+/// Execution counts of one piece of code.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CodeCoverage {
+    pub code: Bytes,
+    /// Addresses the code ran at.
+    pub addresses: BTreeSet<Address>,
+    /// Hit counts by program counter, for instructions that ran.
+    pub hits: BTreeMap<usize, u64>,
+    /// Directions of the `JUMPI`s that ran, by program counter.
+    pub branches: BTreeMap<usize, BranchHits>,
+}
+
+impl CodeCoverage {
+    fn new(code: Bytes) -> Self {
+        Self {
+            code,
+            ..Self::default()
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.addresses.extend(&other.addresses);
+        for (pc, hits) in &other.hits {
+            *self.hits.entry(*pc).or_default() += hits;
+        }
+        for (pc, branch) in &other.branches {
+            let entry = self.branches.entry(*pc).or_default();
+            entry.taken += branch.taken;
+            entry.not_taken += branch.not_taken;
+        }
+    }
+}
+
+/// Coverage of one instruction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstructionCoverage {
+    pub pc: usize,
+    /// The instruction as the disassembler lists it.
+    pub instruction: String,
+    pub hits: u64,
+    /// Directions taken, for a `JUMPI`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub branch: Option<BranchHits>,
+}
+
+/// Coverage of one piece of code, instruction by instruction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CodeReport {
+    pub code_hash: B256,
+    pub addresses: Vec<Address>,
+    /// Number of instructions, and how many of them ran.
+    pub instructions_found: usize,
+    pub instructions_hit: usize,
+    /// Number of `JUMPI` directions, two per `JUMPI`, and how many of them were taken.
+    pub branches_found: usize,
+    pub branches_hit: usize,
+    pub instructions: Vec<InstructionCoverage>,
+}
+
+/// A finished coverage report, one entry per code, in code hash order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CoverageReport {
+    pub codes: Vec<CodeReport>,
+}
+
+impl CoverageReport {
+    /// Serializes the report as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("coverage reports always serialize")
+    }
+
+    /// Renders the report as an lcov tracefile.
+    pub fn to_lcov(&self) -> String {
+        let mut out = String::new();
+        for code in &self.codes {
+            let _ = writeln!(out, "TN:");
+            let _ = writeln!(out, "SF:{}", code.code_hash);
+            for (line, instruction) in (1..).zip(&code.instructions) {
+                let _ = writeln!(out, "DA:{line},{}", instruction.hits);
+                if let Some(branch) = instruction.branch {
+                    let executed = instruction.hits > 0;
+                    for (index, count) in [branch.taken, branch.not_taken].into_iter().enumerate() {
+                        let count = if executed {
+                            count.to_string()
+                        } else {
+                            "-".into()
+                        };
+                        let _ = writeln!(out, "BRDA:{line},0,{index},{count}");
+                    }
+                }
+            }
+            let _ = writeln!(out, "LF:{}", code.instructions_found);
+            let _ = writeln!(out, "LH:{}", code.instructions_hit);
+            let _ = writeln!(out, "BRF:{}", code.branches_found);
+            let _ = writeln!(out, "BRH:{}", code.branches_hit);
+            let _ = writeln!(out, "end_of_record");
+        }
+        out
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    /// Renders a summary line per code, then the disassembly with hit counts in front;
+    /// instructions that never ran are marked `-`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for code in &self.codes {
+            writeln!(
+                f,
+                "{}: {}/{} instructions, {}/{} branches",
+                code.code_hash,
+                code.instructions_hit,
+                code.instructions_found,
+                code.branches_hit,
+                code.branches_found
+            )?;
+            for instruction in &code.instructions {
+                let hits = match instruction.hits {
+                    0 => "-".to_string(),
+                    hits => hits.to_string(),
+                };
+                write!(f, "{hits:>8}  {}", instruction.instruction)?;
+                if let Some(branch) = instruction.branch {
+                    write!(
+                        f,
+                        "  [taken {}, not taken {}]",
+                        branch.taken, branch.not_taken
+                    )?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Collects instruction and branch coverage over any number of transactions.
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    codes: BTreeMap<B256, CodeCoverage>,
+}
+
+impl Coverage {
+    /// Creates an empty record.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers code so that it is reported even if it never runs.
+    pub fn add_code(&mut self, code: Bytes) {
+        self.codes
+            .entry(keccak256(&code))
+            .or_insert_with(|| CodeCoverage::new(code));
+    }
+
+    /// Executes a transaction to completion, adding its coverage to the record.
+    ///
+    /// # Errors
+    /// Returns a [`TransactionError`] if the transaction is invalid.
+    pub fn transact(
+        &mut self,
+        evm: &mut Evm,
+        tx: TxEnv,
+    ) -> Result<ExecutionResult, TransactionError> {
+        evm.inspect_transact(tx, self)
+    }
+
+    /// Adds the coverage recorded by `other`, e.g. in another test run.
+    pub fn merge(&mut self, other: &Self) {
+        for (hash, coverage) in &other.codes {
+            self.codes
+                .entry(*hash)
+                .or_insert_with(|| CodeCoverage::new(coverage.code.clone()))
+                .merge(coverage);
+        }
+    }
+
+    /// Returns the coverage of the code with the given hash, if it was recorded.
+    pub fn code(&self, code_hash: &B256) -> Option<&CodeCoverage> {
+        self.codes.get(code_hash)
+    }
+
+    /// Returns the recorded codes by hash.
+    pub fn codes(&self) -> &BTreeMap<B256, CodeCoverage> {
+        &self.codes
+    }
+
+    /// Builds a report listing every instruction of every recorded code.
+    pub fn report(&self) -> CoverageReport {
+        let codes = self
+            .codes
+            .iter()
+            .map(|(hash, coverage)| {
+                let instructions: Vec<_> = disassemble(&coverage.code)
+                    .instructions
+                    .iter()
+                    .map(|instruction| InstructionCoverage {
+                        pc: instruction.pc,
+                        instruction: instruction.to_string(),
+                        hits: coverage.hits.get(&instruction.pc).copied().unwrap_or(0),
+                        branch: (instruction.opcode == Some(Opcode::Jumpi)).then(|| {
+                            coverage
+                                .branches
+                                .get(&instruction.pc)
+                                .copied()
+                                .unwrap_or_default()
+                        }),
+                    })
+                    .collect();
+                let branches = instructions.iter().filter_map(|entry| entry.branch);
+                CodeReport {
+                    code_hash: *hash,
+                    addresses: coverage.addresses.iter().copied().collect(),
+                    instructions_found: instructions.len(),
+                    instructions_hit: instructions.iter().filter(|entry| entry.hits > 0).count(),
+                    branches_found: 2 * branches.clone().count(),
+                    branches_hit: branches
+                        .map(|branch| {
+                            usize::from(branch.taken > 0) + usize::from(branch.not_taken > 0)
+                        })
+                        .sum(),
+                    instructions,
+                }
+            })
+            .collect();
+        CoverageReport { codes }
+    }
+}
+
+impl Inspector for Coverage {
+    fn step(&mut self, _evm: &Evm, frame: &Frame, opcode: Option<Opcode>) {
+        let code = frame.code();
+        let coverage = self
+            .codes
+            .entry(keccak256(code))
+            .or_insert_with(|| CodeCoverage::new(code.clone()));
+        coverage.addresses.insert(frame.context().code_address);
+        *coverage.hits.entry(frame.pc()).or_default() += 1;
+
+        if opcode == Some(Opcode::Jumpi) {
+            // Destination on top, condition below it
+            let stack = frame.stack().as_slice();
+            if let Some(condition) = stack.len().checked_sub(2).map(|index| stack[index]) {
+                let branch = coverage.branches.entry(frame.pc()).or_default();
+                if condition.is_zero() {
+                    branch.not_taken += 1;
+                } else {
+                    branch.taken += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::assembler::assemble;
+    use crate::evm::test_utils::{deploy, CONTRACT};
+
+    /// Returns 1 if the first calldata byte is non-zero, otherwise stops.
+    const CODE: &str = "PUSH0 CALLDATALOAD PUSH1 7 JUMPI STOP INVALID
+                        JUMPDEST PUSH1 1 PUSH0 MSTORE PUSH1 32 PUSH0 RETURN";
+
+    fn run(coverage: &mut Coverage, calldata: &[u8]) {
+        let (mut evm, tx) = deploy(assemble(CODE).unwrap());
+        let tx = TxEnv {
+            data: Bytes::copy_from_slice(calldata),
+            ..tx
+        };
+        coverage.transact(&mut evm, tx).unwrap();
+    }
+
+    #[test]
+    fn test_instruction_and_branch_coverage() {
+        let code = Bytes::from(assemble(CODE).unwrap());
+        let hash = keccak256(&code);
+
+        let mut coverage = Coverage::new();
+        run(&mut coverage, &[]);
+        let report = coverage.report();
+        let entry = &report.codes[0];
+        assert_eq!(entry.addresses, [CONTRACT]);
+        assert_eq!((entry.instructions_found, entry.instructions_hit), (13, 5));
+        assert_eq!((entry.branches_found, entry.branches_hit), (2, 1));
+        let jumpi = &entry.instructions[3];
+        assert_eq!(jumpi.instruction, "0x0004: JUMPI");
+        assert_eq!(
+            jumpi.branch,
+            Some(BranchHits {
+                taken: 0,
+                not_taken: 1
+            })
+        );
+
+        // A second run, recorded separately, takes the jump
+        let mut other = Coverage::new();
+        run(&mut other, &[1]);
+        coverage.merge(&other);
+        run(&mut coverage, &[1]);
+        let record = coverage.code(&hash).unwrap();
+        assert_eq!(
+            record.branches[&4],
+            BranchHits {
+                taken: 2,
+                not_taken: 1
+            }
+        );
+        assert_eq!(record.hits[&0], 3);
+        let report = coverage.report();
+        // Everything but INVALID ran
+        assert_eq!(report.codes[0].instructions_hit, 12);
+        assert_eq!(report.codes[0].branches_hit, 2);
+    }
+
+    #[test]
+    fn test_reports() {
+        let mut coverage = Coverage::new();
+        coverage.add_code(Bytes::from_static(&[0x60, 0x01, 0x57]));
+        run(&mut coverage, &[]);
+        let report = coverage.report();
+        assert_eq!(report.codes.len(), 2);
+
+        let lcov = report.to_lcov();
+        let unused = keccak256([0x60, 0x01, 0x57]);
+        let record = lcov
+            .split("end_of_record\n")
+            .find(|record| record.contains(&unused.to_string()))
+            .unwrap();
+        assert_eq!(
+            record,
+            format!(
+                "TN:\nSF:{unused}\nDA:1,0\nDA:2,0\nBRDA:2,0,0,-\nBRDA:2,0,1,-\n\
+                 LF:2\nLH:0\nBRF:2\nBRH:0\n"
+            )
+        );
+        assert!(lcov.contains("DA:4,1\nBRDA:4,0,0,0\nBRDA:4,0,1,1\n"));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        let code = json["codes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|code| code["instructionsHit"] == 5)
+            .unwrap();
+        assert_eq!(code["instructions"][3]["branch"]["notTaken"], 1);
+        assert!(code["instructions"][0].get("branch").is_none());
+
+        let text = report.to_string();
+        assert!(text.contains("       1  0x0004: JUMPI  [taken 0, not taken 1]"));
+        assert!(text.contains("       -  0x0006: INVALID"));
+    }
+}
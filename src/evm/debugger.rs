@@ -0,0 +1,389 @@
+//! Step Debugger
+//!
+//! Runs a transaction one instruction at a time, stopping at breakpoints, stepping over
+//! calls, and stepping backwards through snapshots of the interpreter.
+//!
+//! # Design
+//! - The debugger owns an [`Evm`] with a transaction in progress and gives read-only
+//!   access to it; stack, memory and gas are inspected through the current [`Frame`]
+//! - Breakpoints are checked before an instruction runs, so execution pauses with the
+//!   matching instruction up next. [`Debugger::resume`] always runs at least one
+//!   instruction, so resuming from a breakpoint does not stop on it again
+//! - Stepping over runs until execution is back at the starting depth or shallower,
+//!   stopping early at breakpoints inside the calls
+//! - Execution is deterministic, so the interpreter is only cloned every
+//!   [`SNAPSHOT_INTERVAL`] instructions; stepping back restores the latest clone before
+//!   the target and replays forward from it. Clones include the whole state, so only
+//!   those needed to step back [`DEFAULT_SNAPSHOT_LIMIT`] instructions are kept unless
+//!   configured otherwise
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use super::disassembler::{decode, Instruction};
+use super::env::TxEnv;
+use super::execution::{Evm, ExecutionResult, StepReport, TransactionError};
+use super::frame::Frame;
+use super::opcodes::Opcode;
+
+/// Number of instructions that can be stepped back unless configured otherwise.
+pub const DEFAULT_SNAPSHOT_LIMIT: usize = 10_000;
+
+/// Instructions between two snapshots; stepping back replays fewer than this many.
+pub const SNAPSHOT_INTERVAL: usize = 64;
+
+/// A condition under which execution pauses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Breakpoint {
+    /// The next instruction is at this program counter, in any frame.
+    Pc(usize),
+    /// The next instruction is this opcode.
+    Opcode(Opcode),
+    /// Execution moves to a frame at this call depth, by a call or by a return.
+    Depth(usize),
+}
+
+impl fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Breakpoint::Pc(pc) => write!(f, "pc 0x{pc:04x}"),
+            Breakpoint::Opcode(opcode) => write!(f, "opcode {opcode}"),
+            Breakpoint::Depth(depth) => write!(f, "depth {depth}"),
+        }
+    }
+}
+
+/// Why the debugger stopped running instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pause {
+    /// The requested step is done.
+    Step,
+    /// The breakpoint at this index matched.
+    Breakpoint(usize),
+    /// The transaction finished; its result is in [`Debugger::result`].
+    Finished,
+}
+
+/// An interactive, reversible run of one transaction.
+#[derive(Debug, Clone)]
+pub struct Debugger {
+    evm: Evm,
+    breakpoints: Vec<Breakpoint>,
+    /// Instructions executed since the transaction began.
+    position: usize,
+    /// Interpreter states by position, every [`SNAPSHOT_INTERVAL`] instructions, oldest
+    /// first. The oldest is at or before `position - history`.
+    snapshots: VecDeque<(usize, Evm)>,
+    /// Number of instructions that can be stepped back.
+    history: usize,
+    snapshot_limit: usize,
+}
+
+impl Debugger {
+    /// Begins `tx` on `evm`, paused before its first instruction.
+    ///
+    /// # Errors
+    /// Returns a [`TransactionError`] if the transaction is invalid.
+    pub fn new(mut evm: Evm, tx: TxEnv) -> Result<Self, TransactionError> {
+        evm.begin(tx)?;
+        Ok(Self {
+            evm,
+            breakpoints: Vec::new(),
+            position: 0,
+            snapshots: VecDeque::new(),
+            history: 0,
+            snapshot_limit: DEFAULT_SNAPSHOT_LIMIT,
+        })
+    }
+
+    /// Allows stepping back at most `limit` instructions; 0 disables stepping back.
+    pub fn with_snapshot_limit(mut self, limit: usize) -> Self {
+        self.snapshot_limit = limit;
+        self
+    }
+
+    /// Returns the interpreter.
+    pub fn evm(&self) -> &Evm {
+        &self.evm
+    }
+
+    /// Returns the executing frame, `None` once the transaction finished.
+    pub fn frame(&self) -> Option<&Frame> {
+        self.evm.current_frame()
+    }
+
+    /// Returns the call depth of the executing frame, 0 once the transaction finished.
+    pub fn depth(&self) -> usize {
+        self.frame().map_or(0, Frame::depth)
+    }
+
+    /// Returns the instruction that runs next, `None` once the transaction finished or
+    /// when the program counter is past the end of the code, where `STOP` is implied.
+    pub fn next_instruction(&self) -> Option<Instruction> {
+        let frame = self.frame()?;
+        decode(frame.code(), frame.pc())
+    }
+
+    /// Returns `true` once the transaction finished.
+    pub fn is_finished(&self) -> bool {
+        !self.evm.is_running()
+    }
+
+    /// Returns the result of the transaction once it finished.
+    pub fn result(&self) -> Option<&ExecutionResult> {
+        self.evm.result()
+    }
+
+    /// Returns the breakpoints, in the order they were added.
+    pub fn breakpoints(&self) -> &[Breakpoint] {
+        &self.breakpoints
+    }
+
+    /// Adds a breakpoint and returns its index.
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push(breakpoint);
+        self.breakpoints.len() - 1
+    }
+
+    /// Removes the breakpoint at `index`; later breakpoints move down by one.
+    pub fn remove_breakpoint(&mut self, index: usize) -> Option<Breakpoint> {
+        (index < self.breakpoints.len()).then(|| self.breakpoints.remove(index))
+    }
+
+    /// Returns the number of instructions that can be stepped back.
+    pub fn snapshots(&self) -> usize {
+        self.history
+    }
+
+    /// Executes the next instruction, ignoring breakpoints.
+    ///
+    /// Returns `None` if the transaction already finished.
+    pub fn step(&mut self) -> Option<StepReport> {
+        if self.is_finished() {
+            return None;
+        }
+        if self.snapshot_limit > 0 {
+            let taken = self.snapshots.back().map(|(position, _)| *position);
+            if self.position.is_multiple_of(SNAPSHOT_INTERVAL) && taken != Some(self.position) {
+                self.snapshots.push_back((self.position, self.evm.clone()));
+            }
+            self.history = (self.history + 1).min(self.snapshot_limit);
+        }
+        let report = self.evm.step();
+        self.position += 1;
+        // The second oldest snapshot is enough once it reaches back as far as the history
+        let earliest = self.position - self.history;
+        while self
+            .snapshots
+            .get(1)
+            .is_some_and(|(position, _)| *position <= earliest)
+        {
+            self.snapshots.pop_front();
+        }
+        report
+    }
+
+    /// Executes the next instruction, running any call or creation it opens to
+    /// completion.
+    pub fn step_over(&mut self) -> Pause {
+        let start = self.depth();
+        loop {
+            let depth = self.depth();
+            if self.step().is_none() || self.is_finished() {
+                return Pause::Finished;
+            }
+            if self.depth() <= start {
+                return Pause::Step;
+            }
+            if let Some(index) = self.breakpoint_hit(depth) {
+                return Pause::Breakpoint(index);
+            }
+        }
+    }
+
+    /// Runs until a breakpoint matches or the transaction finishes.
+    pub fn resume(&mut self) -> Pause {
+        loop {
+            let depth = self.depth();
+            if self.step().is_none() || self.is_finished() {
+                return Pause::Finished;
+            }
+            if let Some(index) = self.breakpoint_hit(depth) {
+                return Pause::Breakpoint(index);
+            }
+        }
+    }
+
+    /// Undoes the last instruction. Returns `false` if no snapshot is left.
+    pub fn step_back(&mut self) -> bool {
+        if self.history == 0 {
+            return false;
+        }
+        let target = self.position - 1;
+        while self
+            .snapshots
+            .back()
+            .is_some_and(|(position, _)| *position > target)
+        {
+            self.snapshots.pop_back();
+        }
+        let (position, snapshot) = self
+            .snapshots
+            .back()
+            .expect("the oldest snapshot precedes the history");
+        let mut evm = snapshot.clone();
+        for _ in *position..target {
+            evm.step();
+        }
+        self.evm = evm;
+        self.position = target;
+        self.history -= 1;
+        true
+    }
+
+    /// Returns the index of the first breakpoint matching the paused position, given the
+    /// depth before the last instruction.
+    fn breakpoint_hit(&self, previous_depth: usize) -> Option<usize> {
+        let frame = self.frame()?;
+        // Past the end of the code, the interpreter reads zeros: STOP
+        let op = frame.code().get(frame.pc()).copied().unwrap_or(0);
+        self.breakpoints
+            .iter()
+            .position(|breakpoint| match *breakpoint {
+                Breakpoint::Pc(pc) => frame.pc() == pc,
+                Breakpoint::Opcode(opcode) => opcode.to_byte() == op,
+                Breakpoint::Depth(depth) => frame.depth() == depth && depth != previous_depth,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::assembler::assemble;
+    use crate::evm::execution::ExitReason;
+    use crate::evm::storage::Account;
+    use crate::evm::test_utils::{self, deploy};
+    use crate::types::{Address, U256};
+
+    const CALLEE: Address = Address::with_last_byte(0xc1);
+
+    /// The contract stores 1, calls the callee, which stores 2 in its own storage, then
+    /// stops.
+    fn debugger() -> Debugger {
+        let caller = assemble(
+            "PUSH1 1 PUSH0 SSTORE
+             PUSH0 PUSH0 PUSH0 PUSH0 PUSH0 PUSH1 0xc1 GAS CALL
+             POP STOP",
+        )
+        .unwrap();
+        let callee = assemble("PUSH1 2 PUSH0 SSTORE STOP").unwrap();
+        let mut state = test_utils::state(Account::with_code(caller.into()));
+        state.insert_account(CALLEE, Account::with_code(callee.into()));
+        Debugger::new(Evm::new().with_state(state), test_utils::call()).unwrap()
+    }
+
+    fn next_opcode(debugger: &Debugger) -> Option<Opcode> {
+        debugger
+            .next_instruction()
+            .and_then(|instruction| instruction.opcode)
+    }
+
+    #[test]
+    fn test_step_over_and_back() {
+        let mut debugger = debugger();
+        assert_eq!(next_opcode(&debugger), Some(Opcode::Push1));
+        while next_opcode(&debugger) != Some(Opcode::Call) {
+            assert_eq!(debugger.step_over(), Pause::Step);
+        }
+        let steps = debugger.snapshots();
+        assert_eq!(debugger.step_over(), Pause::Step);
+        assert_eq!(next_opcode(&debugger), Some(Opcode::Pop));
+        assert_eq!(
+            debugger.frame().unwrap().stack().as_slice(),
+            [U256::from(1)]
+        );
+        // Three callee instructions and STOP ran in between
+        assert_eq!(debugger.snapshots(), steps + 5);
+
+        // Back into the callee, just before its STOP
+        assert!(debugger.step_back());
+        assert_eq!(debugger.depth(), 1);
+        assert_eq!(next_opcode(&debugger), Some(Opcode::Stop));
+
+        assert_eq!(debugger.resume(), Pause::Finished);
+        assert!(debugger.step().is_none());
+        assert_eq!(debugger.result().unwrap().exit_reason, ExitReason::Stop);
+        let state = debugger.evm().state();
+        assert_eq!(state.storage(&CALLEE, &Default::default()), U256::from(2));
+
+        // Rewinding undoes the storage writes
+        while debugger.step_back() {}
+        assert_eq!(debugger.frame().unwrap().pc(), 0);
+        assert!(debugger.result().is_none());
+        let state = debugger.evm().state();
+        assert_eq!(state.storage(&CALLEE, &Default::default()), U256::ZERO);
+    }
+
+    #[test]
+    fn test_step_back_replays_from_snapshots() {
+        // Counts down from 40, running a few hundred instructions
+        let code =
+            assemble("PUSH1 40 @loop: JUMPDEST PUSH1 1 SWAP1 SUB DUP1 PUSH @loop JUMPI STOP")
+                .unwrap();
+        let (evm, tx) = deploy(code);
+        let mut debugger = Debugger::new(evm, tx).unwrap().with_snapshot_limit(100);
+
+        let position = |debugger: &Debugger| {
+            let frame = debugger.frame().unwrap();
+            let stack = frame.stack().as_slice().to_vec();
+            (frame.pc(), stack, frame.gas().remaining_gas())
+        };
+        let mut positions = Vec::new();
+        while !debugger.is_finished() {
+            positions.push(position(&debugger));
+            debugger.step();
+        }
+        assert!(positions.len() > 200);
+        assert!(debugger.snapshots.len() <= 100 / SNAPSHOT_INTERVAL + 2);
+
+        for expected in positions.iter().rev().take(100) {
+            assert!(debugger.step_back());
+            assert_eq!(&position(&debugger), expected);
+        }
+        assert!(!debugger.step_back());
+        // Stepping forward again reaches the same states
+        debugger.step();
+        assert_eq!(position(&debugger), positions[positions.len() - 99]);
+        assert_eq!(debugger.snapshots(), 1);
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut debugger = debugger();
+        let sstore = debugger.add_breakpoint(Breakpoint::Opcode(Opcode::Sstore));
+        let depth = debugger.add_breakpoint(Breakpoint::Depth(1));
+        assert_eq!(debugger.breakpoints()[depth].to_string(), "depth 1");
+
+        assert_eq!(debugger.resume(), Pause::Breakpoint(sstore));
+        assert_eq!(debugger.frame().unwrap().pc(), 3);
+        assert_eq!(debugger.resume(), Pause::Breakpoint(depth));
+        assert_eq!(debugger.frame().unwrap().pc(), 0);
+        assert_eq!(debugger.resume(), Pause::Breakpoint(sstore));
+        assert_eq!(debugger.depth(), 1);
+        // Returning to depth 0 does not match
+        assert_eq!(debugger.resume(), Pause::Finished);
+
+        // Stepping over a call stops at breakpoints inside it
+        let mut debugger = self::debugger().with_snapshot_limit(2);
+        debugger.add_breakpoint(Breakpoint::Pc(5));
+        assert_eq!(debugger.resume(), Pause::Breakpoint(0));
+        assert_eq!(debugger.remove_breakpoint(0), Some(Breakpoint::Pc(5)));
+        debugger.add_breakpoint(Breakpoint::Pc(3));
+        while next_opcode(&debugger) != Some(Opcode::Call) {
+            debugger.step();
+        }
+        assert_eq!(debugger.step_over(), Pause::Breakpoint(0));
+        assert_eq!((debugger.depth(), debugger.snapshots()), (1, 2));
+    }
+}
@@ -0,0 +1,218 @@
+//! Disassembler
+//!
+//! Turns raw bytecode into a listing of instructions, one per line, e.g.
+//! `0x0004: PUSH1 0x80`.
+//!
+//! # Design
+//! - Decoding follows [`Opcode::from_byte`] and does not depend on the fork
+//! - `PUSH` data running past the end of the code is kept, but the instruction is marked
+//!   truncated: the interpreter reads the missing bytes as zeros
+//! - Bytes that are not opcodes are listed as `UNKNOWN 0x..` instead of being skipped,
+//!   so every byte of the code appears in the listing
+//! - A compiler's metadata trailer (see [`find_metadata`]) is data, not code: it is
+//!   listed as one `METADATA` line instead of being decoded
+//!
+//! # References
+//! - [Ethereum Yellow Paper, Appendix H (Virtual Machine Specification)]
+
+use std::fmt;
+
+use serde::Serialize;
+
+use super::metadata::{find_metadata, Metadata};
+use super::opcodes::Opcode;
+
+/// One decoded instruction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Instruction {
+    /// Offset of the instruction in the code.
+    pub pc: usize,
+    /// The byte at `pc`.
+    pub op: u8,
+    /// Decoded opcode, `None` if the byte is not an opcode.
+    pub opcode: Option<Opcode>,
+    /// Immediate data following the opcode, as much of it as the code contains.
+    #[serde(serialize_with = "serialize_hex")]
+    pub immediate: Vec<u8>,
+    /// `true` if the code ends before the immediate data does.
+    pub truncated: bool,
+}
+
+impl Instruction {
+    /// Returns the number of bytes the instruction occupies in the code.
+    pub fn size(&self) -> usize {
+        1 + self.immediate.len()
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:04x}: ", self.pc)?;
+        let Some(opcode) = self.opcode else {
+            return write!(f, "UNKNOWN 0x{:02x}", self.op);
+        };
+        write!(f, "{opcode}")?;
+        if opcode.immediate_size() > 0 {
+            write!(f, " 0x{}", hex::encode(&self.immediate))?;
+        }
+        if self.truncated {
+            write!(
+                f,
+                " (truncated: {} of {} bytes)",
+                self.immediate.len(),
+                opcode.immediate_size()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A disassembled piece of code.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Disassembly {
+    pub instructions: Vec<Instruction>,
+    /// The metadata trailer following the instructions, if the code has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+}
+
+impl Disassembly {
+    /// Returns the instruction starting at `pc`, if one does.
+    pub fn instruction_at(&self, pc: usize) -> Option<&Instruction> {
+        self.instructions
+            .binary_search_by_key(&pc, |instruction| instruction.pc)
+            .ok()
+            .map(|index| &self.instructions[index])
+    }
+}
+
+impl fmt::Display for Disassembly {
+    /// Renders the listing, one instruction per line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for instruction in &self.instructions {
+            writeln!(f, "{instruction}")?;
+        }
+        if let Some(metadata) = &self.metadata {
+            writeln!(
+                f,
+                "0x{:04x}: METADATA {}",
+                metadata.offset, metadata.trailer
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Disassembles `code` into its instructions, and its metadata trailer if it has one.
+///
+/// # Examples
+/// ```
+/// use smol_evm::disassembler::disassemble;
+///
+/// let listing = disassemble(&[0x60, 0x80, 0x60, 0x40, 0x52]).to_string();
+/// assert_eq!(listing, "0x0000: PUSH1 0x80\n0x0002: PUSH1 0x40\n0x0004: MSTORE\n");
+/// ```
+pub fn disassemble(code: &[u8]) -> Disassembly {
+    let metadata = find_metadata(code);
+    let end = metadata
+        .as_ref()
+        .map_or(code.len(), |metadata| metadata.offset);
+    // A PUSH running into the trailer is truncated rather than reading it as data
+    let code = &code[..end];
+    let mut instructions = Vec::new();
+    let mut pc = 0;
+    while let Some(instruction) = decode(code, pc) {
+        pc += instruction.size();
+        instructions.push(instruction);
+    }
+    Disassembly {
+        instructions,
+        metadata,
+    }
+}
+
+/// Decodes the single instruction starting at `pc`, or returns `None` past the end of
+/// the code. `pc` is taken as is, even if it falls inside another instruction's data.
+pub fn decode(code: &[u8], pc: usize) -> Option<Instruction> {
+    let op = *code.get(pc)?;
+    let opcode = Opcode::from_byte(op);
+    let size = opcode.map_or(0, Opcode::immediate_size);
+    let end = (pc + 1 + size).min(code.len());
+    let immediate = code[pc + 1..end].to_vec();
+    Some(Instruction {
+        pc,
+        op,
+        opcode,
+        truncated: immediate.len() < size,
+        immediate,
+    })
+}
+
+fn serialize_hex<S: serde::Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("0x{}", hex::encode(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disassemble() {
+        // PUSH1 0x80 PUSH1 0x40 MSTORE 0x0c PUSH0 PUSH2 0x12 (truncated)
+        let code = [0x60, 0x80, 0x60, 0x40, 0x52, 0x0c, 0x5f, 0x61, 0x12];
+        let disassembly = disassemble(&code);
+        assert_eq!(
+            disassembly.to_string(),
+            "0x0000: PUSH1 0x80\n\
+             0x0002: PUSH1 0x40\n\
+             0x0004: MSTORE\n\
+             0x0005: UNKNOWN 0x0c\n\
+             0x0006: PUSH0\n\
+             0x0007: PUSH2 0x12 (truncated: 1 of 2 bytes)\n"
+        );
+        let push = disassembly.instruction_at(7).unwrap();
+        assert_eq!(
+            (push.opcode, push.truncated, push.size()),
+            (Some(Opcode::Push2), true, 2)
+        );
+        assert!(disassembly.instruction_at(1).is_none());
+        // Decoding from inside PUSH data reads the data as an opcode
+        assert_eq!(decode(&code, 1).unwrap().to_string(), "0x0001: DUP1");
+        assert!(decode(&code, code.len()).is_none());
+
+        let json = serde_json::to_value(&disassembly).unwrap();
+        assert_eq!(json["instructions"][0]["opcode"], "PUSH1");
+        assert_eq!(json["instructions"][0]["immediate"], "0x80");
+        assert_eq!(json["instructions"][3]["opcode"], serde_json::Value::Null);
+        assert!(json.get("metadata").is_none());
+    }
+
+    #[test]
+    fn test_metadata_is_data() {
+        // PUSH1 0x80 STOP, then {"solc": 0.8.24} and its length
+        let code = [
+            0x60, 0x80, 0x00, 0xa1, 0x64, b's', b'o', b'l', b'c', 0x43, 0x00, 0x08, 0x18, 0x00,
+            0x0a,
+        ];
+        let disassembly = disassemble(&code);
+        assert_eq!(disassembly.instructions.len(), 2);
+        assert_eq!(
+            disassembly.to_string(),
+            "0x0000: PUSH1 0x80\n\
+             0x0002: STOP\n\
+             0x0003: METADATA 0xa164736f6c6343000818000a\n"
+        );
+        let json = serde_json::to_value(&disassembly).unwrap();
+        assert_eq!(json["metadata"]["compiler"]["name"], "solc");
+
+        // A PUSH2 whose second byte would be the first byte of the trailer
+        let code = [&[0x61, 0xaa][..], &code[3..]].concat();
+        let disassembly = disassemble(&code);
+        assert_eq!(
+            disassembly.to_string(),
+            "0x0000: PUSH2 0xaa (truncated: 1 of 2 bytes)\n\
+             0x0002: METADATA 0xa164736f6c6343000818000a\n"
+        );
+    }
+}
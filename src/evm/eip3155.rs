@@ -0,0 +1,241 @@
+//! EIP-3155 Tracer
+//!
+//! Records one JSON line per executed instruction in the format of EIP-3155, the trace
+//! format geth and evmone print with `--json`, followed by a summary line. Diffing two
+//! traces finds the first instruction where implementations disagree.
+//!
+//! # Design
+//! - An [`Inspector`]: each line describes the state *before* the instruction, its gas,
+//!   stack, memory size and refund counter; `gasCost` and `error` come from the
+//!   instruction's [`StepReport`]
+//! - `depth` starts at 1 for the outermost frame, and `refund` is the transaction-wide
+//!   counter, summed over the frames in progress
+//! - `gasCost` of calls and creations includes the gas forwarded to them, as geth does
+//! - `error` is the [`ExitReason`](super::execution::ExitReason) of an exceptional halt,
+//!   in its `Debug` form; revert and the other normal exits are not errors
+//! - The summary omits `stateRoot`, since the crate does not compute state tries
+//! - With [`Metering::PerBlock`](super::execution::Metering::PerBlock), `gas` excludes gas
+//!   already charged for the rest of the block, so only per-instruction traces compare
+//!
+//! # References
+//! - [EIP-3155: EVM trace specification](https://eips.ethereum.org/EIPS/eip-3155)
+
+use std::fmt;
+
+use serde::{Serialize, Serializer};
+
+use super::env::TxEnv;
+use super::execution::{Evm, ExecutionResult, StepReport, TransactionError};
+use super::frame::Frame;
+use super::inspector::Inspector;
+use super::opcodes::Opcode;
+use crate::types::{Bytes, U256};
+
+/// One line of the trace: the state before an instruction, and what it cost.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceStep {
+    pub pc: usize,
+    /// The byte at `pc`.
+    pub op: u8,
+    /// Gas remaining before the instruction.
+    #[serde(serialize_with = "serialize_quantity")]
+    pub gas: u64,
+    #[serde(serialize_with = "serialize_quantity")]
+    pub gas_cost: u64,
+    /// Memory size in bytes.
+    pub mem_size: usize,
+    /// Stack, bottom first.
+    pub stack: Vec<U256>,
+    /// Call depth, 1 for the outermost frame.
+    pub depth: usize,
+    /// Refund counter of the transaction.
+    pub refund: i64,
+    /// Mnemonic of `op`, `UNKNOWN` if it is not an opcode.
+    pub op_name: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// The last line of the trace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceSummary {
+    pub output: Bytes,
+    /// Gas used by the transaction, intrinsic gas included.
+    #[serde(serialize_with = "serialize_quantity")]
+    pub gas_used: u64,
+    /// `true` if the transaction succeeded.
+    pub pass: bool,
+    /// Why the transaction failed, if it did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl From<&ExecutionResult> for TraceSummary {
+    fn from(result: &ExecutionResult) -> Self {
+        Self {
+            output: result.output.clone(),
+            gas_used: result.gas.gas_used,
+            pass: result.is_success(),
+            error: (!result.is_success()).then(|| format!("{:?}", result.exit_reason)),
+        }
+    }
+}
+
+/// Collects an EIP-3155 trace of a transaction.
+#[derive(Debug, Clone, Default)]
+pub struct Eip3155Tracer {
+    steps: Vec<TraceStep>,
+    summary: Option<TraceSummary>,
+}
+
+impl Eip3155Tracer {
+    /// Creates an empty tracer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Executes a transaction to completion, tracing every instruction.
+    ///
+    /// # Errors
+    /// Returns a [`TransactionError`] if the transaction is invalid.
+    pub fn transact(
+        &mut self,
+        evm: &mut Evm,
+        tx: TxEnv,
+    ) -> Result<ExecutionResult, TransactionError> {
+        let result = evm.inspect_transact(tx, self)?;
+        self.summary = Some(TraceSummary::from(&result));
+        Ok(result)
+    }
+
+    /// Returns the traced instructions.
+    pub fn steps(&self) -> &[TraceStep] {
+        &self.steps
+    }
+
+    /// Returns the summary, once a transaction finished.
+    pub fn summary(&self) -> Option<&TraceSummary> {
+        self.summary.as_ref()
+    }
+}
+
+impl Inspector for Eip3155Tracer {
+    fn step(&mut self, evm: &Evm, frame: &Frame, _opcode: Option<Opcode>) {
+        // Past the end of the code, the interpreter reads zeros: STOP
+        let op = frame.code().get(frame.pc()).copied().unwrap_or(0);
+        self.steps.push(TraceStep {
+            pc: frame.pc(),
+            op,
+            gas: frame.gas().remaining_gas(),
+            gas_cost: 0,
+            mem_size: frame.memory().size(),
+            stack: frame.stack().as_slice().to_vec(),
+            depth: frame.depth() + 1,
+            refund: evm
+                .frames()
+                .iter()
+                .map(|frame| frame.gas().gas_refund())
+                .sum(),
+            op_name: Opcode::from_byte(op).map_or("UNKNOWN", Opcode::name),
+            error: None,
+        });
+    }
+
+    fn step_end(&mut self, _evm: &Evm, report: &StepReport) {
+        let step = self
+            .steps
+            .last_mut()
+            .expect("every instruction is traced before it runs");
+        step.gas_cost = report.gas.total() + report.gas.forwarded;
+        step.error = report.error.map(|reason| format!("{reason:?}"));
+    }
+}
+
+impl fmt::Display for Eip3155Tracer {
+    /// Renders the trace as JSON lines: one per instruction, then the summary.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{}", to_json(step))?;
+        }
+        if let Some(summary) = &self.summary {
+            writeln!(f, "{}", to_json(summary))?;
+        }
+        Ok(())
+    }
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).expect("trace lines always serialize")
+}
+
+/// Serializes a number as a `0x`-prefixed hex quantity, as EIP-3155 and geth do.
+pub(crate) fn serialize_quantity<S: Serializer>(
+    value: &u64,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{value:#x}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::assembler::assemble;
+    use crate::evm::execution::ExitReason;
+    use crate::evm::test_utils::deploy;
+
+    fn trace(code: &str) -> (Eip3155Tracer, ExecutionResult) {
+        let (mut evm, tx) = deploy(assemble(code).unwrap());
+        let mut tracer = Eip3155Tracer::new();
+        let result = tracer.transact(&mut evm, tx).unwrap();
+        (tracer, result)
+    }
+
+    #[test]
+    fn test_trace_lines() {
+        let (tracer, result) = trace("PUSH1 0x2a PUSH0 MSTORE PUSH1 32 PUSH0 RETURN");
+        assert!(result.is_success());
+        let text = tracer.to_string();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert_eq!(
+            lines[0],
+            r#"{"pc":0,"op":96,"gas":"0x13498","gasCost":"0x3","memSize":0,"stack":[],"depth":1,"refund":0,"opName":"PUSH1"}"#
+        );
+        // MSTORE: stack before it, memory expansion in its cost
+        assert_eq!(
+            lines[2],
+            r#"{"pc":3,"op":82,"gas":"0x13493","gasCost":"0x6","memSize":0,"stack":["0x2a","0x0"],"depth":1,"refund":0,"opName":"MSTORE"}"#
+        );
+        assert!(lines[3].contains(r#""memSize":32"#));
+        assert_eq!(
+            lines[6],
+            format!(
+                r#"{{"output":"0x{:064x}","gasUsed":"{:#x}","pass":true}}"#,
+                0x2a, result.gas.gas_used
+            )
+        );
+    }
+
+    #[test]
+    fn test_trace_errors_and_refunds() {
+        // Clearing a slot set earlier in the transaction earns a refund; then an invalid
+        // jump halts
+        let (tracer, result) = trace("PUSH1 1 PUSH0 SSTORE PUSH0 PUSH0 SSTORE PUSH1 3 JUMP");
+        assert_eq!(result.exit_reason, ExitReason::InvalidJump);
+        let steps = tracer.steps();
+        let jump = steps.last().unwrap();
+        assert_eq!(jump.op_name, "JUMP");
+        assert_eq!(jump.error.as_deref(), Some("InvalidJump"));
+        assert!(jump.refund > 0);
+        assert_eq!(steps[2].refund, 0);
+
+        let summary = tracer.summary().unwrap();
+        assert!(!summary.pass);
+        assert_eq!(summary.gas_used, 100_000);
+        let json = serde_json::to_value(summary).unwrap();
+        assert_eq!(json["error"], "InvalidJump");
+        assert_eq!(json["gasUsed"], "0x186a0");
+    }
+}
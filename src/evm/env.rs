@@ -0,0 +1,135 @@
+//! Execution Environment
+//!
+//! The block and transaction context an execution runs in: everything the environment
+//! opcodes (`COINBASE`, `NUMBER`, `ORIGIN`, `GASPRICE`, ...) read, plus the transaction
+//! parameters the interpreter validates and charges before running any code.
+//!
+//! # References
+//! - [Ethereum Yellow Paper, Section 4.3 (The Block) and 4.2 (The Transaction)]
+//! - [EIP-4399: Supplant DIFFICULTY opcode with PREVRANDAO](https://eips.ethereum.org/EIPS/eip-4399)
+//! - [EIP-4844: Shard Blob Transactions](https://eips.ethereum.org/EIPS/eip-4844)
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{AccessList, Address, Bytes, B256, U256};
+
+/// Block-level context.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BlockEnv {
+    /// Block number (`NUMBER`).
+    pub number: u64,
+    /// Block timestamp in seconds (`TIMESTAMP`).
+    pub timestamp: u64,
+    /// Beneficiary of the block's fees (`COINBASE`).
+    pub coinbase: Address,
+    /// Block gas limit (`GASLIMIT`).
+    pub gas_limit: u64,
+    /// Base fee per gas (`BASEFEE`, EIP-1559).
+    pub basefee: U256,
+    /// Proof-of-work difficulty (`DIFFICULTY` before Paris).
+    pub difficulty: U256,
+    /// Beacon chain randomness (`PREVRANDAO` from Paris).
+    pub prevrandao: B256,
+    /// Chain identifier (`CHAINID`, EIP-1344).
+    pub chain_id: u64,
+    /// Blob base fee (`BLOBBASEFEE`, EIP-7516).
+    pub blob_basefee: U256,
+    /// Hashes of recent blocks by number, served by `BLOCKHASH`.
+    pub block_hashes: BTreeMap<u64, B256>,
+}
+
+impl Default for BlockEnv {
+    fn default() -> Self {
+        Self {
+            number: 0,
+            timestamp: 0,
+            coinbase: Address::ZERO,
+            gas_limit: 30_000_000,
+            basefee: U256::ZERO,
+            difficulty: U256::ZERO,
+            prevrandao: B256::ZERO,
+            chain_id: 1,
+            blob_basefee: U256::ZERO,
+            block_hashes: BTreeMap::new(),
+        }
+    }
+}
+
+impl BlockEnv {
+    /// Returns the hash `BLOCKHASH` reports for block `number`: zero unless the block is
+    /// one of the 256 most recent and its hash is known.
+    pub fn block_hash(&self, number: U256) -> B256 {
+        let Ok(number) = u64::try_from(number) else {
+            return B256::ZERO;
+        };
+        if number >= self.number || self.number - number > 256 {
+            return B256::ZERO;
+        }
+        self.block_hashes
+            .get(&number)
+            .copied()
+            .unwrap_or(B256::ZERO)
+    }
+}
+
+/// Transaction-level context.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TxEnv {
+    /// Sender of the transaction (`ORIGIN`, and `CALLER` of the outermost frame).
+    pub caller: Address,
+    /// Recipient, or `None` for contract creation.
+    pub to: Option<Address>,
+    /// Value transferred in wei.
+    pub value: U256,
+    /// Calldata, or init code for contract creation.
+    pub data: Bytes,
+    /// Gas limit of the transaction.
+    pub gas_limit: u64,
+    /// Gas price paid per unit of gas (`GASPRICE`).
+    pub gas_price: U256,
+    /// Expected sender nonce; `None` skips the nonce check.
+    pub nonce: Option<u64>,
+    /// EIP-2930 access list.
+    pub access_list: AccessList,
+    /// Versioned hashes of the transaction's blobs (`BLOBHASH`).
+    pub blob_hashes: Vec<B256>,
+}
+
+impl Default for TxEnv {
+    fn default() -> Self {
+        Self {
+            caller: Address::ZERO,
+            to: None,
+            value: U256::ZERO,
+            data: Bytes::new(),
+            gas_limit: 30_000_000,
+            gas_price: U256::ZERO,
+            nonce: None,
+            access_list: AccessList::new(),
+            blob_hashes: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_hash_window() {
+        let mut block = BlockEnv {
+            number: 300,
+            ..BlockEnv::default()
+        };
+        block.block_hashes.insert(299, B256::with_last_byte(1));
+        block.block_hashes.insert(10, B256::with_last_byte(2));
+        assert_eq!(block.block_hash(U256::from(299)), B256::with_last_byte(1));
+        assert_eq!(block.block_hash(U256::from(10)), B256::ZERO);
+        assert_eq!(block.block_hash(U256::from(300)), B256::ZERO);
+        assert_eq!(block.block_hash(U256::MAX), B256::ZERO);
+    }
+}
@@ -0,0 +1,294 @@
+//! Gas Estimation
+//!
+//! Finds the lowest gas limit a transaction succeeds with, the way `eth_estimateGas`
+//! does: by executing it repeatedly against a copy of the state and binary searching
+//! over the gas limit.
+//!
+//! # Design
+//! - Gas used is not enough on its own: refunds are only paid after execution, and the
+//!   63/64 rule (EIP-150) withholds gas from sub-calls, so a transaction can need a
+//!   limit well above what it ends up using
+//! - The first run uses the highest allowed limit; if it fails, no limit can succeed
+//!   and the revert reason or halt is reported instead
+//! - A second, optimistic run at `(used + refund + stipend) * 64 / 63` settles most
+//!   transactions without a full search
+//! - Every run starts from a fresh clone of the interpreter passed in, which is never
+//!   modified. [`GasMeter::reset`](super::gas::GasMeter::reset) does not fit here: a run
+//!   changes balances, nonces, storage and the warm sets in the journal, and every frame
+//!   gets its own meter, so resetting one meter cannot rewind a run
+//! - Limits are computed with saturating arithmetic, so a `gas_limit` of `u64::MAX`
+//!   searches the whole range without overflowing
+//!
+//! # References
+//! - [go-ethereum `eth/gasestimator`](https://github.com/ethereum/go-ethereum/tree/master/eth/gasestimator)
+
+use super::env::TxEnv;
+use super::execution::{Evm, ExecutionResult, ExitReason, TransactionError};
+use super::gas::{intrinsic_gas, CALL_STIPEND};
+use crate::types::Bytes;
+
+/// Reasons no gas limit can be estimated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EstimateError {
+    /// The transaction is invalid regardless of its gas limit.
+    Transaction(TransactionError),
+    /// The transaction reverts even with the highest allowed gas limit.
+    Reverted {
+        /// The decoded `Error(string)` or `Panic(uint256)` reason, if any.
+        reason: Option<String>,
+        /// The raw revert data.
+        output: Bytes,
+    },
+    /// The transaction halts even with the highest allowed gas limit.
+    Halted(ExitReason),
+}
+
+/// The outcome of a successful estimation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasEstimate {
+    /// Lowest gas limit the transaction succeeds with.
+    pub gas_limit: u64,
+    /// Gas used, after refunds, when run with the highest allowed limit.
+    pub gas_used: u64,
+    /// Refund received when run with the highest allowed limit.
+    pub gas_refund: u64,
+    /// Number of times the transaction was executed.
+    pub runs: usize,
+}
+
+/// Estimates the lowest gas limit `tx` succeeds with in `evm`'s current state.
+///
+/// The transaction's own `gas_limit` is the upper bound of the search. With a non-zero
+/// gas price the bound is also capped at what the sender can afford.
+///
+/// # Errors
+/// Returns [`EstimateError::Transaction`] if the transaction is invalid at any limit,
+/// and [`EstimateError::Reverted`] or [`EstimateError::Halted`] if it fails even with
+/// the highest allowed limit.
+pub fn estimate_gas(evm: &Evm, tx: TxEnv) -> Result<GasEstimate, EstimateError> {
+    let mut hi = tx.gas_limit;
+    if !tx.gas_price.is_zero() {
+        let balance = evm.journal().balance(&tx.caller);
+        let allowance = balance.saturating_sub(tx.value) / tx.gas_price;
+        hi = hi.min(u64::try_from(allowance).unwrap_or(u64::MAX));
+    }
+    let intrinsic = intrinsic_gas(evm.spec(), &tx.data, tx.to.is_none(), &tx.access_list, 0);
+    let mut runs = 0;
+    let mut execute = |gas_limit: u64| -> Result<Option<ExecutionResult>, EstimateError> {
+        runs += 1;
+        let tx = TxEnv {
+            gas_limit,
+            ..tx.clone()
+        };
+        match evm.clone().transact(tx) {
+            Ok(result) => Ok(Some(result)),
+            // Too little gas is a failed run, not an invalid transaction
+            Err(TransactionError::IntrinsicGasTooLow { .. }) => Ok(None),
+            Err(error) => Err(EstimateError::Transaction(error)),
+        }
+    };
+
+    let result = match execute(hi)? {
+        Some(result) => result,
+        None => {
+            return Err(EstimateError::Transaction(
+                TransactionError::IntrinsicGasTooLow {
+                    required: intrinsic.minimum_gas_limit(),
+                    gas_limit: hi,
+                },
+            ))
+        }
+    };
+    if result.exit_reason.is_revert() {
+        return Err(EstimateError::Reverted {
+            reason: result.revert_reason(),
+            output: result.output,
+        });
+    }
+    if !result.is_success() {
+        return Err(EstimateError::Halted(result.exit_reason));
+    }
+    let (gas_used, gas_refund) = (result.gas.gas_used, result.gas.gas_refund);
+
+    // The transaction needs at least the gas it used; with refunds and the 63/64 rule
+    // it can need more
+    let mut lo = gas_used
+        .max(intrinsic.minimum_gas_limit())
+        .saturating_sub(1);
+    let succeeds = |run: Option<ExecutionResult>| run.is_some_and(|result| result.is_success());
+    let optimistic = gas_used
+        .saturating_add(gas_refund)
+        .saturating_add(CALL_STIPEND)
+        .saturating_mul(64)
+        / 63;
+    if optimistic < hi {
+        if succeeds(execute(optimistic)?) {
+            hi = optimistic;
+        } else {
+            lo = optimistic;
+        }
+    }
+    while lo + 1 < hi {
+        // Most transactions need little more than they use, so probe close to `lo`
+        let mid = (lo + (hi - lo) / 2).min(lo.saturating_mul(2));
+        if succeeds(execute(mid)?) {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+
+    Ok(GasEstimate {
+        gas_limit: hi,
+        gas_used,
+        gas_refund,
+        runs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::assembler::assemble;
+    use crate::evm::spec::SpecId;
+    use crate::evm::storage::{Account, WorldState};
+    use crate::evm::test_utils::{self, call};
+    use crate::types::{Address, U256};
+
+    const CALLEE: Address = Address::with_last_byte(0xc1);
+
+    fn evm(state: WorldState) -> Evm {
+        Evm::with_spec(SpecId::Cancun).with_state(state)
+    }
+
+    fn deploy(code: Vec<u8>) -> Evm {
+        evm(test_utils::state(Account::with_code(code.into())))
+    }
+
+    /// Asserts that the estimate succeeds and is tight: one gas less fails.
+    fn assert_tight(evm: &Evm, estimate: &GasEstimate) {
+        for (gas_limit, success) in [(estimate.gas_limit, true), (estimate.gas_limit - 1, false)] {
+            let tx = TxEnv {
+                gas_limit,
+                ..call()
+            };
+            let result = evm.clone().transact(tx);
+            assert_eq!(result.is_ok_and(|result| result.is_success()), success);
+        }
+    }
+
+    #[test]
+    fn test_plain_transfer() {
+        let evm = deploy(vec![]);
+        let estimate = estimate_gas(&evm, call()).unwrap();
+        assert_eq!(estimate.gas_limit, 21000);
+        assert_eq!(estimate.gas_used, 21000);
+    }
+
+    #[test]
+    fn test_refund_raises_estimate() {
+        // Set slot 0, then clear it: the write is paid up front and mostly refunded
+        // PUSH1 1 PUSH1 0 SSTORE PUSH1 0 PUSH1 0 SSTORE STOP
+        let code = vec![0x60, 1, 0x60, 0, 0x55, 0x60, 0, 0x60, 0, 0x55, 0x00];
+        let evm = deploy(code);
+        let estimate = estimate_gas(&evm, call()).unwrap();
+        assert!(estimate.gas_refund > 0);
+        assert!(estimate.gas_limit > estimate.gas_used + estimate.gas_refund);
+        assert_tight(&evm, &estimate);
+    }
+
+    #[test]
+    fn test_call_needs_63_64_headroom() {
+        // The callee writes a slot and fails without enough gas, failing the caller too
+        // Callee: PUSH1 1 PUSH1 0 SSTORE STOP
+        let callee = vec![0x60, 1, 0x60, 0, 0x55, 0x00];
+        // Caller: CALL(GAS, callee) and revert unless it succeeded
+        // PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH20 callee GAS CALL
+        // PUSH1 <ok> JUMPI PUSH1 0 PUSH1 0 REVERT <ok>: JUMPDEST STOP
+        let mut caller = vec![0x60, 0, 0x60, 0, 0x60, 0, 0x60, 0, 0x60, 0, 0x73];
+        caller.extend_from_slice(CALLEE.as_slice());
+        caller.extend_from_slice(&[0x5a, 0xf1, 0x60, 41, 0x57, 0x60, 0, 0x60, 0, 0xfd, 0x5b]);
+        caller.push(0x00);
+        let mut state = test_utils::state(Account::with_code(caller.into()));
+        state.insert_account(CALLEE, Account::with_code(callee.into()));
+        let evm = evm(state);
+
+        let estimate = estimate_gas(&evm, call()).unwrap();
+        // The caller must hold back a 64th of its gas, so the used gas is not enough
+        assert!(estimate.gas_limit > estimate.gas_used);
+        assert_tight(&evm, &estimate);
+    }
+
+    #[test]
+    fn test_unbounded_gas_limit() {
+        // Reverts unless more than a million gas is left, far above what it uses, so the
+        // optimistic run fails and the search climbs towards `u64::MAX`
+        let code =
+            assemble("GAS PUSH3 1000000 LT PUSH @ok JUMPI PUSH0 PUSH0 REVERT @ok: JUMPDEST STOP")
+                .unwrap();
+        let evm = deploy(code);
+        let tx = TxEnv {
+            gas_limit: u64::MAX,
+            ..call()
+        };
+        let estimate = estimate_gas(&evm, tx).unwrap();
+        assert!(estimate.gas_limit > 1_000_000);
+        assert!(estimate.runs > 2);
+        assert_tight(&evm, &estimate);
+    }
+
+    #[test]
+    fn test_revert_reason_reported() {
+        // Store Error("nope") in memory and revert with it:
+        // PUSH4 0x08c379a0 PUSH1 224 SHL PUSH1 0 MSTORE
+        // PUSH1 32 PUSH1 4 MSTORE PUSH1 4 PUSH1 36 MSTORE
+        // PUSH4 "nope" PUSH1 224 SHL PUSH1 68 MSTORE PUSH1 100 PUSH1 0 REVERT
+        let code = vec![
+            0x63, 0x08, 0xc3, 0x79, 0xa0, 0x60, 224, 0x1b, 0x60, 0, 0x52, 0x60, 32, 0x60, 4, 0x52,
+            0x60, 4, 0x60, 36, 0x52, 0x63, b'n', b'o', b'p', b'e', 0x60, 224, 0x1b, 0x60, 68, 0x52,
+            0x60, 100, 0x60, 0, 0xfd,
+        ];
+        let evm = deploy(code);
+        match estimate_gas(&evm, call()) {
+            Err(EstimateError::Reverted { reason, .. }) => {
+                assert_eq!(reason.as_deref(), Some("nope"))
+            }
+            other => panic!("expected a revert, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_halt_and_invalid_transactions() {
+        let evm = deploy(vec![0xfe]);
+        assert_eq!(
+            estimate_gas(&evm, call()),
+            Err(EstimateError::Halted(ExitReason::InvalidOpcode(0xfe)))
+        );
+
+        let tx = TxEnv {
+            nonce: Some(5),
+            ..call()
+        };
+        assert!(matches!(
+            estimate_gas(&evm, tx),
+            Err(EstimateError::Transaction(
+                TransactionError::NonceMismatch { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_gas_price_caps_search() {
+        let evm = deploy(vec![]);
+        let tx = TxEnv {
+            gas_price: U256::from(10u64.pow(18) / 20_000),
+            ..call()
+        };
+        assert!(matches!(
+            estimate_gas(&evm, tx),
+            Err(EstimateError::Transaction(
+                TransactionError::IntrinsicGasTooLow { .. }
+            ))
+        ));
+    }
+}
@@ -0,0 +1,1471 @@
+//! EVM Interpreter
+//!
+//! Runs transactions against a [`WorldState`]. The interpreter validates and charges the
+//! transaction, executes its code one instruction at a time, and settles gas, refunds and
+//! fees once the outermost frame returns.
+//!
+//! # Design
+//! - Execution is step-wise: [`Evm::begin`] starts a transaction, [`Evm::step`] runs a
+//!   single instruction and reports what it cost, [`Evm::run`] steps to completion
+//! - Sub-calls push a new [`Frame`] instead of recursing, so any tool driving `step` sees
+//!   every instruction at every depth
+//! - Each frame has its own [`GasMeter`]; gas left over by a sub-call is returned to the
+//!   caller, and its refunds are kept only if it succeeds
+//! - State changes go through the [`Journal`] and are reverted when a frame fails
+//! - Base costs are charged per instruction by default; [`Metering::PerBlock`] charges
+//!   each basic block's base costs once on entry, with identical results
+//! - [`Metering::Unmetered`] disables gas for analysis and fuzzing; a step limit and a
+//!   memory limit ([`Evm::with_step_limit`], [`Evm::with_memory_limit`]) keep untrusted
+//!   code from running forever or exhausting memory, with or without gas
+//! - Of the precompiles only identity (`0x04`) is implemented; calling any other
+//!   precompile fails with [`ExitReason::PrecompileFailure`]
+//!
+//! # References
+//! - [Ethereum Yellow Paper, Sections 6-9]
+//! - [EIP-150: Gas cost changes for IO-heavy operations](https://eips.ethereum.org/EIPS/eip-150)
+//! - [EIP-170: Contract code size limit](https://eips.ethereum.org/EIPS/eip-170)
+//! - [EIP-3541: Reject new contracts starting with the 0xEF byte](https://eips.ethereum.org/EIPS/eip-3541)
+
+use serde::Serialize;
+
+use super::env::{BlockEnv, TxEnv};
+use super::frame::{
+    Action, CallContext, CallRequest, CallScheme, CreateRequest, CreateScheme, Frame, FrameKind,
+    Host, MAX_CODE_SIZE, MAX_INITCODE_SIZE,
+};
+use super::gas::{intrinsic_gas, GasMeter, GasSettlement};
+use super::inspector::{
+    CallInputs, CallOutcome, CreateInputs, CreateOutcome, Inspector, NoopInspector,
+};
+use super::journal::Journal;
+use super::memory::MEMORY_MAX_SIZE;
+use super::opcodes::Opcode;
+use super::schedule::GasSchedule;
+use super::spec::SpecId;
+use super::storage::WorldState;
+use crate::types::{Address, Bytes, Log, B256, U256};
+
+/// Maximum depth of the call stack.
+pub const CALL_DEPTH_LIMIT: usize = 1024;
+
+/// Why a frame stopped executing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum ExitReason {
+    /// `STOP`, or execution ran past the end of the code.
+    Stop,
+    /// `RETURN`.
+    Return,
+    /// `SELFDESTRUCT`.
+    SelfDestruct,
+    /// `REVERT`: state changes are undone but unused gas is returned.
+    Revert,
+    /// Not enough gas for the next operation.
+    OutOfGas,
+    /// An instruction needed more stack items than available.
+    StackUnderflow,
+    /// An instruction would grow the stack past 1024 items.
+    StackOverflow,
+    /// The byte is not an opcode in the active fork, or is `INVALID` (0xfe).
+    InvalidOpcode(u8),
+    /// `JUMP` or `JUMPI` to something other than a `JUMPDEST`.
+    InvalidJump,
+    /// A state-modifying instruction inside a `STATICCALL` (EIP-214).
+    StateChangeDuringStaticCall,
+    /// Memory would grow past the interpreter's memory limit, `MEMORY_MAX_SIZE` by
+    /// default.
+    MemoryLimit,
+    /// The transaction ran out of the interpreter's step limit.
+    StepLimit,
+    /// `RETURNDATACOPY` read past the end of the return data (EIP-211).
+    ReturnDataOutOfBounds,
+    /// A contract already exists at the address being created.
+    CreateCollision,
+    /// Not enough gas left to pay the deposit for the deployed code.
+    CodeStoreOutOfGas,
+    /// The deployed code exceeds the size limit (EIP-170).
+    CodeSizeLimit,
+    /// The deployed code starts with 0xEF (EIP-3541).
+    InvalidCodePrefix,
+    /// The call would exceed [`CALL_DEPTH_LIMIT`]; the callee never ran.
+    CallTooDeep,
+    /// The caller cannot afford the value transferred; the callee never ran.
+    OutOfFunds,
+    /// The creator's nonce cannot be incremented; the init code never ran.
+    NonceOverflow,
+    /// A precompile rejected its input or is not implemented.
+    PrecompileFailure,
+}
+
+impl ExitReason {
+    /// Returns `true` if the frame completed successfully and keeps its state changes.
+    pub fn is_success(self) -> bool {
+        matches!(self, Self::Stop | Self::Return | Self::SelfDestruct)
+    }
+
+    /// Returns `true` if the frame reverted.
+    pub fn is_revert(self) -> bool {
+        self == Self::Revert
+    }
+
+    /// Returns `true` if the frame halted exceptionally, consuming all of its gas.
+    pub fn is_halt(self) -> bool {
+        !self.is_success() && !self.is_revert() && !self.is_aborted()
+    }
+
+    /// Returns `true` if the sub-call or creation was refused before any code ran, in
+    /// which case its gas is returned to the caller.
+    fn is_aborted(self) -> bool {
+        matches!(
+            self,
+            Self::CallTooDeep | Self::OutOfFunds | Self::NonceOverflow
+        )
+    }
+}
+
+/// Reasons a transaction is rejected before execution.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionError {
+    /// A transaction is already being executed.
+    TransactionInProgress,
+    /// The transaction's nonce does not match the sender's.
+    NonceMismatch { expected: u64, found: u64 },
+    /// The sender's nonce is at its maximum.
+    NonceOverflow,
+    /// The sender cannot pay for the gas limit and value.
+    InsufficientFunds { required: U256, available: U256 },
+    /// The gas limit does not cover the intrinsic gas or the calldata floor.
+    IntrinsicGasTooLow { required: u64, gas_limit: u64 },
+    /// The init code of a creation transaction exceeds the size limit (EIP-3860).
+    InitCodeTooLarge { size: usize },
+}
+
+/// The outcome of an executed transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionResult {
+    /// Why the outermost frame stopped.
+    pub exit_reason: ExitReason,
+    /// Gas used, refunded and returned to the sender.
+    pub gas: GasSettlement,
+    /// Return or revert data; the deployed code for successful creations.
+    pub output: Bytes,
+    /// Logs emitted, empty unless the transaction succeeded.
+    pub logs: Vec<Log>,
+    /// Address of the contract created by a successful creation transaction.
+    pub created_address: Option<Address>,
+}
+
+impl ExecutionResult {
+    /// Returns `true` if the transaction succeeded.
+    pub fn is_success(&self) -> bool {
+        self.exit_reason.is_success()
+    }
+
+    /// Returns the decoded revert reason if the transaction reverted with Solidity's
+    /// `Error(string)` or `Panic(uint256)`.
+    pub fn revert_reason(&self) -> Option<String> {
+        if !self.exit_reason.is_revert() {
+            return None;
+        }
+        decode_revert_reason(&self.output)
+    }
+}
+
+/// Decodes revert data produced by Solidity's `revert("...")`/`require` (`Error(string)`)
+/// or by a failed assertion or arithmetic check (`Panic(uint256)`).
+pub fn decode_revert_reason(output: &[u8]) -> Option<String> {
+    const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+    const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+    let (selector, data) = output.split_at_checked(4)?;
+    if selector == PANIC_SELECTOR {
+        let code = U256::try_from_be_slice(data.get(..32)?)?;
+        return Some(format!("Panic(0x{code:x})"));
+    }
+    if selector != ERROR_SELECTOR {
+        return None;
+    }
+    let offset = usize::try_from(U256::try_from_be_slice(data.get(..32)?)?).ok()?;
+    let len_end = offset.checked_add(32)?;
+    let len = usize::try_from(U256::try_from_be_slice(data.get(offset..len_end)?)?).ok()?;
+    let bytes = data.get(len_end..len_end.checked_add(len)?)?;
+    String::from_utf8(bytes.to_vec()).ok()
+}
+
+/// How the interpreter charges the base cost of instructions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Metering {
+    /// Charge each instruction's base cost as it runs.
+    #[default]
+    PerInstruction,
+    /// Charge the summed base cost of a basic block once, when the block is entered.
+    /// Dynamic costs are still charged per instruction. If a block cannot be paid up
+    /// front, or a dynamic cost needs the gas prepaid for the rest of it, the block falls
+    /// back to per-instruction charging, so execution halts exactly where it would with
+    /// [`Metering::PerInstruction`].
+    PerBlock,
+    /// Charge no gas at all: every frame's [`GasMeter`] is a no-op. The transaction's
+    /// intrinsic gas is still validated and settled, so only execution is free. Pair it
+    /// with a step or memory limit to bound untrusted code.
+    Unmetered,
+}
+
+/// Gas charged by a single instruction, split by kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepGas {
+    /// Base cost of the opcode.
+    pub static_gas: u64,
+    /// Parameter-dependent cost: copied words, cold access, value transfer, ...
+    pub dynamic_gas: u64,
+    /// Memory expansion cost.
+    pub memory_gas: u64,
+    /// Gas passed on to a sub-call or creation. It is not spent by the instruction itself;
+    /// whatever the callee leaves unused is returned.
+    pub forwarded: u64,
+}
+
+impl StepGas {
+    /// Returns the gas the instruction spent itself, excluding forwarded gas.
+    pub fn total(&self) -> u64 {
+        self.static_gas + self.dynamic_gas + self.memory_gas
+    }
+}
+
+/// What happened during one [`Evm::step`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepReport {
+    /// Call depth of the frame the instruction ran in.
+    pub depth: usize,
+    /// Address the executed code was loaded from.
+    pub code_address: Address,
+    /// Program counter of the instruction.
+    pub pc: usize,
+    /// Byte at the program counter.
+    pub op: u8,
+    /// Decoded opcode, `None` if the byte is not an opcode in the active fork.
+    pub opcode: Option<Opcode>,
+    /// Gas remaining in the frame before the instruction. With [`Metering::PerBlock`] this
+    /// excludes gas already charged for the rest of the block.
+    pub gas_remaining: u64,
+    /// Gas the instruction was charged.
+    pub gas: StepGas,
+    /// The exceptional halt the instruction caused, if any.
+    pub error: Option<ExitReason>,
+}
+
+/// Result of a frame, passed back to its caller.
+#[derive(Debug)]
+struct FrameResult {
+    reason: ExitReason,
+    output: Bytes,
+    gas_remaining: u64,
+    gas_refund: i64,
+}
+
+impl FrameResult {
+    /// A frame that failed without using its gas, or consumed all of it if it halted.
+    fn failed(reason: ExitReason, gas_limit: u64) -> Self {
+        Self {
+            reason,
+            output: Bytes::new(),
+            gas_remaining: if reason.is_halt() { 0 } else { gas_limit },
+            gas_refund: 0,
+        }
+    }
+}
+
+/// The EVM interpreter.
+///
+/// # Example
+/// ```
+/// use smol_evm::env::TxEnv;
+/// use smol_evm::execution::Evm;
+/// use smol_evm::storage::{Account, WorldState};
+/// use smol_evm::{Address, Bytes};
+///
+/// // PUSH1 0x2a PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+/// let code = Bytes::from_static(&[0x60, 0x2a, 0x60, 0, 0x52, 0x60, 32, 0x60, 0, 0xf3]);
+/// let contract = Address::with_last_byte(0xc0);
+/// let mut state = WorldState::new();
+/// state.insert_account(contract, Account::with_code(code));
+///
+/// let mut evm = Evm::new().with_state(state);
+/// let tx = TxEnv { to: Some(contract), gas_limit: 100_000, ..TxEnv::default() };
+/// let result = evm.transact(tx).unwrap();
+/// assert!(result.is_success());
+/// assert_eq!(result.output[31], 0x2a);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Evm {
+    schedule: GasSchedule,
+    metering: Metering,
+    /// Maximum number of steps per transaction, if limited.
+    step_limit: Option<u64>,
+    /// Maximum memory size of a frame, in bytes.
+    memory_limit: usize,
+    /// Steps taken by the transaction in progress, or the last one executed.
+    steps: u64,
+    block: BlockEnv,
+    journal: Journal,
+    /// The transaction being executed, or the last one executed.
+    tx: TxEnv,
+    /// EIP-7623 calldata floor of the transaction.
+    floor_gas: u64,
+    /// The call stack; the last frame is executing.
+    frames: Vec<Frame>,
+    /// Result of the last finished transaction, until taken.
+    result: Option<ExecutionResult>,
+    /// Outermost frame of the last finished transaction, as it exited.
+    last_frame: Option<Frame>,
+}
+
+impl Default for Evm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Evm {
+    /// Creates an interpreter following the latest fork, with an empty state.
+    pub fn new() -> Self {
+        Self::with_spec(SpecId::default())
+    }
+
+    /// Creates an interpreter following the given fork, with an empty state.
+    pub fn with_spec(spec: SpecId) -> Self {
+        Self::with_schedule(GasSchedule::for_spec(spec))
+    }
+
+    /// Creates an interpreter using a custom gas schedule, with an empty state.
+    pub fn with_schedule(schedule: GasSchedule) -> Self {
+        Self {
+            schedule,
+            metering: Metering::default(),
+            step_limit: None,
+            memory_limit: MEMORY_MAX_SIZE,
+            steps: 0,
+            block: BlockEnv::default(),
+            journal: Journal::new(),
+            tx: TxEnv::default(),
+            floor_gas: 0,
+            frames: Vec::new(),
+            result: None,
+            last_frame: None,
+        }
+    }
+
+    /// Sets the world state transactions run against.
+    pub fn with_state(mut self, state: WorldState) -> Self {
+        self.journal = Journal::with_state(state);
+        self
+    }
+
+    /// Sets the block environment.
+    pub fn with_block(mut self, block: BlockEnv) -> Self {
+        self.block = block;
+        self
+    }
+
+    /// Sets how base costs are charged.
+    pub fn with_metering(mut self, metering: Metering) -> Self {
+        self.metering = metering;
+        self
+    }
+
+    /// Limits every transaction to `step_limit` executed instructions. Once it runs out,
+    /// each remaining frame halts with [`ExitReason::StepLimit`].
+    pub fn with_step_limit(mut self, step_limit: u64) -> Self {
+        self.step_limit = Some(step_limit);
+        self
+    }
+
+    /// Limits the memory of every frame to `memory_limit` bytes, at most
+    /// `MEMORY_MAX_SIZE`. Growing past it halts the frame with [`ExitReason::MemoryLimit`].
+    pub fn with_memory_limit(mut self, memory_limit: usize) -> Self {
+        self.memory_limit = memory_limit.min(MEMORY_MAX_SIZE);
+        self
+    }
+
+    /// Returns the fork whose rules apply.
+    pub fn spec(&self) -> SpecId {
+        self.schedule.spec
+    }
+
+    /// Returns the gas schedule.
+    pub fn schedule(&self) -> &GasSchedule {
+        &self.schedule
+    }
+
+    /// Returns how gas is charged.
+    pub fn metering(&self) -> Metering {
+        self.metering
+    }
+
+    /// Returns the number of steps taken by the transaction in progress, or the last one
+    /// executed.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    /// Returns the block environment.
+    pub fn block(&self) -> &BlockEnv {
+        &self.block
+    }
+
+    /// Returns the transaction being executed, or the last one executed.
+    pub fn tx(&self) -> &TxEnv {
+        &self.tx
+    }
+
+    /// Returns the journal of the current transaction.
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    /// Returns the world state, including the changes of a transaction in progress.
+    pub fn state(&self) -> &WorldState {
+        self.journal.state()
+    }
+
+    /// Consumes the interpreter, returning the world state.
+    pub fn into_state(self) -> WorldState {
+        self.journal.into_state()
+    }
+
+    /// Returns the call stack, outermost frame first.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Returns the frame that executes next, if a transaction is in progress.
+    pub fn current_frame(&self) -> Option<&Frame> {
+        self.frames.last()
+    }
+
+    /// Returns `true` if a transaction is in progress.
+    pub fn is_running(&self) -> bool {
+        !self.frames.is_empty()
+    }
+
+    /// Returns the result of the last finished transaction, until taken.
+    pub fn result(&self) -> Option<&ExecutionResult> {
+        self.result.as_ref()
+    }
+
+    /// Takes the result of the last finished transaction.
+    pub fn take_result(&mut self) -> Option<ExecutionResult> {
+        self.result.take()
+    }
+
+    /// Returns the outermost frame of the last finished transaction as it exited, with its
+    /// final stack and memory. `None` if the transaction ran no code.
+    pub fn last_frame(&self) -> Option<&Frame> {
+        self.last_frame.as_ref()
+    }
+
+    /// Validates and executes a transaction to completion.
+    ///
+    /// # Errors
+    /// Returns a [`TransactionError`] if the transaction is invalid; the state is then
+    /// unchanged.
+    pub fn transact(&mut self, tx: TxEnv) -> Result<ExecutionResult, TransactionError> {
+        self.inspect_transact(tx, &mut NoopInspector)
+    }
+
+    /// Like [`Evm::transact`], calling the hooks of `inspector` throughout.
+    ///
+    /// # Errors
+    /// Returns a [`TransactionError`] if the transaction is invalid; the state is then
+    /// unchanged.
+    pub fn inspect_transact(
+        &mut self,
+        tx: TxEnv,
+        inspector: &mut dyn Inspector,
+    ) -> Result<ExecutionResult, TransactionError> {
+        self.inspect_begin(tx, inspector)?;
+        Ok(self
+            .inspect_run(inspector)
+            .expect("a begun transaction always produces a result"))
+    }
+
+    /// Runs the transaction in progress to completion and returns its result, or `None`
+    /// if no transaction was begun.
+    pub fn run(&mut self) -> Option<ExecutionResult> {
+        self.inspect_run(&mut NoopInspector)
+    }
+
+    /// Like [`Evm::run`], calling the hooks of `inspector` throughout.
+    pub fn inspect_run(&mut self, inspector: &mut dyn Inspector) -> Option<ExecutionResult> {
+        while self.inspect_step(inspector).is_some() {}
+        self.take_result()
+    }
+
+    /// Validates a transaction, charges the sender, and prepares the outermost frame.
+    ///
+    /// Transactions that run no code (e.g. plain transfers) finish immediately.
+    ///
+    /// # Errors
+    /// Returns a [`TransactionError`] if the transaction is invalid; the state is then
+    /// unchanged.
+    pub fn begin(&mut self, tx: TxEnv) -> Result<(), TransactionError> {
+        self.inspect_begin(tx, &mut NoopInspector)
+    }
+
+    /// Like [`Evm::begin`], calling the hooks of `inspector` for the transaction's message.
+    ///
+    /// # Errors
+    /// Returns a [`TransactionError`] if the transaction is invalid; the state is then
+    /// unchanged.
+    pub fn inspect_begin(
+        &mut self,
+        tx: TxEnv,
+        inspector: &mut dyn Inspector,
+    ) -> Result<(), TransactionError> {
+        if self.is_running() {
+            return Err(TransactionError::TransactionInProgress);
+        }
+        let spec = self.spec();
+        let is_create = tx.to.is_none();
+        if is_create && spec.is_enabled_in(SpecId::Shanghai) && tx.data.len() > MAX_INITCODE_SIZE {
+            return Err(TransactionError::InitCodeTooLarge {
+                size: tx.data.len(),
+            });
+        }
+        let intrinsic = intrinsic_gas(spec, &tx.data, is_create, &tx.access_list, 0);
+        if tx.gas_limit < intrinsic.minimum_gas_limit() {
+            return Err(TransactionError::IntrinsicGasTooLow {
+                required: intrinsic.minimum_gas_limit(),
+                gas_limit: tx.gas_limit,
+            });
+        }
+        let nonce = self.journal.nonce(&tx.caller);
+        if let Some(expected) = tx.nonce {
+            if expected != nonce {
+                return Err(TransactionError::NonceMismatch {
+                    expected,
+                    found: nonce,
+                });
+            }
+        }
+        if nonce == u64::MAX {
+            return Err(TransactionError::NonceOverflow);
+        }
+        let gas_cost = U256::from(tx.gas_limit).saturating_mul(tx.gas_price);
+        let required = gas_cost.saturating_add(tx.value);
+        let available = self.journal.balance(&tx.caller);
+        if available < required {
+            return Err(TransactionError::InsufficientFunds {
+                required,
+                available,
+            });
+        }
+
+        self.result = None;
+        self.last_frame = None;
+        self.steps = 0;
+        self.journal.set_balance(tx.caller, available - gas_cost);
+        let target = tx.to.unwrap_or_else(|| tx.caller.create(nonce));
+        self.journal.prewarm(
+            spec,
+            tx.caller,
+            target,
+            self.block.coinbase,
+            &tx.access_list,
+        );
+        self.floor_gas = intrinsic.floor_gas;
+        self.tx = tx.clone();
+
+        let gas_limit = tx.gas_limit - intrinsic.initial_gas;
+        match tx.to {
+            Some(to) => {
+                // A creation increments the nonce in `start_create` as part of computing
+                // the address; a call increments the sender's nonce here
+                self.journal.set_nonce(tx.caller, nonce + 1);
+                self.start_call(
+                    CallRequest {
+                        scheme: CallScheme::Call,
+                        context: CallContext {
+                            caller: tx.caller,
+                            address: to,
+                            code_address: to,
+                            value: tx.value,
+                            input: tx.data,
+                            is_static: false,
+                        },
+                        transfer: tx.value,
+                        gas_limit,
+                        ret_offset: 0,
+                        ret_len: 0,
+                    },
+                    0,
+                    inspector,
+                );
+            }
+            None => self.start_create(
+                CreateRequest {
+                    scheme: CreateScheme::Create,
+                    caller: tx.caller,
+                    value: tx.value,
+                    init_code: tx.data,
+                    gas_limit,
+                },
+                0,
+                inspector,
+            ),
+        }
+        Ok(())
+    }
+
+    /// Executes the next instruction of the transaction in progress.
+    ///
+    /// Returns `None` if no transaction is in progress.
+    pub fn step(&mut self) -> Option<StepReport> {
+        self.inspect_step(&mut NoopInspector)
+    }
+
+    /// Like [`Evm::step`], calling the hooks of `inspector`.
+    pub fn inspect_step(&mut self, inspector: &mut dyn Inspector) -> Option<StepReport> {
+        let spec = self.spec();
+        let frame = self.frames.last()?;
+        let op = frame.current_byte();
+        let opcode = Opcode::from_byte(op).filter(|opcode| opcode.is_enabled_in(spec));
+        inspector.step(self, frame, opcode);
+        // What a self-destruct sends where, read before it runs
+        let destruct = match (opcode, frame.stack().peek()) {
+            (Some(Opcode::Selfdestruct), Some(target)) => {
+                let address = frame.context().address;
+                let target = Address::from_word(B256::from(*target));
+                Some((address, target, self.journal.balance(&address)))
+            }
+            _ => None,
+        };
+
+        let Self {
+            frames,
+            journal,
+            block,
+            tx,
+            ..
+        } = self;
+        let frame = frames.last_mut().expect("a frame is executing");
+        let depth = frame.depth();
+        let code_address = frame.context().code_address;
+        let pc = frame.pc();
+        let gas_remaining = frame.gas().remaining_gas();
+
+        let mut gas = StepGas::default();
+        // Once out of steps, every remaining frame halts without running anything
+        let out_of_steps = self.step_limit.is_some_and(|limit| self.steps >= limit);
+        if !out_of_steps {
+            self.steps += 1;
+        }
+        let outcome = match opcode {
+            _ if out_of_steps => Err(ExitReason::StepLimit),
+            None => Err(ExitReason::InvalidOpcode(op)),
+            Some(opcode) => frame.charge_static(opcode).and_then(|cost| {
+                gas.static_gas = cost;
+                let mut host = Host { journal, block, tx };
+                frame.execute(opcode, &mut host, &mut gas)
+            }),
+        };
+
+        let error = outcome.as_ref().err().copied();
+        match outcome {
+            Ok(Action::Continue) => {
+                let is_log = matches!(
+                    opcode,
+                    Some(Opcode::Log0 | Opcode::Log1 | Opcode::Log2 | Opcode::Log3 | Opcode::Log4)
+                );
+                if let Some(log) = self.journal.logs().last().filter(|_| is_log) {
+                    inspector.log(self, log);
+                }
+            }
+            Ok(Action::Call(request)) => self.start_call(request, depth + 1, inspector),
+            Ok(Action::Create(request)) => self.start_create(request, depth + 1, inspector),
+            Ok(Action::Exit(reason, output)) => {
+                if let (ExitReason::SelfDestruct, Some((address, target, value))) =
+                    (reason, destruct)
+                {
+                    inspector.selfdestruct(self, address, target, value);
+                }
+                self.exit_frame(reason, output, inspector);
+            }
+            Err(reason) => self.exit_frame(reason, Bytes::new(), inspector),
+        }
+
+        let report = StepReport {
+            depth,
+            code_address,
+            pc,
+            op,
+            opcode,
+            gas_remaining,
+            gas,
+            error,
+        };
+        inspector.step_end(self, &report);
+        Some(report)
+    }
+
+    /// Opens a message call, running it immediately if it has no code to execute.
+    fn start_call(&mut self, request: CallRequest, depth: usize, inspector: &mut dyn Inspector) {
+        let kind = FrameKind::Call {
+            scheme: request.scheme,
+            ret_offset: request.ret_offset,
+            ret_len: request.ret_len,
+        };
+        let inputs = CallInputs {
+            scheme: request.scheme,
+            context: request.context.clone(),
+            gas_limit: request.gas_limit,
+        };
+        if let Some(outcome) = inspector.call(self, &inputs) {
+            let result = FrameResult {
+                reason: outcome.reason,
+                output: outcome.output,
+                gas_remaining: outcome.gas_remaining,
+                gas_refund: 0,
+            };
+            return self.end_call(kind, &inputs, result, inspector);
+        }
+        if depth > CALL_DEPTH_LIMIT {
+            let result = FrameResult::failed(ExitReason::CallTooDeep, request.gas_limit);
+            return self.end_call(kind, &inputs, result, inspector);
+        }
+        let context = request.context;
+        let value = context.value;
+        let has_value = matches!(request.scheme, CallScheme::Call | CallScheme::Callcode);
+        if has_value && self.journal.balance(&context.caller) < value {
+            let result = FrameResult::failed(ExitReason::OutOfFunds, request.gas_limit);
+            return self.end_call(kind, &inputs, result, inspector);
+        }
+
+        let checkpoint = self.journal.checkpoint();
+        if request.scheme == CallScheme::Call {
+            self.journal
+                .transfer(context.caller, context.address, request.transfer);
+        }
+
+        let result = if self.spec().precompiles().contains(&context.code_address) {
+            run_precompile(context.code_address, &context.input, request.gas_limit)
+        } else {
+            let code = self.journal.code(&context.code_address);
+            if !code.is_empty() {
+                let gas = self.gas_meter(request.gas_limit);
+                let frame = Frame::new(kind, context, code, gas, checkpoint, depth);
+                self.push_frame(frame);
+                return;
+            }
+            FrameResult {
+                reason: ExitReason::Stop,
+                output: Bytes::new(),
+                gas_remaining: request.gas_limit,
+                gas_refund: 0,
+            }
+        };
+        if !result.reason.is_success() {
+            self.journal.revert(checkpoint);
+        }
+        self.end_call(kind, &inputs, result, inspector);
+    }
+
+    /// Starts a contract creation, running it immediately if the init code is empty.
+    fn start_create(
+        &mut self,
+        request: CreateRequest,
+        depth: usize,
+        inspector: &mut dyn Inspector,
+    ) {
+        let spec = self.spec();
+        let caller = request.caller;
+        let inputs = CreateInputs {
+            scheme: request.scheme,
+            caller,
+            value: request.value,
+            init_code: request.init_code.clone(),
+            gas_limit: request.gas_limit,
+        };
+        if let Some(outcome) = inspector.create(self, &inputs) {
+            let result = FrameResult {
+                reason: outcome.reason,
+                output: outcome.output,
+                gas_remaining: outcome.gas_remaining,
+                gas_refund: 0,
+            };
+            let address = outcome.address.unwrap_or(Address::ZERO);
+            return self.end_create(address, &inputs, result, inspector);
+        }
+        // The address is only known once the nonce is taken; failures before that
+        // report the zero address, which the caller never sees
+        if depth > CALL_DEPTH_LIMIT {
+            let result = FrameResult::failed(ExitReason::CallTooDeep, request.gas_limit);
+            return self.end_create(Address::ZERO, &inputs, result, inspector);
+        }
+        if self.journal.balance(&caller) < request.value {
+            let result = FrameResult::failed(ExitReason::OutOfFunds, request.gas_limit);
+            return self.end_create(Address::ZERO, &inputs, result, inspector);
+        }
+        let Some(nonce) = self.journal.increment_nonce(caller) else {
+            let result = FrameResult::failed(ExitReason::NonceOverflow, request.gas_limit);
+            return self.end_create(Address::ZERO, &inputs, result, inspector);
+        };
+
+        let address = match request.scheme {
+            CreateScheme::Create => caller.create(nonce),
+            CreateScheme::Create2 { salt } => {
+                caller.create2_from_code(B256::from(salt), &request.init_code)
+            }
+        };
+        let kind = FrameKind::Create {
+            scheme: request.scheme,
+            address,
+        };
+        self.journal.warm_account(address);
+        let collides = self.journal.account(&address).is_some_and(|account| {
+            account.nonce != 0 || !account.code.is_empty() || !account.storage.is_empty()
+        });
+        if collides {
+            let result = FrameResult::failed(ExitReason::CreateCollision, request.gas_limit);
+            return self.end_create(address, &inputs, result, inspector);
+        }
+
+        let checkpoint = self.journal.checkpoint();
+        self.journal.touch(address);
+        if spec.is_enabled_in(SpecId::SpuriousDragon) {
+            // EIP-161: contracts start with nonce 1
+            self.journal.set_nonce(address, 1);
+        }
+        self.journal.transfer(caller, address, request.value);
+        self.journal.mark_created(address);
+
+        let context = CallContext {
+            caller,
+            address,
+            code_address: address,
+            value: request.value,
+            input: Bytes::new(),
+            is_static: false,
+        };
+        let gas = self.gas_meter(request.gas_limit);
+        let frame = Frame::new(kind, context, request.init_code, gas, checkpoint, depth);
+        let is_empty = frame.code().is_empty();
+        self.push_frame(frame);
+        if is_empty {
+            // Nothing to run: deploy empty code right away
+            self.exit_frame(ExitReason::Stop, Bytes::new(), inspector);
+        }
+    }
+
+    fn gas_meter(&self, gas_limit: u64) -> GasMeter {
+        match self.metering {
+            Metering::Unmetered => GasMeter::unmetered(gas_limit, self.schedule.clone()),
+            Metering::PerInstruction | Metering::PerBlock => {
+                GasMeter::with_schedule(gas_limit, self.schedule.clone())
+            }
+        }
+    }
+
+    fn push_frame(&mut self, frame: Frame) {
+        let frame = frame.with_memory_limit(self.memory_limit);
+        let frame = match self.metering {
+            Metering::PerBlock => frame.with_block_metering(),
+            Metering::PerInstruction | Metering::Unmetered => frame,
+        };
+        self.frames.push(frame);
+    }
+
+    /// Pops the executing frame and hands its result to the caller.
+    fn exit_frame(&mut self, reason: ExitReason, output: Bytes, inspector: &mut dyn Inspector) {
+        let mut frame = self
+            .frames
+            .pop()
+            .expect("exit_frame is only called with a frame executing");
+        let mut reason = reason;
+        if let FrameKind::Create { address, .. } = frame.kind() {
+            if reason.is_success() {
+                reason = self.deploy(&mut frame, address, &output);
+            }
+        }
+        let result = FrameResult {
+            reason,
+            output,
+            gas_remaining: if reason.is_halt() {
+                0
+            } else {
+                frame.gas().remaining_gas()
+            },
+            gas_refund: if reason.is_success() {
+                frame.gas().gas_refund()
+            } else {
+                0
+            },
+        };
+        if !reason.is_success() {
+            self.journal.revert(frame.checkpoint());
+        }
+        let context = frame.context();
+        match frame.kind() {
+            FrameKind::Call { scheme, .. } => {
+                let inputs = CallInputs {
+                    scheme,
+                    context: context.clone(),
+                    gas_limit: frame.gas().gas_limit(),
+                };
+                self.end_call(frame.kind(), &inputs, result, inspector);
+            }
+            FrameKind::Create { scheme, address } => {
+                let inputs = CreateInputs {
+                    scheme,
+                    caller: context.caller,
+                    value: context.value,
+                    init_code: frame.code().clone(),
+                    gas_limit: frame.gas().gas_limit(),
+                };
+                self.end_create(address, &inputs, result, inspector);
+            }
+        }
+        if self.frames.is_empty() {
+            self.last_frame = Some(frame);
+        }
+    }
+
+    /// Charges for and stores the code returned by init code.
+    ///
+    /// Returns the frame's final exit reason, which is an exceptional halt if the code
+    /// cannot be deployed.
+    fn deploy(&mut self, frame: &mut Frame, address: Address, code: &Bytes) -> ExitReason {
+        let spec = self.spec();
+        if spec.is_enabled_in(SpecId::SpuriousDragon) && code.len() > MAX_CODE_SIZE {
+            return ExitReason::CodeSizeLimit;
+        }
+        if spec.is_enabled_in(SpecId::London) && code.first() == Some(&0xef) {
+            return ExitReason::InvalidCodePrefix;
+        }
+        let deposit_cost = frame
+            .gas()
+            .schedule()
+            .code_deposit_cost
+            .saturating_mul(code.len() as u64);
+        if frame.gas_mut().consume_gas(deposit_cost).is_err() {
+            // Before Homestead an unaffordable deposit just left the contract without code
+            if spec.is_enabled_in(SpecId::Homestead) {
+                return ExitReason::CodeStoreOutOfGas;
+            }
+            return ExitReason::Stop;
+        }
+        self.journal.set_code(address, code.clone());
+        ExitReason::Return
+    }
+
+    /// Delivers a finished frame's result to its caller, or finishes the transaction if
+    /// it was the outermost frame.
+    fn resume(&mut self, kind: FrameKind, result: FrameResult, inspector: &mut dyn Inspector) {
+        let Some(parent) = self.frames.last_mut() else {
+            return self.finish(kind, result);
+        };
+        let success = result.reason.is_success();
+        parent.gas_mut().return_gas(result.gas_remaining);
+        parent.gas_mut().record_refund(result.gas_refund);
+        let outcome = match kind {
+            FrameKind::Call {
+                ret_offset,
+                ret_len,
+                ..
+            } => parent.finish_call(success, result.output, ret_offset, ret_len),
+            FrameKind::Create { address, .. } => {
+                let revert_output = if result.reason.is_revert() {
+                    result.output
+                } else {
+                    Bytes::new()
+                };
+                parent.finish_create(success.then_some(address), revert_output)
+            }
+        };
+        if let Err(reason) = outcome {
+            self.exit_frame(reason, Bytes::new(), inspector);
+        }
+    }
+
+    /// Shows a finished message call to the inspector, then delivers its result.
+    fn end_call(
+        &mut self,
+        kind: FrameKind,
+        inputs: &CallInputs,
+        result: FrameResult,
+        inspector: &mut dyn Inspector,
+    ) {
+        let mut outcome = CallOutcome {
+            reason: result.reason,
+            output: result.output,
+            gas_remaining: result.gas_remaining,
+        };
+        inspector.call_end(self, inputs, &mut outcome);
+        let result = FrameResult {
+            reason: outcome.reason,
+            output: outcome.output,
+            gas_remaining: outcome.gas_remaining.min(inputs.gas_limit),
+            gas_refund: if outcome.reason.is_success() {
+                result.gas_refund
+            } else {
+                0
+            },
+        };
+        self.resume(kind, result, inspector);
+    }
+
+    /// Shows a finished contract creation to the inspector, then delivers its result.
+    fn end_create(
+        &mut self,
+        address: Address,
+        inputs: &CreateInputs,
+        result: FrameResult,
+        inspector: &mut dyn Inspector,
+    ) {
+        let mut outcome = CreateOutcome {
+            reason: result.reason,
+            output: result.output,
+            gas_remaining: result.gas_remaining,
+            address: result.reason.is_success().then_some(address),
+        };
+        inspector.create_end(self, inputs, &mut outcome);
+        let kind = FrameKind::Create {
+            scheme: inputs.scheme,
+            address: outcome.address.unwrap_or(Address::ZERO),
+        };
+        let result = FrameResult {
+            reason: outcome.reason,
+            output: outcome.output,
+            gas_remaining: outcome.gas_remaining.min(inputs.gas_limit),
+            gas_refund: if outcome.reason.is_success() {
+                result.gas_refund
+            } else {
+                0
+            },
+        };
+        self.resume(kind, result, inspector);
+    }
+
+    /// Settles gas and fees once the outermost frame has finished.
+    fn finish(&mut self, kind: FrameKind, result: FrameResult) {
+        let spec = self.spec();
+        let tx = &self.tx;
+        let mut meter = GasMeter::with_schedule(tx.gas_limit, self.schedule.clone());
+        meter
+            .consume_gas(tx.gas_limit - result.gas_remaining)
+            .expect("frames never use more gas than the transaction provides");
+        meter.record_refund(result.gas_refund);
+        let gas = meter.settle(self.floor_gas);
+
+        // Return unused gas to the sender and pay the priority fee to the coinbase
+        let caller = tx.caller;
+        let refund = U256::from(gas.gas_returned).saturating_mul(tx.gas_price);
+        let balance = self.journal.balance(&caller);
+        self.journal
+            .set_balance(caller, balance.saturating_add(refund));
+        let tip = if spec.is_enabled_in(SpecId::London) {
+            tx.gas_price.saturating_sub(self.block.basefee)
+        } else {
+            tx.gas_price
+        };
+        let fee = U256::from(gas.gas_used).saturating_mul(tip);
+        if !fee.is_zero() {
+            let coinbase = self.block.coinbase;
+            let balance = self.journal.balance(&coinbase);
+            self.journal
+                .set_balance(coinbase, balance.saturating_add(fee));
+        }
+
+        let created_address = match kind {
+            FrameKind::Create { address, .. } if result.reason.is_success() => Some(address),
+            _ => None,
+        };
+        self.result = Some(ExecutionResult {
+            exit_reason: result.reason,
+            gas,
+            output: result.output,
+            logs: self.journal.finalize(spec),
+            created_address,
+        });
+    }
+}
+
+/// Runs a precompiled contract.
+fn run_precompile(address: Address, input: &Bytes, gas_limit: u64) -> FrameResult {
+    if address != Address::with_last_byte(0x04) {
+        return FrameResult::failed(ExitReason::PrecompileFailure, gas_limit);
+    }
+    // Identity: 15 gas plus 3 per word
+    let cost = 15 + 3 * (input.len() as u64).div_ceil(32);
+    if cost > gas_limit {
+        return FrameResult::failed(ExitReason::OutOfGas, gas_limit);
+    }
+    FrameResult {
+        reason: ExitReason::Return,
+        output: input.clone(),
+        gas_remaining: gas_limit - cost,
+        gas_refund: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::storage::Account;
+    use alloy_primitives::I256;
+    use std::collections::HashSet;
+
+    const CALLER: Address = Address::with_last_byte(0xca);
+    const CONTRACT: Address = Address::with_last_byte(0xc0);
+
+    fn evm_with_code(spec: SpecId, code: &[u8]) -> Evm {
+        let mut state = WorldState::new();
+        state.insert_account(CALLER, Account::with_balance(U256::from(10u64.pow(18))));
+        state.insert_account(CONTRACT, Account::with_code(Bytes::copy_from_slice(code)));
+        Evm::with_spec(spec).with_state(state)
+    }
+
+    fn call(gas_limit: u64) -> TxEnv {
+        TxEnv {
+            caller: CALLER,
+            to: Some(CONTRACT),
+            gas_limit,
+            ..TxEnv::default()
+        }
+    }
+
+    #[test]
+    fn test_arithmetic_and_return() {
+        // PUSH1 2 PUSH1 3 ADD PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+        let code = [
+            0x60, 2, 0x60, 3, 0x01, 0x60, 0, 0x52, 0x60, 32, 0x60, 0, 0xf3,
+        ];
+        let mut evm = evm_with_code(SpecId::Cancun, &code);
+        let result = evm.transact(call(100_000)).unwrap();
+        assert_eq!(result.exit_reason, ExitReason::Return);
+        assert_eq!(U256::from_be_slice(&result.output), U256::from(5));
+        // 21000 intrinsic + 4 PUSH1 (3 each) + ADD (3) + MSTORE (3 + 3 memory) + 2 PUSH1
+        assert_eq!(result.gas.gas_used, 21000 + 7 * 3 + 3);
+    }
+
+    #[test]
+    fn test_signed_arithmetic() {
+        // PUSH1 2 PUSH32 -8 SDIV (-4) PUSH1 0 SGT (0 > -4 = 1) PUSH1 0 SSTORE
+        let mut code = vec![0x60, 2, 0x7f];
+        code.extend_from_slice(&I256::try_from(-8).unwrap().to_be_bytes::<32>());
+        code.extend_from_slice(&[0x05, 0x60, 0, 0x13, 0x60, 0, 0x55]);
+        let mut evm = evm_with_code(SpecId::Cancun, &code);
+        let result = evm.transact(call(100_000)).unwrap();
+        assert!(result.is_success());
+        assert_eq!(evm.state().storage(&CONTRACT, &B256::ZERO), U256::from(1));
+    }
+
+    #[test]
+    fn test_sstore_refund_and_revert() {
+        // PUSH1 1 PUSH1 0 SSTORE PUSH1 0 PUSH1 0 REVERT
+        let code = [0x60, 1, 0x60, 0, 0x55, 0x60, 0, 0x60, 0, 0xfd];
+        let mut evm = evm_with_code(SpecId::Cancun, &code);
+        let result = evm.transact(call(100_000)).unwrap();
+        assert_eq!(result.exit_reason, ExitReason::Revert);
+        assert_eq!(evm.state().storage(&CONTRACT, &B256::ZERO), U256::ZERO);
+        // 21000 + 2 PUSH1 + cold SSTORE (22100) + 2 PUSH1, no refund after a revert
+        assert_eq!(result.gas.gas_used, 21000 + 6 + 22100 + 6);
+        assert_eq!(evm.state().account(&CALLER).unwrap().nonce, 1);
+    }
+
+    #[test]
+    fn test_halt_consumes_all_gas() {
+        // PUSH1 3 JUMP (not a JUMPDEST)
+        let mut evm = evm_with_code(SpecId::Cancun, &[0x60, 3, 0x56, 0x00]);
+        let result = evm.transact(call(50_000)).unwrap();
+        assert_eq!(result.exit_reason, ExitReason::InvalidJump);
+        assert_eq!(result.gas.gas_used, 50_000);
+
+        // PUSH0 is unknown before Shanghai
+        let mut evm = evm_with_code(SpecId::London, &[0x5f]);
+        let result = evm.transact(call(50_000)).unwrap();
+        assert_eq!(result.exit_reason, ExitReason::InvalidOpcode(0x5f));
+    }
+
+    #[test]
+    fn test_step_reports() {
+        // PUSH1 0 MLOAD POP STOP
+        let mut evm = evm_with_code(SpecId::Cancun, &[0x60, 0, 0x51, 0x50, 0x00]);
+        evm.begin(call(100_000)).unwrap();
+        let steps: Vec<_> = std::iter::from_fn(|| evm.step()).collect();
+        let pcs: Vec<_> = steps.iter().map(|step| step.pc).collect();
+        assert_eq!(pcs, vec![0, 2, 3, 4]);
+        assert_eq!(steps[1].opcode, Some(Opcode::Mload));
+        assert_eq!(steps[1].gas.static_gas, 3);
+        assert_eq!(steps[1].gas.memory_gas, 3);
+        assert_eq!(steps[0].gas_remaining - steps[1].gas_remaining, 3);
+        assert!(evm.take_result().unwrap().is_success());
+    }
+
+    #[test]
+    fn test_nested_call_and_create() {
+        // Init code deploys runtime 0x60_2a_60_00_52_60_20_60_00_f3 (returns 42)
+        let runtime = [0x60, 0x2a, 0x60, 0, 0x52, 0x60, 32, 0x60, 0, 0xf3];
+        let mut init = vec![0x69];
+        init.extend_from_slice(&runtime);
+        // PUSH10 runtime PUSH1 0 MSTORE PUSH1 10 PUSH1 22 RETURN
+        init.extend_from_slice(&[0x60, 0, 0x52, 0x60, 10, 0x60, 22, 0xf3]);
+
+        let mut evm = evm_with_code(SpecId::Cancun, &[]);
+        let tx = TxEnv {
+            caller: CALLER,
+            to: None,
+            data: init.into(),
+            gas_limit: 200_000,
+            ..TxEnv::default()
+        };
+        let result = evm.transact(tx).unwrap();
+        assert!(result.is_success());
+        let deployed = result.created_address.unwrap();
+        assert_eq!(deployed, CALLER.create(0));
+        assert_eq!(
+            evm.state().account(&deployed).unwrap().code.as_ref(),
+            &runtime
+        );
+
+        // Call the deployed contract and return what it returned:
+        // PUSH1 32 PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH20 addr GAS CALL
+        // PUSH1 32 PUSH1 0 RETURN
+        let mut caller_code = vec![0x60, 32, 0x60, 0, 0x60, 0, 0x60, 0, 0x60, 0, 0x73];
+        caller_code.extend_from_slice(deployed.as_slice());
+        caller_code.extend_from_slice(&[0x5a, 0xf1, 0x60, 32, 0x60, 0, 0xf3]);
+        let mut state = evm.into_state();
+        state.insert_account(CONTRACT, Account::with_code(caller_code.into()));
+        let mut evm = Evm::with_spec(SpecId::Cancun).with_state(state);
+        let result = evm.transact(call(200_000)).unwrap();
+        assert!(result.is_success());
+        assert_eq!(U256::from_be_slice(&result.output), U256::from(42));
+    }
+
+    #[test]
+    fn test_code_deposit_cost_from_schedule() {
+        // PUSH1 0 PUSH1 0 MSTORE PUSH1 10 PUSH1 0 RETURN: deploys 10 zero bytes
+        let init = vec![0x60, 0, 0x60, 0, 0x52, 0x60, 10, 0x60, 0, 0xf3];
+        let deploy = |schedule: GasSchedule| {
+            let state = evm_with_code(SpecId::Cancun, &[]).into_state();
+            let mut evm = Evm::with_schedule(schedule).with_state(state);
+            let tx = TxEnv {
+                caller: CALLER,
+                to: None,
+                data: init.clone().into(),
+                gas_limit: 200_000,
+                ..TxEnv::default()
+            };
+            evm.transact(tx).unwrap().gas.gas_used
+        };
+
+        let mut free = GasSchedule::for_spec(SpecId::Cancun);
+        free.code_deposit_cost = 0;
+        assert_eq!(
+            deploy(GasSchedule::for_spec(SpecId::Cancun)) - deploy(free),
+            200 * 10
+        );
+    }
+
+    #[test]
+    fn test_transaction_validation() {
+        let mut evm = evm_with_code(SpecId::Cancun, &[]);
+        assert_eq!(
+            evm.transact(call(20_000)),
+            Err(TransactionError::IntrinsicGasTooLow {
+                required: 21000,
+                gas_limit: 20_000
+            })
+        );
+        let tx = TxEnv {
+            nonce: Some(3),
+            ..call(21_000)
+        };
+        assert_eq!(
+            evm.transact(tx),
+            Err(TransactionError::NonceMismatch {
+                expected: 3,
+                found: 0
+            })
+        );
+        let tx = TxEnv {
+            gas_price: U256::from(10u64.pow(18)),
+            ..call(21_000)
+        };
+        assert!(matches!(
+            evm.transact(tx),
+            Err(TransactionError::InsufficientFunds { .. })
+        ));
+    }
+
+    #[test]
+    fn test_fees() {
+        let mut evm = evm_with_code(SpecId::Cancun, &[]).with_block(BlockEnv {
+            coinbase: Address::with_last_byte(0xcb),
+            basefee: U256::from(7),
+            ..BlockEnv::default()
+        });
+        let tx = TxEnv {
+            gas_price: U256::from(10),
+            value: U256::from(1),
+            ..call(30_000)
+        };
+        let result = evm.transact(tx).unwrap();
+        assert_eq!(result.gas.gas_used, 21000);
+        let state = evm.state();
+        assert_eq!(
+            state.account(&CALLER).unwrap().balance,
+            U256::from(10u64.pow(18) - 21000 * 10 - 1)
+        );
+        assert_eq!(
+            state
+                .account(&Address::with_last_byte(0xcb))
+                .unwrap()
+                .balance,
+            U256::from(21000 * 3)
+        );
+        assert_eq!(state.account(&CONTRACT).unwrap().balance, U256::from(1));
+    }
+
+    /// Runs `tx` to completion, returning the result, every step and the final state.
+    /// Block metering reports less gas remaining mid-block, so that is left out.
+    fn run_traced(mut evm: Evm, tx: TxEnv) -> (ExecutionResult, Vec<StepReport>, WorldState) {
+        evm.begin(tx).unwrap();
+        let mut steps = Vec::new();
+        while let Some(step) = evm.step() {
+            steps.push(StepReport {
+                gas_remaining: 0,
+                ..step
+            });
+        }
+        let result = evm.take_result().unwrap();
+        (result, steps, evm.into_state())
+    }
+
+    /// Asserts that block metering behaves exactly like per-instruction metering at every
+    /// gas limit from the intrinsic cost up to `max_gas`.
+    fn assert_metering_parity(evm: &Evm, max_gas: u64) {
+        let mut outcomes = HashSet::new();
+        for gas_limit in 21000..=max_gas {
+            let per_instruction = run_traced(evm.clone(), call(gas_limit));
+            let per_block = run_traced(
+                evm.clone().with_metering(Metering::PerBlock),
+                call(gas_limit),
+            );
+            assert_eq!(per_instruction, per_block, "gas limit {gas_limit}");
+            outcomes.insert(per_block.0.exit_reason);
+        }
+        // The sweep must reach both failing and successful runs
+        assert!(outcomes.len() > 1, "{outcomes:?}");
+    }
+
+    #[test]
+    fn test_block_metering_loop() {
+        // i = 0; do { mstore(i << 5, i); i += 1 } while (10 > i)
+        // PUSH1 0 | JUMPDEST DUP1 DUP1 PUSH1 5 SHL MSTORE PUSH1 1 ADD DUP1 PUSH1 10 GT
+        // PUSH1 2 JUMPI | STOP
+        let code = [
+            0x60, 0, 0x5b, 0x80, 0x80, 0x60, 5, 0x1b, 0x52, 0x60, 1, 0x01, 0x80, 0x60, 10, 0x11,
+            0x60, 2, 0x57, 0x00,
+        ];
+        let evm = evm_with_code(SpecId::Cancun, &code);
+        assert_metering_parity(&evm, 21600);
+    }
+
+    #[test]
+    fn test_block_metering_dynamic_cost_then_other_halt() {
+        // Expanding memory costs more than the prepaid block leaves, and the next
+        // instruction underflows the stack: the halt must be the same either way
+        // PUSH1 0 PUSH2 0x1000 MSTORE POP STOP
+        let code = [0x60, 0, 0x61, 0x10, 0x00, 0x52, 0x50, 0x00];
+        let evm = evm_with_code(SpecId::Cancun, &code);
+        assert_metering_parity(&evm, 21500);
+    }
+
+    #[test]
+    fn test_block_metering_calls_and_gas() {
+        // Callee: PUSH1 1 PUSH1 0 SSTORE PUSH1 0 PUSH2 0x0400 MSTORE STOP
+        let callee = Address::with_last_byte(0xc1);
+        let callee_code = [
+            0x60, 1, 0x60, 0, 0x55, 0x60, 0, 0x61, 0x04, 0x00, 0x52, 0x00,
+        ];
+        // Caller: return GAS and the success of CALL(30000, callee)
+        // GAS PUSH1 32 MSTORE PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH20 callee
+        // PUSH2 30000 CALL PUSH1 0 MSTORE PUSH1 64 PUSH1 0 RETURN
+        let mut code = vec![
+            0x5a, 0x60, 32, 0x52, 0x60, 0, 0x60, 0, 0x60, 0, 0x60, 0, 0x60, 0,
+        ];
+        code.push(0x73);
+        code.extend_from_slice(callee.as_slice());
+        code.extend_from_slice(&[
+            0x61, 0x75, 0x30, 0xf1, 0x60, 0, 0x52, 0x60, 64, 0x60, 0, 0xf3,
+        ]);
+        let mut state = evm_with_code(SpecId::Cancun, &code).into_state();
+        state.insert_account(
+            callee,
+            Account::with_code(Bytes::copy_from_slice(&callee_code)),
+        );
+        let evm = Evm::with_spec(SpecId::Cancun).with_state(state);
+        assert_metering_parity(&evm, 21000 + 2600 + 22100 + 500);
+    }
+
+    #[test]
+    fn test_unmetered_step_limit() {
+        // An infinite loop of SSTOREs: JUMPDEST PUSH1 1 PUSH1 0 SSTORE PUSH1 0 JUMP
+        let code = [0x5b, 0x60, 1, 0x60, 0, 0x55, 0x60, 0, 0x56];
+        let mut evm = evm_with_code(SpecId::Cancun, &code)
+            .with_metering(Metering::Unmetered)
+            .with_step_limit(1000);
+        // Intrinsic gas is all the transaction needs
+        let result = evm.transact(call(21000)).unwrap();
+        assert_eq!(result.exit_reason, ExitReason::StepLimit);
+        assert_eq!(result.gas.gas_used, 21000);
+        assert_eq!(evm.steps(), 1000);
+        // The halt reverted the writes
+        assert_eq!(evm.state().storage(&CONTRACT, &B256::ZERO), U256::ZERO);
+    }
+
+    #[test]
+    fn test_unmetered_memory_limit() {
+        // Call a child with no gas that writes storage and memory, then return its
+        // success: PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH1 0 PUSH20 callee PUSH1 0 CALL
+        // PUSH1 0 MSTORE PUSH1 32 PUSH1 0 RETURN
+        let callee = Address::with_last_byte(0xc1);
+        let mut code = vec![0x60, 0, 0x60, 0, 0x60, 0, 0x60, 0, 0x60, 0, 0x73];
+        code.extend_from_slice(callee.as_slice());
+        code.extend_from_slice(&[0x60, 0, 0xf1, 0x60, 0, 0x52, 0x60, 32, 0x60, 0, 0xf3]);
+        let evm_with_callee = |callee_code: &[u8]| {
+            let mut state = evm_with_code(SpecId::Cancun, &code).into_state();
+            state.insert_account(
+                callee,
+                Account::with_code(Bytes::copy_from_slice(callee_code)),
+            );
+            Evm::with_spec(SpecId::Cancun)
+                .with_state(state)
+                .with_metering(Metering::Unmetered)
+                .with_memory_limit(4096)
+        };
+
+        // PUSH1 1 PUSH1 0 SSTORE PUSH1 1 PUSH2 0x0fe0 MSTORE STOP: just within the limit
+        let fits = [
+            0x60, 1, 0x60, 0, 0x55, 0x60, 1, 0x61, 0x0f, 0xe0, 0x52, 0x00,
+        ];
+        let mut evm = evm_with_callee(&fits);
+        let result = evm.transact(call(21000)).unwrap();
+        assert_eq!(U256::from_be_slice(&result.output), U256::from(1));
+        assert_eq!(evm.state().storage(&callee, &B256::ZERO), U256::from(1));
+
+        // PUSH1 1 PUSH2 0x0fe1 MSTORE STOP: one byte too far
+        let too_large = [0x60, 1, 0x61, 0x0f, 0xe1, 0x52, 0x00];
+        let mut evm = evm_with_callee(&too_large);
+        evm.begin(call(21000)).unwrap();
+        let mut errors = Vec::new();
+        while let Some(step) = evm.step() {
+            errors.extend(step.error);
+            assert_eq!(step.gas.total(), 0);
+        }
+        assert_eq!(errors, [ExitReason::MemoryLimit]);
+        let result = evm.take_result().unwrap();
+        assert_eq!(U256::from_be_slice(&result.output), U256::ZERO);
+    }
+}
@@ -0,0 +1,1066 @@
+//! EVM Call Frames and Instructions
+//!
+//! A [`Frame`] is one level of the call stack: the code being run, its program counter,
+//! stack, memory and gas meter. This module also implements the instruction set. Each
+//! instruction either completes within its frame or returns an [`Action`] asking the
+//! interpreter to open a sub-call, create a contract, or leave the frame.
+//!
+//! # Design
+//! - Frames never recurse; the interpreter keeps an explicit stack of them
+//! - Base costs are charged by the interpreter before an instruction runs; instructions
+//!   charge their dynamic and memory expansion costs themselves
+//! - Memory is charged for *before* it is expanded, in whole words
+//!
+//! # References
+//! - [Ethereum Yellow Paper, Appendix H (Virtual Machine Specification)]
+//! - [evm.codes](https://www.evm.codes/)
+
+use alloy_primitives::{keccak256, I256};
+
+use super::analysis::{BlockGasTable, JumpTable};
+use super::env::{BlockEnv, TxEnv};
+use super::execution::{ExitReason, StepGas};
+use super::gas::{DynamicGasParams, GasError, GasMeter};
+use super::journal::{Journal, JournalCheckpoint};
+use super::memory::{Memory, MEMORY_MAX_SIZE};
+use super::opcodes::Opcode;
+use super::spec::SpecId;
+use super::stack::{Stack, StackError};
+use crate::types::{Address, Bytes, Log, StorageKey, B256, U256};
+
+/// Largest memory offset an instruction may touch. Anything beyond costs more gas than
+/// can exist, so it is rejected as out of gas before pricing overflows.
+const MAX_MEMORY_OFFSET: usize = u32::MAX as usize;
+
+/// Maximum size of init code (EIP-3860).
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
+
+/// Maximum size of deployed code (EIP-170).
+pub const MAX_CODE_SIZE: usize = 0x6000;
+
+/// How a message call was made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CallScheme {
+    Call,
+    Callcode,
+    Delegatecall,
+    Staticcall,
+}
+
+/// How a contract creation was made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum CreateScheme {
+    Create,
+    Create2 { salt: U256 },
+}
+
+/// The kind of frame, and what the parent does with its result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    /// A message call; the output is copied to `ret_offset..ret_offset + ret_len` in the
+    /// parent's memory.
+    Call {
+        scheme: CallScheme,
+        ret_offset: usize,
+        ret_len: usize,
+    },
+    /// A contract creation; on success the output is deployed at `address`.
+    Create {
+        scheme: CreateScheme,
+        address: Address,
+    },
+}
+
+/// The addresses and inputs a frame runs with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallContext {
+    /// Sender of the message (`CALLER`).
+    pub caller: Address,
+    /// Account whose storage and balance the code acts on (`ADDRESS`).
+    pub address: Address,
+    /// Account the code was loaded from; differs from `address` for `CALLCODE` and
+    /// `DELEGATECALL`.
+    pub code_address: Address,
+    /// Apparent value of the message (`CALLVALUE`).
+    pub value: U256,
+    /// Calldata; empty for contract creation.
+    pub input: Bytes,
+    /// `true` if state modifications are forbidden (`STATICCALL`, EIP-214).
+    pub is_static: bool,
+}
+
+/// A request from an instruction to open a message call.
+#[derive(Debug, Clone)]
+pub(crate) struct CallRequest {
+    pub scheme: CallScheme,
+    pub context: CallContext,
+    /// Value moved from `caller` to `address`; only `CALL` transfers.
+    pub transfer: U256,
+    /// Gas given to the callee, including any stipend.
+    pub gas_limit: u64,
+    pub ret_offset: usize,
+    pub ret_len: usize,
+}
+
+/// A request from an instruction to create a contract.
+#[derive(Debug, Clone)]
+pub(crate) struct CreateRequest {
+    pub scheme: CreateScheme,
+    pub caller: Address,
+    pub value: U256,
+    pub init_code: Bytes,
+    pub gas_limit: u64,
+}
+
+/// What the interpreter should do after an instruction.
+#[derive(Debug)]
+pub(crate) enum Action {
+    /// Continue with the next instruction of the frame.
+    Continue,
+    /// Open a message call.
+    Call(CallRequest),
+    /// Create a contract.
+    Create(CreateRequest),
+    /// Leave the frame with the given reason and output.
+    Exit(ExitReason, Bytes),
+}
+
+/// The parts of the interpreter an instruction may read or modify besides its frame.
+pub(crate) struct Host<'a> {
+    pub journal: &'a mut Journal,
+    pub block: &'a BlockEnv,
+    pub tx: &'a TxEnv,
+}
+
+/// One level of the EVM call stack.
+#[derive(Debug, Clone)]
+pub struct Frame {
+    kind: FrameKind,
+    context: CallContext,
+    code: Bytes,
+    jump_table: JumpTable,
+    /// Per-block static gas, when base costs are charged once per basic block.
+    block_gas: Option<BlockGasTable>,
+    /// Static gas already charged for the current block's instructions that have not
+    /// run yet; `None` while base costs are charged per instruction.
+    prepaid_gas: Option<u64>,
+    pc: usize,
+    stack: Stack,
+    memory: Memory,
+    /// Size memory may not grow past, in bytes.
+    memory_limit: usize,
+    gas: GasMeter,
+    /// Output of the most recent sub-call (`RETURNDATASIZE`, EIP-211).
+    return_data: Bytes,
+    /// Journal position to revert to if the frame fails.
+    checkpoint: JournalCheckpoint,
+    /// Call depth, 0 for the transaction's outermost frame.
+    depth: usize,
+}
+
+impl Frame {
+    /// Creates a frame about to run `code` from its first instruction.
+    pub(crate) fn new(
+        kind: FrameKind,
+        context: CallContext,
+        code: Bytes,
+        gas: GasMeter,
+        checkpoint: JournalCheckpoint,
+        depth: usize,
+    ) -> Self {
+        Self {
+            kind,
+            context,
+            jump_table: JumpTable::analyze(&code),
+            block_gas: None,
+            prepaid_gas: None,
+            code,
+            pc: 0,
+            stack: Stack::new(),
+            memory: Memory::new(),
+            memory_limit: MEMORY_MAX_SIZE,
+            gas,
+            return_data: Bytes::new(),
+            checkpoint,
+            depth,
+        }
+    }
+
+    /// Charges base costs once per basic block instead of once per instruction.
+    pub(crate) fn with_block_metering(mut self) -> Self {
+        self.block_gas = Some(BlockGasTable::analyze(&self.code, self.gas.schedule()));
+        self
+    }
+
+    /// Lowers the size memory may grow to, in bytes.
+    pub(crate) fn with_memory_limit(mut self, memory_limit: usize) -> Self {
+        self.memory_limit = memory_limit.min(MEMORY_MAX_SIZE);
+        self
+    }
+
+    /// Returns the kind of frame.
+    pub fn kind(&self) -> FrameKind {
+        self.kind
+    }
+
+    /// Returns the addresses and inputs the frame runs with.
+    pub fn context(&self) -> &CallContext {
+        &self.context
+    }
+
+    /// Returns the code being executed.
+    pub fn code(&self) -> &Bytes {
+        &self.code
+    }
+
+    /// Returns the code's valid jump destinations.
+    pub fn jump_table(&self) -> &JumpTable {
+        &self.jump_table
+    }
+
+    /// Returns the program counter of the next instruction.
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// Returns the frame's stack.
+    pub fn stack(&self) -> &Stack {
+        &self.stack
+    }
+
+    /// Returns the frame's memory.
+    pub fn memory(&self) -> &Memory {
+        &self.memory
+    }
+
+    /// Returns the frame's gas meter.
+    pub fn gas(&self) -> &GasMeter {
+        &self.gas
+    }
+
+    /// Returns the output of the most recent sub-call.
+    pub fn return_data(&self) -> &Bytes {
+        &self.return_data
+    }
+
+    /// Returns the call depth, 0 for the outermost frame.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub(crate) fn gas_mut(&mut self) -> &mut GasMeter {
+        &mut self.gas
+    }
+
+    pub(crate) fn checkpoint(&self) -> JournalCheckpoint {
+        self.checkpoint
+    }
+
+    /// Returns the byte at the program counter; running off the end of the code is an
+    /// implicit `STOP`.
+    pub(crate) fn current_byte(&self) -> u8 {
+        self.code.get(self.pc).copied().unwrap_or(0x00)
+    }
+
+    /// Charges the base cost of `opcode`, about to run at the current `pc`, and returns it.
+    ///
+    /// With block metering, entering a block charges the whole block's base cost and the
+    /// block's instructions then draw on it. A block that cannot be paid up front is
+    /// charged per instruction instead, so running out of gas halts at the same
+    /// instruction either way.
+    pub(crate) fn charge_static(&mut self, opcode: Opcode) -> Result<u64, ExitReason> {
+        let cost = self.gas.opcode_cost(opcode);
+        if let Some(block_cost) = self.block_gas.as_ref().and_then(|t| t.block_cost(self.pc)) {
+            self.prepaid_gas = self.gas.consume_gas(block_cost).ok().map(|_| block_cost);
+        }
+        match self.prepaid_gas.as_mut() {
+            Some(prepaid) => {
+                debug_assert!(cost <= *prepaid, "block gas covers its instructions");
+                *prepaid -= cost;
+            }
+            None => self.gas.consume_gas(cost).map_err(out_of_gas)?,
+        }
+        Ok(cost)
+    }
+
+    /// Completes a message call made by this frame.
+    pub(crate) fn finish_call(
+        &mut self,
+        success: bool,
+        output: Bytes,
+        ret_offset: usize,
+        ret_len: usize,
+    ) -> Result<(), ExitReason> {
+        let len = ret_len.min(output.len());
+        if len > 0 {
+            // The range was charged for and expanded when the call was made
+            self.memory
+                .write_bytes(ret_offset, &output[..len])
+                .map_err(|_| ExitReason::MemoryLimit)?;
+        }
+        self.return_data = output;
+        self.push(U256::from(success as u8))
+    }
+
+    /// Completes a contract creation made by this frame. `address` is `None` if the
+    /// creation failed; the output is kept as return data only if it reverted.
+    pub(crate) fn finish_create(
+        &mut self,
+        address: Option<Address>,
+        revert_output: Bytes,
+    ) -> Result<(), ExitReason> {
+        self.return_data = revert_output;
+        self.push(address.map(address_to_word).unwrap_or_default())
+    }
+
+    /// Executes `opcode`, whose base cost has already been charged.
+    pub(crate) fn execute(
+        &mut self,
+        opcode: Opcode,
+        host: &mut Host<'_>,
+        step_gas: &mut StepGas,
+    ) -> Result<Action, ExitReason> {
+        let spec = self.gas.spec();
+        let pc = self.pc;
+        self.pc += 1 + opcode.immediate_size();
+
+        match opcode {
+            // Stop and arithmetic operations
+            Opcode::Stop => return Ok(Action::Exit(ExitReason::Stop, Bytes::new())),
+            Opcode::Add => self.binary(|a, b| a.wrapping_add(b))?,
+            Opcode::Mul => self.binary(|a, b| a.wrapping_mul(b))?,
+            Opcode::Sub => self.binary(|a, b| a.wrapping_sub(b))?,
+            Opcode::Div => self.binary(|a, b| a.checked_div(b).unwrap_or_default())?,
+            Opcode::Sdiv => self.binary(|a, b| {
+                if b.is_zero() {
+                    U256::ZERO
+                } else {
+                    I256::from_raw(a).wrapping_div(I256::from_raw(b)).into_raw()
+                }
+            })?,
+            Opcode::Mod => self.binary(|a, b| a.checked_rem(b).unwrap_or_default())?,
+            Opcode::Smod => self.binary(|a, b| {
+                if b.is_zero() {
+                    U256::ZERO
+                } else {
+                    I256::from_raw(a).wrapping_rem(I256::from_raw(b)).into_raw()
+                }
+            })?,
+            Opcode::Addmod => {
+                let (a, b, n) = (self.pop()?, self.pop()?, self.pop()?);
+                self.push(a.add_mod(b, n))?;
+            }
+            Opcode::Mulmod => {
+                let (a, b, n) = (self.pop()?, self.pop()?, self.pop()?);
+                self.push(a.mul_mod(b, n))?;
+            }
+            Opcode::Exp => {
+                let (base, exponent) = (self.pop()?, self.pop()?);
+                let params = DynamicGasParams::new().with_exponent(exponent);
+                self.charge_dynamic(opcode, &params, step_gas)?;
+                self.push(base.wrapping_pow(exponent))?;
+            }
+            Opcode::Signextend => self.binary(|b, x| {
+                if b >= U256::from(31) {
+                    return x;
+                }
+                let bit = b.to::<usize>() * 8 + 7;
+                let mask = (U256::from(1) << bit) - U256::from(1);
+                if x.bit(bit) {
+                    x | !mask
+                } else {
+                    x & mask
+                }
+            })?,
+
+            // Comparison operations
+            Opcode::Lt => self.binary(|a, b| U256::from(a < b))?,
+            Opcode::Gt => self.binary(|a, b| U256::from(a > b))?,
+            Opcode::Slt => self.binary(|a, b| U256::from(I256::from_raw(a) < I256::from_raw(b)))?,
+            Opcode::Sgt => self.binary(|a, b| U256::from(I256::from_raw(a) > I256::from_raw(b)))?,
+            Opcode::Eq => self.binary(|a, b| U256::from(a == b))?,
+            Opcode::Iszero => {
+                let a = self.pop()?;
+                self.push(U256::from(a.is_zero()))?;
+            }
+
+            // Bitwise operations
+            Opcode::And => self.binary(|a, b| a & b)?,
+            Opcode::Or => self.binary(|a, b| a | b)?,
+            Opcode::Xor => self.binary(|a, b| a ^ b)?,
+            Opcode::Not => {
+                let a = self.pop()?;
+                self.push(!a)?;
+            }
+            Opcode::Byte => self.binary(|i, x| {
+                if i >= U256::from(32) {
+                    U256::ZERO
+                } else {
+                    U256::from(x.byte(31 - i.to::<usize>()))
+                }
+            })?,
+            Opcode::Shl => self.binary(|shift, value| {
+                if shift >= U256::from(256) {
+                    U256::ZERO
+                } else {
+                    value << shift.to::<usize>()
+                }
+            })?,
+            Opcode::Shr => self.binary(|shift, value| {
+                if shift >= U256::from(256) {
+                    U256::ZERO
+                } else {
+                    value >> shift.to::<usize>()
+                }
+            })?,
+            Opcode::Sar => self.binary(|shift, value| {
+                let value = I256::from_raw(value);
+                if shift >= U256::from(256) {
+                    if value.is_negative() {
+                        U256::MAX
+                    } else {
+                        U256::ZERO
+                    }
+                } else {
+                    value.asr(shift.to::<usize>()).into_raw()
+                }
+            })?,
+
+            // Cryptographic operations
+            Opcode::Keccak256 => {
+                let (offset, size) = (self.pop()?, self.pop()?);
+                let (offset, size) = self.expand_memory(offset, size, step_gas)?;
+                let params = DynamicGasParams::new().with_size(size);
+                self.charge_dynamic(opcode, &params, step_gas)?;
+                let data = self.read_memory(offset, size)?;
+                self.push(U256::from_be_bytes(keccak256(&data).0))?;
+            }
+
+            // Environment information
+            Opcode::Address => self.push(address_to_word(self.context.address))?,
+            Opcode::Balance => {
+                let address = word_to_address(self.pop()?);
+                self.charge_account_access(opcode, address, host, step_gas)?;
+                self.push(host.journal.balance(&address))?;
+            }
+            Opcode::Origin => self.push(address_to_word(host.tx.caller))?,
+            Opcode::Caller => self.push(address_to_word(self.context.caller))?,
+            Opcode::Callvalue => self.push(self.context.value)?,
+            Opcode::Calldataload => {
+                let offset = self.pop()?;
+                let word = padded_slice(&self.context.input, offset, 32);
+                self.push(U256::from_be_slice(&word))?;
+            }
+            Opcode::Calldatasize => self.push(U256::from(self.context.input.len()))?,
+            Opcode::Calldatacopy => {
+                let (dest, offset, size) = (self.pop()?, self.pop()?, self.pop()?);
+                let input = self.context.input.clone();
+                self.copy_to_memory(opcode, &input, dest, offset, size, step_gas)?;
+            }
+            Opcode::Codesize => self.push(U256::from(self.code.len()))?,
+            Opcode::Codecopy => {
+                let (dest, offset, size) = (self.pop()?, self.pop()?, self.pop()?);
+                let code = self.code.clone();
+                self.copy_to_memory(opcode, &code, dest, offset, size, step_gas)?;
+            }
+            Opcode::Gasprice => self.push(host.tx.gas_price)?,
+            Opcode::Extcodesize => {
+                let address = word_to_address(self.pop()?);
+                self.charge_account_access(opcode, address, host, step_gas)?;
+                self.push(U256::from(host.journal.code(&address).len()))?;
+            }
+            Opcode::Extcodecopy => {
+                let address = word_to_address(self.pop()?);
+                let (dest, offset, size) = (self.pop()?, self.pop()?, self.pop()?);
+                self.charge_account_access(opcode, address, host, step_gas)?;
+                let code = host.journal.code(&address);
+                self.copy_to_memory(opcode, &code, dest, offset, size, step_gas)?;
+            }
+            Opcode::Returndatasize => self.push(U256::from(self.return_data.len()))?,
+            Opcode::Returndatacopy => {
+                let (dest, offset, size) = (self.pop()?, self.pop()?, self.pop()?);
+                // Unlike the other copies, reading past the return data is an error (EIP-211)
+                let end = offset.checked_add(size);
+                if end.is_none_or(|end| end > U256::from(self.return_data.len())) {
+                    return Err(ExitReason::ReturnDataOutOfBounds);
+                }
+                let return_data = self.return_data.clone();
+                self.copy_to_memory(opcode, &return_data, dest, offset, size, step_gas)?;
+            }
+            Opcode::Extcodehash => {
+                let address = word_to_address(self.pop()?);
+                self.charge_account_access(opcode, address, host, step_gas)?;
+                let hash = host.journal.code_hash(&address);
+                self.push(U256::from_be_bytes(hash.0))?;
+            }
+            Opcode::Blockhash => {
+                let number = self.pop()?;
+                self.push(U256::from_be_bytes(host.block.block_hash(number).0))?;
+            }
+            Opcode::Coinbase => self.push(address_to_word(host.block.coinbase))?,
+            Opcode::Timestamp => self.push(U256::from(host.block.timestamp))?,
+            Opcode::Number => self.push(U256::from(host.block.number))?,
+            Opcode::Difficulty => {
+                // PREVRANDAO from Paris (EIP-4399)
+                if spec.is_enabled_in(SpecId::Paris) {
+                    self.push(U256::from_be_bytes(host.block.prevrandao.0))?;
+                } else {
+                    self.push(host.block.difficulty)?;
+                }
+            }
+            Opcode::Gaslimit => self.push(U256::from(host.block.gas_limit))?,
+            Opcode::Chainid => self.push(U256::from(host.block.chain_id))?,
+            Opcode::Selfbalance => {
+                let balance = host.journal.balance(&self.context.address);
+                self.push(balance)?;
+            }
+            Opcode::Basefee => self.push(host.block.basefee)?,
+            Opcode::Blobhash => {
+                let index = self.pop()?;
+                let hash = usize::try_from(index)
+                    .ok()
+                    .and_then(|index| host.tx.blob_hashes.get(index))
+                    .map(|hash| U256::from_be_bytes(hash.0))
+                    .unwrap_or_default();
+                self.push(hash)?;
+            }
+            Opcode::Blobbasefee => self.push(host.block.blob_basefee)?,
+
+            // Stack, memory, storage and flow operations
+            Opcode::Pop => {
+                self.pop()?;
+            }
+            Opcode::Mload => {
+                let offset = self.pop()?;
+                let (offset, _) = self.expand_memory(offset, U256::from(32), step_gas)?;
+                let value = self
+                    .memory
+                    .load_word(offset)
+                    .map_err(|_| ExitReason::MemoryLimit)?;
+                self.push(value)?;
+            }
+            Opcode::Mstore => {
+                let (offset, value) = (self.pop()?, self.pop()?);
+                let (offset, _) = self.expand_memory(offset, U256::from(32), step_gas)?;
+                self.memory
+                    .store_word(offset, value)
+                    .map_err(|_| ExitReason::MemoryLimit)?;
+            }
+            Opcode::Mstore8 => {
+                let (offset, value) = (self.pop()?, self.pop()?);
+                let (offset, _) = self.expand_memory(offset, U256::from(1), step_gas)?;
+                self.memory
+                    .write_byte(offset, value.byte(0))
+                    .map_err(|_| ExitReason::MemoryLimit)?;
+            }
+            Opcode::Sload => {
+                let key = StorageKey::from(self.pop()?);
+                let address = self.context.address;
+                let is_cold = host.journal.warm_slot(address, key);
+                let params = DynamicGasParams::new().with_cold_access(is_cold);
+                self.charge_dynamic(opcode, &params, step_gas)?;
+                self.push(host.journal.sload(address, key))?;
+            }
+            Opcode::Sstore => {
+                self.require_non_static()?;
+                let (key, value) = (StorageKey::from(self.pop()?), self.pop()?);
+                let address = self.context.address;
+                let is_cold = host.journal.warm_slot(address, key);
+                let current = host.journal.sload(address, key);
+                let original = host.journal.original_storage(&address, &key);
+                let params = DynamicGasParams::new()
+                    .with_storage_values(current, original, value)
+                    .with_cold_access(is_cold);
+                let used_before = self.gas.total_gas_used();
+                self.gas.charge_sstore(&params).map_err(out_of_gas)?;
+                step_gas.dynamic_gas += self.gas.total_gas_used() - used_before;
+                host.journal.sstore(address, key, value);
+            }
+            Opcode::Jump => {
+                let dest = self.pop()?;
+                self.jump(dest)?;
+            }
+            Opcode::Jumpi => {
+                let (dest, condition) = (self.pop()?, self.pop()?);
+                if !condition.is_zero() {
+                    self.jump(dest)?;
+                }
+            }
+            Opcode::Pc => self.push(U256::from(pc))?,
+            Opcode::Msize => self.push(U256::from(self.memory.size()))?,
+            Opcode::Gas => self.push(U256::from(self.gas.remaining_gas()))?,
+            Opcode::Jumpdest => {}
+            Opcode::Tload => {
+                let key = StorageKey::from(self.pop()?);
+                self.push(host.journal.tload(&self.context.address, &key))?;
+            }
+            Opcode::Tstore => {
+                self.require_non_static()?;
+                let (key, value) = (StorageKey::from(self.pop()?), self.pop()?);
+                host.journal.tstore(self.context.address, key, value);
+            }
+            Opcode::Mcopy => {
+                let (dest, src, size) = (self.pop()?, self.pop()?, self.pop()?);
+                let (src, size) = self.expand_memory(src, size, step_gas)?;
+                let (dest, _) = self.expand_memory(dest, U256::from(size), step_gas)?;
+                let params = DynamicGasParams::new().with_size(size);
+                self.charge_dynamic(opcode, &params, step_gas)?;
+                let data = self.read_memory(src, size)?;
+                self.write_memory(dest, &data)?;
+            }
+
+            // Push, duplicate and swap operations
+            Opcode::Push0 => self.push(U256::ZERO)?,
+            Opcode::Push1
+            | Opcode::Push2
+            | Opcode::Push3
+            | Opcode::Push4
+            | Opcode::Push5
+            | Opcode::Push6
+            | Opcode::Push7
+            | Opcode::Push8
+            | Opcode::Push9
+            | Opcode::Push10
+            | Opcode::Push11
+            | Opcode::Push12
+            | Opcode::Push13
+            | Opcode::Push14
+            | Opcode::Push15
+            | Opcode::Push16
+            | Opcode::Push17
+            | Opcode::Push18
+            | Opcode::Push19
+            | Opcode::Push20
+            | Opcode::Push21
+            | Opcode::Push22
+            | Opcode::Push23
+            | Opcode::Push24
+            | Opcode::Push25
+            | Opcode::Push26
+            | Opcode::Push27
+            | Opcode::Push28
+            | Opcode::Push29
+            | Opcode::Push30
+            | Opcode::Push31
+            | Opcode::Push32 => {
+                // Immediate bytes past the end of the code read as zero
+                let size = opcode.immediate_size();
+                let immediate = padded_slice(&self.code, U256::from(pc + 1), size);
+                self.push(U256::from_be_slice(&immediate))?;
+            }
+            Opcode::Dup1
+            | Opcode::Dup2
+            | Opcode::Dup3
+            | Opcode::Dup4
+            | Opcode::Dup5
+            | Opcode::Dup6
+            | Opcode::Dup7
+            | Opcode::Dup8
+            | Opcode::Dup9
+            | Opcode::Dup10
+            | Opcode::Dup11
+            | Opcode::Dup12
+            | Opcode::Dup13
+            | Opcode::Dup14
+            | Opcode::Dup15
+            | Opcode::Dup16 => {
+                let n = (opcode.to_byte() - Opcode::Dup1.to_byte() + 1) as usize;
+                self.stack.dup(n).map_err(stack_error)?;
+            }
+            Opcode::Swap1
+            | Opcode::Swap2
+            | Opcode::Swap3
+            | Opcode::Swap4
+            | Opcode::Swap5
+            | Opcode::Swap6
+            | Opcode::Swap7
+            | Opcode::Swap8
+            | Opcode::Swap9
+            | Opcode::Swap10
+            | Opcode::Swap11
+            | Opcode::Swap12
+            | Opcode::Swap13
+            | Opcode::Swap14
+            | Opcode::Swap15
+            | Opcode::Swap16 => {
+                let n = (opcode.to_byte() - Opcode::Swap1.to_byte() + 1) as usize;
+                self.stack.swap(n).map_err(stack_error)?;
+            }
+
+            // Logging operations
+            Opcode::Log0 | Opcode::Log1 | Opcode::Log2 | Opcode::Log3 | Opcode::Log4 => {
+                self.require_non_static()?;
+                let (offset, size) = (self.pop()?, self.pop()?);
+                let topic_count = (opcode.to_byte() - Opcode::Log0.to_byte()) as usize;
+                let topics = (0..topic_count)
+                    .map(|_| self.pop().map(B256::from))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let (offset, size) = self.expand_memory(offset, size, step_gas)?;
+                let params = DynamicGasParams::new().with_size(size);
+                self.charge_dynamic(opcode, &params, step_gas)?;
+                let data = self.read_memory(offset, size)?;
+                host.journal.log(Log {
+                    address: self.context.address,
+                    topics,
+                    data: data.into(),
+                });
+            }
+
+            // Contract creation and calls
+            Opcode::Create | Opcode::Create2 => {
+                self.require_non_static()?;
+                let (value, offset, size) = (self.pop()?, self.pop()?, self.pop()?);
+                let scheme = if opcode == Opcode::Create2 {
+                    CreateScheme::Create2 { salt: self.pop()? }
+                } else {
+                    CreateScheme::Create
+                };
+                let (offset, size) = self.expand_memory(offset, size, step_gas)?;
+                if spec.is_enabled_in(SpecId::Shanghai) && size > MAX_INITCODE_SIZE {
+                    return Err(ExitReason::OutOfGas);
+                }
+                let params = DynamicGasParams::new().with_size(size);
+                self.charge_dynamic(opcode, &params, step_gas)?;
+                let init_code = self.read_memory(offset, size)?;
+
+                // All but one 64th of the remaining gas goes to the init code (EIP-150)
+                let mut gas_limit = self.gas.remaining_gas();
+                if spec.is_enabled_in(SpecId::TangerineWhistle) {
+                    gas_limit -= gas_limit / 64;
+                }
+                self.gas.consume_gas(gas_limit).map_err(out_of_gas)?;
+                step_gas.forwarded = gas_limit;
+                return Ok(Action::Create(CreateRequest {
+                    scheme,
+                    caller: self.context.address,
+                    value,
+                    init_code: init_code.into(),
+                    gas_limit,
+                }));
+            }
+            Opcode::Call | Opcode::Callcode | Opcode::Delegatecall | Opcode::Staticcall => {
+                return self.call(opcode, host, step_gas);
+            }
+            Opcode::Return | Opcode::Revert => {
+                let (offset, size) = (self.pop()?, self.pop()?);
+                let (offset, size) = self.expand_memory(offset, size, step_gas)?;
+                let output = self.read_memory(offset, size)?;
+                let reason = if opcode == Opcode::Return {
+                    ExitReason::Return
+                } else {
+                    ExitReason::Revert
+                };
+                return Ok(Action::Exit(reason, output.into()));
+            }
+            Opcode::Invalid => return Err(ExitReason::InvalidOpcode(opcode.to_byte())),
+            Opcode::Selfdestruct => {
+                self.require_non_static()?;
+                let beneficiary = word_to_address(self.pop()?);
+                let address = self.context.address;
+                let balance = host.journal.balance(&address);
+                let is_cold = host.journal.warm_account(beneficiary);
+                let params = DynamicGasParams::new()
+                    .with_balance(balance)
+                    .with_call_params(U256::ZERO, host.journal.is_dead(spec, &beneficiary))
+                    .with_cold_access(is_cold);
+                self.charge_dynamic(opcode, &params, step_gas)?;
+
+                host.journal.transfer(address, beneficiary, balance);
+                // From Cancun only contracts created in the same transaction are deleted
+                // (EIP-6780); others just send their balance
+                if !spec.is_enabled_in(SpecId::Cancun) || host.journal.is_created(&address) {
+                    // The balance is burnt if the contract names itself as beneficiary
+                    host.journal.set_balance(address, U256::ZERO);
+                    let newly_destroyed = host.journal.mark_destroyed(address);
+                    // EIP-3529 removed the refund from London
+                    if newly_destroyed && !spec.is_enabled_in(SpecId::London) {
+                        self.gas.record_refund(24_000);
+                    }
+                }
+                return Ok(Action::Exit(ExitReason::SelfDestruct, Bytes::new()));
+            }
+        }
+        Ok(Action::Continue)
+    }
+
+    /// Prices and prepares a CALL-family operation.
+    fn call(
+        &mut self,
+        opcode: Opcode,
+        host: &mut Host<'_>,
+        step_gas: &mut StepGas,
+    ) -> Result<Action, ExitReason> {
+        let spec = self.gas.spec();
+        let requested_gas = self.pop()?;
+        let target = word_to_address(self.pop()?);
+        let value = match opcode {
+            Opcode::Call | Opcode::Callcode => self.pop()?,
+            _ => U256::ZERO,
+        };
+        let (args_offset, args_size) = (self.pop()?, self.pop()?);
+        let (ret_offset, ret_size) = (self.pop()?, self.pop()?);
+        if opcode == Opcode::Call && !value.is_zero() {
+            self.require_non_static()?;
+        }
+
+        let (args_offset, args_size) = self.expand_memory(args_offset, args_size, step_gas)?;
+        let (ret_offset, ret_size) = self.expand_memory(ret_offset, ret_size, step_gas)?;
+        let is_cold = host.journal.warm_account(target);
+        let params = DynamicGasParams::new()
+            .with_call_params(value, host.journal.is_dead(spec, &target))
+            .with_cold_access(is_cold)
+            .with_requested_gas(requested_gas);
+
+        // `call_gas` prices the whole operation, including the base cost already charged
+        let base_cost = self.gas.opcode_cost(opcode);
+        self.gas.return_gas(base_cost);
+        let cost = self.gas.call_gas(opcode, &params).map_err(out_of_gas)?;
+        self.gas
+            .consume_gas(cost.charged + cost.forwarded)
+            .map_err(out_of_gas)?;
+        step_gas.dynamic_gas += cost.charged.saturating_sub(base_cost);
+        step_gas.forwarded = cost.forwarded;
+
+        let input = self.read_memory(args_offset, args_size)?;
+        let (scheme, context, transfer) = match opcode {
+            Opcode::Call => (
+                CallScheme::Call,
+                CallContext {
+                    caller: self.context.address,
+                    address: target,
+                    code_address: target,
+                    value,
+                    input: input.into(),
+                    is_static: self.context.is_static,
+                },
+                value,
+            ),
+            Opcode::Callcode => (
+                CallScheme::Callcode,
+                CallContext {
+                    caller: self.context.address,
+                    address: self.context.address,
+                    code_address: target,
+                    value,
+                    input: input.into(),
+                    is_static: self.context.is_static,
+                },
+                U256::ZERO,
+            ),
+            Opcode::Delegatecall => (
+                CallScheme::Delegatecall,
+                CallContext {
+                    caller: self.context.caller,
+                    address: self.context.address,
+                    code_address: target,
+                    value: self.context.value,
+                    input: input.into(),
+                    is_static: self.context.is_static,
+                },
+                U256::ZERO,
+            ),
+            _ => (
+                CallScheme::Staticcall,
+                CallContext {
+                    caller: self.context.address,
+                    address: target,
+                    code_address: target,
+                    value: U256::ZERO,
+                    input: input.into(),
+                    is_static: true,
+                },
+                U256::ZERO,
+            ),
+        };
+        Ok(Action::Call(CallRequest {
+            scheme,
+            context,
+            transfer,
+            gas_limit: cost.callee_gas_limit(),
+            ret_offset,
+            ret_len: ret_size,
+        }))
+    }
+
+    fn push(&mut self, value: U256) -> Result<(), ExitReason> {
+        self.stack.push(value).map_err(stack_error)
+    }
+
+    fn pop(&mut self) -> Result<U256, ExitReason> {
+        self.stack.pop().map_err(stack_error)
+    }
+
+    /// Pops two operands and pushes `f(top, second)`.
+    fn binary(&mut self, f: impl FnOnce(U256, U256) -> U256) -> Result<(), ExitReason> {
+        let (a, b) = (self.pop()?, self.pop()?);
+        self.push(f(a, b))
+    }
+
+    fn require_non_static(&self) -> Result<(), ExitReason> {
+        if self.context.is_static {
+            Err(ExitReason::StateChangeDuringStaticCall)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn jump(&mut self, dest: U256) -> Result<(), ExitReason> {
+        let dest = usize::try_from(dest).map_err(|_| ExitReason::InvalidJump)?;
+        if !self.jump_table.is_valid(dest) {
+            return Err(ExitReason::InvalidJump);
+        }
+        self.pc = dest;
+        Ok(())
+    }
+
+    /// Charges the dynamic cost of `opcode` given `params`.
+    fn charge_dynamic(
+        &mut self,
+        opcode: Opcode,
+        params: &DynamicGasParams,
+        step_gas: &mut StepGas,
+    ) -> Result<(), ExitReason> {
+        let cost = self.gas.dynamic_gas_cost(opcode, params);
+        self.consume_gas(cost)?;
+        step_gas.dynamic_gas += cost;
+        Ok(())
+    }
+
+    /// Consumes `amount` gas, giving up any gas prepaid for the rest of the block if that
+    /// is what it takes.
+    fn consume_gas(&mut self, amount: u64) -> Result<(), ExitReason> {
+        if self.gas.consume_gas(amount).is_err() {
+            self.release_prepaid_gas();
+            self.gas.consume_gas(amount).map_err(out_of_gas)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the gas prepaid for instructions of the current block that have not run,
+    /// charging them per instruction instead. Without this, a dynamic cost could run out
+    /// of gas that per-instruction metering would still have had.
+    fn release_prepaid_gas(&mut self) {
+        if let Some(prepaid) = self.prepaid_gas.take() {
+            self.gas.return_gas(prepaid);
+        }
+    }
+
+    /// Warms `address` and charges the cold access surcharge if it was cold (EIP-2929).
+    fn charge_account_access(
+        &mut self,
+        opcode: Opcode,
+        address: Address,
+        host: &mut Host<'_>,
+        step_gas: &mut StepGas,
+    ) -> Result<(), ExitReason> {
+        let is_cold = host.journal.warm_account(address);
+        let params = DynamicGasParams::new().with_cold_access(is_cold);
+        self.charge_dynamic(opcode, &params, step_gas)
+    }
+
+    /// Charges for and expands memory to cover `size` bytes at `offset`.
+    ///
+    /// Returns the range as `usize`s; an empty range never expands memory and its offset
+    /// is meaningless, so it is returned as 0.
+    fn expand_memory(
+        &mut self,
+        offset: U256,
+        size: U256,
+        step_gas: &mut StepGas,
+    ) -> Result<(usize, usize), ExitReason> {
+        if size.is_zero() {
+            return Ok((0, 0));
+        }
+        let offset = usize::try_from(offset).map_err(|_| ExitReason::OutOfGas)?;
+        let size = usize::try_from(size).map_err(|_| ExitReason::OutOfGas)?;
+        let end = offset
+            .checked_add(size)
+            .filter(|end| *end <= MAX_MEMORY_OFFSET)
+            .ok_or(ExitReason::OutOfGas)?;
+        let new_size = end.div_ceil(32) * 32;
+        if new_size > self.memory.size() {
+            let cost = match self.gas.charge_memory_expansion(new_size) {
+                Ok(cost) => cost,
+                Err(_) => {
+                    self.release_prepaid_gas();
+                    self.gas
+                        .charge_memory_expansion(new_size)
+                        .map_err(out_of_gas)?
+                }
+            };
+            step_gas.memory_gas += cost;
+            if new_size > self.memory_limit {
+                return Err(ExitReason::MemoryLimit);
+            }
+            self.memory
+                .expand(new_size)
+                .map_err(|_| ExitReason::MemoryLimit)?;
+        }
+        Ok((offset, size))
+    }
+
+    fn read_memory(&self, offset: usize, size: usize) -> Result<Vec<u8>, ExitReason> {
+        self.memory
+            .read_bytes(offset, size)
+            .map_err(|_| ExitReason::MemoryLimit)
+    }
+
+    fn write_memory(&mut self, offset: usize, data: &[u8]) -> Result<(), ExitReason> {
+        self.memory
+            .write_bytes(offset, data)
+            .map_err(|_| ExitReason::MemoryLimit)
+    }
+
+    /// Implements the `*COPY` opcodes: copies `size` bytes of `source` from `offset` to
+    /// memory at `dest`, zero-padding reads past the end of `source`.
+    fn copy_to_memory(
+        &mut self,
+        opcode: Opcode,
+        source: &[u8],
+        dest: U256,
+        offset: U256,
+        size: U256,
+        step_gas: &mut StepGas,
+    ) -> Result<(), ExitReason> {
+        let (dest, size) = self.expand_memory(dest, size, step_gas)?;
+        let params = DynamicGasParams::new().with_size(size);
+        self.charge_dynamic(opcode, &params, step_gas)?;
+        let data = padded_slice(source, offset, size);
+        self.write_memory(dest, &data)
+    }
+}
+
+/// Returns `size` bytes of `data` starting at `offset`, zero-padded past its end.
+fn padded_slice(data: &[u8], offset: U256, size: usize) -> Vec<u8> {
+    let mut out = vec![0u8; size];
+    if let Ok(offset) = usize::try_from(offset) {
+        if offset < data.len() {
+            let available = (data.len() - offset).min(size);
+            out[..available].copy_from_slice(&data[offset..offset + available]);
+        }
+    }
+    out
+}
+
+/// Converts an address to a stack word.
+pub(crate) fn address_to_word(address: Address) -> U256 {
+    U256::from_be_slice(address.as_slice())
+}
+
+/// Converts a stack word to an address, keeping its low 20 bytes.
+pub(crate) fn word_to_address(word: U256) -> Address {
+    Address::from_word(B256::from(word))
+}
+
+fn stack_error(error: StackError) -> ExitReason {
+    match error {
+        StackError::Overflow => ExitReason::StackOverflow,
+        StackError::Underflow => ExitReason::StackUnderflow,
+    }
+}
+
+fn out_of_gas(_: GasError) -> ExitReason {
+    ExitReason::OutOfGas
+}
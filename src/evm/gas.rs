@@ -7,7 +7,20 @@
 
 use super::memory::Memory;
 use super::opcodes::Opcode;
-use crate::types::U256;
+use super::schedule::GasSchedule;
+use super::spec::SpecId;
+use crate::types::{AccessList, U256};
+use serde::Serialize;
+
+/// Cost of accessing an address or storage slot that is already warm (EIP-2929).
+pub const WARM_STORAGE_READ_COST: u64 = 100;
+/// Cost of the first access to an address in a transaction (EIP-2929).
+pub const COLD_ACCOUNT_ACCESS_COST: u64 = 2600;
+/// Cost of the first access to a storage slot in a transaction (EIP-2929).
+pub const COLD_SLOAD_COST: u64 = 2100;
+
+/// Gas stipend given to the callee of a value-transferring call.
+pub const CALL_STIPEND: u64 = 2300;
 
 /// Gas-related errors that can occur during EVM execution.
 #[derive(Debug, PartialEq, Eq)]
@@ -20,6 +33,127 @@ pub enum GasError {
     InvalidGasAmount,
 }
 
+/// Final gas accounting of a transaction, as reported in its receipt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasSettlement {
+    /// Gas charged to the sender, after refunds and the calldata floor.
+    pub gas_used: u64,
+    /// Refund applied to the transaction, after the cap.
+    pub gas_refund: u64,
+    /// Unused gas returned to the sender.
+    pub gas_returned: u64,
+}
+
+/// Returns the EIP-7623 minimum gas a transaction pays for its calldata.
+///
+/// Each zero byte counts as one token and each non-zero byte as four; the floor is
+/// 21000 plus 10 gas per token.
+pub fn calldata_floor_gas(calldata: &[u8]) -> u64 {
+    const TOTAL_COST_FLOOR_PER_TOKEN: u64 = 10;
+    let zero_bytes = calldata.iter().filter(|byte| **byte == 0).count() as u64;
+    let tokens = zero_bytes + 4 * (calldata.len() as u64 - zero_bytes);
+    21000 + TOTAL_COST_FLOOR_PER_TOKEN * tokens
+}
+
+/// Gas breakdown of a CALL-family operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallGasCost {
+    /// Gas charged for the call itself: base and access cost, value transfer,
+    /// account creation and memory expansion.
+    pub charged: u64,
+    /// Gas deducted from the caller and handed to the callee. Whatever the callee
+    /// does not use is returned to the caller.
+    pub forwarded: u64,
+    /// Free gas the callee receives on top of `forwarded` when value is transferred.
+    pub stipend: u64,
+}
+
+impl CallGasCost {
+    /// Returns the total gas available to the callee.
+    pub fn callee_gas_limit(&self) -> u64 {
+        self.forwarded + self.stipend
+    }
+}
+
+/// Gas a transaction must pay before any code runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntrinsicGas {
+    /// Gas charged up front: base cost, calldata, creation, access list and authorizations.
+    pub initial_gas: u64,
+    /// EIP-7623 calldata floor the transaction pays at minimum (zero before Prague).
+    pub floor_gas: u64,
+}
+
+impl IntrinsicGas {
+    /// Returns the smallest gas limit the transaction can be submitted with.
+    pub fn minimum_gas_limit(&self) -> u64 {
+        self.initial_gas.max(self.floor_gas)
+    }
+}
+
+/// Calculates the intrinsic gas of a transaction under the given fork.
+///
+/// # Arguments
+/// * `calldata` - The transaction input; the init code for contract creations.
+/// * `is_create` - Whether the transaction creates a contract.
+/// * `access_list` - The EIP-2930 access list, charged from Berlin.
+/// * `authorization_count` - Number of EIP-7702 authorizations, charged from Prague.
+pub fn intrinsic_gas(
+    spec: SpecId,
+    calldata: &[u8],
+    is_create: bool,
+    access_list: &AccessList,
+    authorization_count: u64,
+) -> IntrinsicGas {
+    const TX_BASE_COST: u64 = 21000;
+    const TX_CREATE_COST: u64 = 32000;
+    const TX_DATA_ZERO_COST: u64 = 4;
+    const ACCESS_LIST_ADDRESS_COST: u64 = 2400;
+    const ACCESS_LIST_STORAGE_KEY_COST: u64 = 1900;
+    const PER_EMPTY_ACCOUNT_COST: u64 = 25000;
+
+    let zero_bytes = calldata.iter().filter(|byte| **byte == 0).count() as u64;
+    let non_zero_bytes = calldata.len() as u64 - zero_bytes;
+    // EIP-2028 cut the non-zero byte cost from 68 to 16
+    let non_zero_cost = if spec.is_enabled_in(SpecId::Istanbul) {
+        16
+    } else {
+        68
+    };
+    let mut initial_gas =
+        TX_BASE_COST + TX_DATA_ZERO_COST * zero_bytes + non_zero_cost * non_zero_bytes;
+
+    // Contract creation was only surcharged from Homestead (EIP-2)
+    if is_create && spec.is_enabled_in(SpecId::Homestead) {
+        initial_gas += TX_CREATE_COST;
+        if spec.is_enabled_in(SpecId::Shanghai) {
+            // EIP-3860: 2 gas per word of init code
+            initial_gas += 2 * (calldata.len() as u64).div_ceil(32);
+        }
+    }
+
+    if spec.is_enabled_in(SpecId::Berlin) {
+        for item in access_list {
+            initial_gas += ACCESS_LIST_ADDRESS_COST
+                + ACCESS_LIST_STORAGE_KEY_COST * item.storage_keys.len() as u64;
+        }
+    }
+
+    let mut floor_gas = 0;
+    if spec.is_enabled_in(SpecId::Prague) {
+        initial_gas += PER_EMPTY_ACCOUNT_COST * authorization_count;
+        floor_gas = calldata_floor_gas(calldata);
+    }
+
+    IntrinsicGas {
+        initial_gas,
+        floor_gas,
+    }
+}
+
 /// Parameters for calculating dynamic gas costs.
 /// Contains the contextual information needed for operations with variable costs.
 #[derive(Debug, Clone)]
@@ -40,6 +174,20 @@ pub struct DynamicGasParams {
     pub balance: U256,
     /// Whether the target account is empty
     pub is_account_empty: bool,
+    /// Whether the accessed address or storage slot was cold (EIP-2929)
+    pub is_cold: bool,
+    /// Gas requested for the callee by CALL-family operations
+    pub requested_gas: U256,
+    /// Memory size (bytes) before the operation
+    pub memory_size: usize,
+    /// Memory size (bytes) the operation expands memory to
+    pub new_memory_size: usize,
+}
+
+impl Default for DynamicGasParams {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DynamicGasParams {
@@ -54,6 +202,10 @@ impl DynamicGasParams {
             value: U256::ZERO,
             balance: U256::ZERO,
             is_account_empty: false,
+            is_cold: false,
+            requested_gas: U256::ZERO,
+            memory_size: 0,
+            new_memory_size: 0,
         }
     }
 
@@ -89,6 +241,42 @@ impl DynamicGasParams {
         self.balance = balance;
         self
     }
+
+    /// Marks the accessed address or storage slot as cold.
+    ///
+    /// The flag is usually the return value of `Journal::warm_account` or
+    /// `Journal::warm_slot`, which also mark the entry warm for later accesses.
+    pub fn with_cold_access(mut self, is_cold: bool) -> Self {
+        self.is_cold = is_cold;
+        self
+    }
+
+    /// Sets the gas a CALL-family operation requests for the callee.
+    pub fn with_requested_gas(mut self, requested_gas: U256) -> Self {
+        self.requested_gas = requested_gas;
+        self
+    }
+
+    /// Sets the memory expansion caused by a call's argument and return ranges.
+    ///
+    /// Each range is an `(offset, length)` pair; empty ranges never expand memory.
+    pub fn with_call_memory(
+        mut self,
+        memory_size: usize,
+        args: (usize, usize),
+        ret: (usize, usize),
+    ) -> Self {
+        let range_end = |(offset, length): (usize, usize)| {
+            if length == 0 {
+                0
+            } else {
+                offset.saturating_add(length)
+            }
+        };
+        self.memory_size = memory_size;
+        self.new_memory_size = memory_size.max(range_end(args)).max(range_end(ret));
+        self
+    }
 }
 
 /// The EVM gas meter, responsible for tracking gas consumption and limits.
@@ -104,29 +292,80 @@ impl DynamicGasParams {
 /// - Stack costs: Minimal costs for stack operations
 /// - Storage costs: Costs for storage operations (future)
 /// - Computation costs: Costs for complex operations
+///
+/// Costs follow a [`GasSchedule`]: by default the preset of the latest fork, or a custom
+/// schedule loaded from JSON or TOML.
+///
+/// A meter created with [`GasMeter::unmetered`] prices every operation at zero, never
+/// runs out of gas and records no refunds, for running code with gas disabled.
+#[derive(Debug, Clone)]
 pub struct GasMeter {
     /// Total gas consumed so far.
     gas_used: u64,
     /// Maximum gas allowed for this execution.
     gas_limit: u64,
-    /// Gas refunds (e.g., from storage clearing).
-    gas_refund: u64,
+    /// Gas refund counter (e.g., from storage clearing). May be negative within a call
+    /// frame, since net gas metering can take back refunds granted earlier.
+    gas_refund: i64,
     /// Memory gas cost tracking.
     memory_gas_cost: u64,
     /// Previous memory size for expansion cost calculation.
     previous_memory_size: usize,
+    /// Gas schedule applied, including the fork whose rules it follows.
+    schedule: GasSchedule,
+    /// `false` if gas is disabled and every charge is a no-op.
+    metered: bool,
 }
 
 impl GasMeter {
+    /// Creates a gas meter using the latest fork's gas schedule.
     pub fn new(gas_limit: u64) -> Self {
+        Self::with_spec(gas_limit, SpecId::default())
+    }
+
+    /// Creates a gas meter using the gas schedule of the given fork.
+    pub fn with_spec(gas_limit: u64, spec: SpecId) -> Self {
+        Self::with_schedule(gas_limit, GasSchedule::for_spec(spec))
+    }
+
+    /// Creates a gas meter using a custom gas schedule.
+    pub fn with_schedule(gas_limit: u64, schedule: GasSchedule) -> Self {
         Self {
             gas_used: 0,
             gas_limit,
             gas_refund: 0,
             memory_gas_cost: 0,
             previous_memory_size: 0,
+            schedule,
+            metered: true,
         }
     }
+
+    /// Creates a meter with gas disabled: nothing costs gas and `remaining_gas` stays at
+    /// `gas_limit`. The schedule still decides fork-dependent rules such as the call
+    /// stipend.
+    pub fn unmetered(gas_limit: u64, schedule: GasSchedule) -> Self {
+        Self {
+            metered: false,
+            ..Self::with_schedule(gas_limit, schedule)
+        }
+    }
+
+    /// Returns `false` if gas is disabled.
+    pub fn is_metered(&self) -> bool {
+        self.metered
+    }
+
+    /// Returns the fork whose rules this meter applies.
+    pub fn spec(&self) -> SpecId {
+        self.schedule.spec
+    }
+
+    /// Returns the gas schedule this meter applies.
+    pub fn schedule(&self) -> &GasSchedule {
+        &self.schedule
+    }
+
     /// Consumes the specified amount of gas.
     ///
     /// # Arguments
@@ -136,7 +375,10 @@ impl GasMeter {
     /// Returns `GasError::OutOfGas` if insufficient gas is available.
     /// Returns `GasError::GasLimitExceeded` if the gas limit would be exceeded.
     pub fn consume_gas(&mut self, amount: u64) -> Result<(), GasError> {
-        if self.gas_used + amount > self.gas_limit {
+        if !self.metered {
+            return Ok(());
+        }
+        if amount > self.gas_limit - self.gas_used {
             return Err(GasError::GasLimitExceeded);
         }
         self.gas_used += amount;
@@ -145,10 +387,24 @@ impl GasMeter {
 
     /// Refunds gas (e.g., from storage clearing).
     pub fn refund_gas(&mut self, amount: u64) -> Result<(), GasError> {
-        self.gas_refund = self.gas_refund.saturating_add(amount);
+        let amount = i64::try_from(amount).map_err(|_| GasError::InvalidGasAmount)?;
+        self.record_refund(amount);
         Ok(())
     }
 
+    /// Adds a signed change to the refund counter.
+    pub fn record_refund(&mut self, delta: i64) {
+        if !self.metered {
+            return;
+        }
+        self.gas_refund = self.gas_refund.saturating_add(delta);
+    }
+
+    /// Returns the current refund counter.
+    pub fn gas_refund(&self) -> i64 {
+        self.gas_refund
+    }
+
     /// Returns the remaining gas available for execution.
     pub fn remaining_gas(&self) -> u64 {
         self.gas_limit.saturating_sub(self.gas_used)
@@ -159,9 +415,28 @@ impl GasMeter {
         self.gas_used
     }
 
-    /// Returns the effective gas used (gas_used - gas_refund).
+    /// Returns the effective gas used (gas_used - gas_refund), with the refund capped.
     pub fn effective_gas_used(&self) -> u64 {
-        self.gas_used.saturating_sub(self.gas_refund)
+        self.settle(0).gas_used
+    }
+
+    /// Settles the transaction's gas once execution has finished.
+    ///
+    /// The refund is capped at `gas_used / 2`, or `gas_used / 5` from London (EIP-3529).
+    /// From Prague the sender pays at least `floor_gas` (EIP-7623, see [`calldata_floor_gas`]).
+    /// The meter is expected to cover the whole transaction, including intrinsic gas.
+    pub fn settle(&self, floor_gas: u64) -> GasSettlement {
+        let max_refund_quotient = self.schedule.max_refund_quotient.max(1);
+        let gas_refund = (self.gas_refund.max(0) as u64).min(self.gas_used / max_refund_quotient);
+        let mut gas_used = self.gas_used - gas_refund;
+        if self.spec().is_enabled_in(SpecId::Prague) {
+            gas_used = gas_used.max(floor_gas);
+        }
+        GasSettlement {
+            gas_used,
+            gas_refund,
+            gas_returned: self.gas_limit.saturating_sub(gas_used),
+        }
     }
 
     /// Updates memory gas cost based on current memory state.
@@ -179,245 +454,153 @@ impl GasMeter {
         Ok(())
     }
 
+    /// Charges for expanding memory to `new_size` bytes, returning the amount charged.
+    ///
+    /// Does nothing if memory is already at least that large. Unlike
+    /// [`update_memory_cost`](Self::update_memory_cost) this charges *before* the memory
+    /// is grown, so an unaffordable expansion never allocates.
+    pub fn charge_memory_expansion(&mut self, new_size: usize) -> Result<u64, GasError> {
+        let expansion_cost = self.memory_expansion_cost(self.previous_memory_size, new_size);
+        if expansion_cost > 0 {
+            self.consume_gas(expansion_cost)?;
+            self.memory_gas_cost += expansion_cost;
+        }
+        self.previous_memory_size = self.previous_memory_size.max(new_size);
+        Ok(expansion_cost)
+    }
+
+    /// Returns the total gas spent on memory expansion so far.
+    pub fn memory_gas_cost(&self) -> u64 {
+        self.memory_gas_cost
+    }
+
+    /// Returns gas that was consumed on behalf of a sub-call but not used by it.
+    pub fn return_gas(&mut self, amount: u64) {
+        self.gas_used = self.gas_used.saturating_sub(amount);
+    }
+
+    /// Returns the gas limit of this meter.
+    pub fn gas_limit(&self) -> u64 {
+        self.gas_limit
+    }
+
     /// Calculates the gas cost for memory expansion.
     pub fn memory_expansion_cost(&self, old_size: usize, new_size: usize) -> u64 {
-        if new_size <= old_size {
+        if new_size <= old_size || !self.metered {
             return 0;
         }
 
-        let g_memory: u64 = 3;
-        let old_words = (old_size + 31) / 32;
-        let new_words = (new_size + 31) / 32;
+        let g_memory = self.schedule.memory_word_cost;
+        let divisor = self.schedule.memory_quadratic_divisor.max(1);
+        let old_words = old_size.div_ceil(32) as u64;
+        let new_words = new_size.div_ceil(32) as u64;
 
-        let old_cost = g_memory * old_words as u64 + (old_words * old_words) as u64 / 512;
-        let new_cost = g_memory * new_words as u64 + (new_words * new_words) as u64 / 512;
+        // Custom schedules can price memory high enough to overflow; saturate instead
+        let cost = |words: u64| {
+            g_memory
+                .saturating_mul(words)
+                .saturating_add(words.saturating_mul(words) / divisor)
+        };
+        let (old_cost, new_cost) = (cost(old_words), cost(new_words));
 
         new_cost.saturating_sub(old_cost)
     }
 
-    /// Returns the gas cost for a specific opcode.
+    /// Returns the base gas cost for a specific opcode under the meter's schedule.
+    ///
+    /// Opcodes that are not yet enabled in the fork are still priced; callers should
+    /// check [`Opcode::is_enabled_in`] before executing them. Zero if gas is disabled.
     pub fn opcode_cost(&self, opcode: Opcode) -> u64 {
-        match opcode {
-            // Stop and arithmetic operations
-            Opcode::Stop => 0,
-            Opcode::Add => 3,
-            Opcode::Mul => 5,
-            Opcode::Sub => 3,
-            Opcode::Div => 5,
-            Opcode::Sdiv => 5,
-            Opcode::Mod => 5,
-            Opcode::Smod => 5,
-            Opcode::Addmod => 8,
-            Opcode::Mulmod => 8,
-            Opcode::Exp => 10, // Base cost, actual cost depends on exponent
-            Opcode::Signextend => 5,
-
-            // Comparison operations
-            Opcode::Lt => 3,
-            Opcode::Gt => 3,
-            Opcode::Slt => 3,
-            Opcode::Sgt => 3,
-            Opcode::Eq => 3,
-            Opcode::Iszero => 3,
-
-            // Bitwise operations
-            Opcode::And => 3,
-            Opcode::Or => 3,
-            Opcode::Xor => 3,
-            Opcode::Not => 3,
-            Opcode::Byte => 3,
-            Opcode::Shl => 3,
-            Opcode::Shr => 3,
-            Opcode::Sar => 3,
-
-            // Cryptographic operations
-            Opcode::Keccak256 => 30, // Base cost, actual cost depends on data size
-
-            // Environment information
-            Opcode::Address => 2,
-            Opcode::Balance => 2600, // Cold storage access cost
-            Opcode::Origin => 2,
-            Opcode::Caller => 2,
-            Opcode::Callvalue => 2,
-            Opcode::Calldataload => 3,
-            Opcode::Calldatasize => 2,
-            Opcode::Calldatacopy => 3, // Base cost, actual cost depends on data size
-            Opcode::Codesize => 2,
-            Opcode::Codecopy => 3, // Base cost, actual cost depends on data size
-            Opcode::Gasprice => 2,
-            Opcode::Extcodecopy => 2600, // Cold storage access cost + copy cost
-            Opcode::Extcodesize => 2600, // Cold storage access cost
-            Opcode::Extcodehash => 2600, // Cold storage access cost
-            Opcode::Returndatasize => 2,
-            Opcode::Returndatacopy => 3, // Base cost, actual cost depends on data size
-            Opcode::Blockhash => 20,
-            Opcode::Coinbase => 2,
-            Opcode::Timestamp => 2,
-            Opcode::Number => 2,
-            Opcode::Difficulty => 2,
-            Opcode::Gaslimit => 2,
-            Opcode::Chainid => 2,
-            Opcode::Selfbalance => 2,
-            Opcode::Basefee => 2,
-            Opcode::Blobhash => 2,
-            Opcode::Blobbasefee => 2,
-
-            // Stack operations
-            Opcode::Pop => 2,
-            Opcode::Mload => 3,
-            Opcode::Mstore => 3,
-            Opcode::Mstore8 => 3,
-            Opcode::Sload => 2100,   // Cold storage access cost
-            Opcode::Sstore => 22100, // Cold storage write cost (base)
-            Opcode::Jump => 8,
-            Opcode::Jumpi => 10,
-            Opcode::Pc => 2,
-            Opcode::Msize => 2,
-            Opcode::Gas => 2,
-            Opcode::Jumpdest => 1,
-            Opcode::Tload => 100,  // Warm storage access
-            Opcode::Tstore => 100, // Warm storage write
-            Opcode::Mcopy => 3,
-
-            // Push operations (all have same cost)
-            Opcode::Push0 => 2,
-            Opcode::Push1 => 2,
-            Opcode::Push2 => 2,
-            Opcode::Push3 => 2,
-            Opcode::Push4 => 2,
-            Opcode::Push5 => 2,
-            Opcode::Push6 => 2,
-            Opcode::Push7 => 2,
-            Opcode::Push8 => 2,
-            Opcode::Push9 => 2,
-            Opcode::Push10 => 2,
-            Opcode::Push11 => 2,
-            Opcode::Push12 => 2,
-            Opcode::Push13 => 2,
-            Opcode::Push14 => 2,
-            Opcode::Push15 => 2,
-            Opcode::Push16 => 2,
-            Opcode::Push17 => 2,
-            Opcode::Push18 => 2,
-            Opcode::Push19 => 2,
-            Opcode::Push20 => 2,
-            Opcode::Push21 => 2,
-            Opcode::Push22 => 2,
-            Opcode::Push23 => 2,
-            Opcode::Push24 => 2,
-            Opcode::Push25 => 2,
-            Opcode::Push26 => 2,
-            Opcode::Push27 => 2,
-            Opcode::Push28 => 2,
-            Opcode::Push29 => 2,
-            Opcode::Push30 => 2,
-            Opcode::Push31 => 2,
-            Opcode::Push32 => 2,
-
-            // Duplicate operations
-            Opcode::Dup1 => 3,
-            Opcode::Dup2 => 3,
-            Opcode::Dup3 => 3,
-            Opcode::Dup4 => 3,
-            Opcode::Dup5 => 3,
-            Opcode::Dup6 => 3,
-            Opcode::Dup7 => 3,
-            Opcode::Dup8 => 3,
-            Opcode::Dup9 => 3,
-            Opcode::Dup10 => 3,
-            Opcode::Dup11 => 3,
-            Opcode::Dup12 => 3,
-            Opcode::Dup13 => 3,
-            Opcode::Dup14 => 3,
-            Opcode::Dup15 => 3,
-            Opcode::Dup16 => 3,
-
-            // Swap operations
-            Opcode::Swap1 => 3,
-            Opcode::Swap2 => 3,
-            Opcode::Swap3 => 3,
-            Opcode::Swap4 => 3,
-            Opcode::Swap5 => 3,
-            Opcode::Swap6 => 3,
-            Opcode::Swap7 => 3,
-            Opcode::Swap8 => 3,
-            Opcode::Swap9 => 3,
-            Opcode::Swap10 => 3,
-            Opcode::Swap11 => 3,
-            Opcode::Swap12 => 3,
-            Opcode::Swap13 => 3,
-            Opcode::Swap14 => 3,
-            Opcode::Swap15 => 3,
-            Opcode::Swap16 => 3,
-
-            // Logging operations
-            Opcode::Log0 => 375,  // Base cost, actual cost depends on data size
-            Opcode::Log1 => 750,  // Base cost, actual cost depends on data size
-            Opcode::Log2 => 1125, // Base cost, actual cost depends on data size
-            Opcode::Log3 => 1500, // Base cost, actual cost depends on data size
-            Opcode::Log4 => 1875, // Base cost, actual cost depends on data size
-
-            // Contract creation and calls
-            Opcode::Create => 32000,  // Base cost for contract creation
-            Opcode::Call => 2600,     // Base cost for calls
-            Opcode::Callcode => 2600, // Base cost for callcode
-            Opcode::Return => 0,
-            Opcode::Delegatecall => 2600, // Base cost for delegatecall
-            Opcode::Create2 => 32000,     // Base cost for contract creation
-            Opcode::Staticcall => 2600,   // Base cost for staticcall
-            Opcode::Revert => 0,
-            Opcode::Invalid => 0,
-            Opcode::Selfdestruct => 5000, // Base cost for selfdestruct
+        if !self.metered {
+            return 0;
         }
+        self.schedule.opcode_cost(opcode)
     }
 
     /// Calculates the dynamic gas cost for operations that depend on parameters.
     /// This should be called in addition to the base opcode cost.
     pub fn dynamic_gas_cost(&self, opcode: Opcode, params: &DynamicGasParams) -> u64 {
+        if !self.metered {
+            return 0;
+        }
+        self.access_surcharge(opcode, params)
+            .saturating_add(self.operation_cost(opcode, params))
+    }
+
+    /// Returns the EIP-2929 surcharge for a cold account or storage slot access.
+    ///
+    /// `opcode_cost` already charges the warm price from Berlin, so only the difference
+    /// is added here. SSTORE and SELFDESTRUCT have no warm price and pay the full cold cost.
+    fn access_surcharge(&self, opcode: Opcode, params: &DynamicGasParams) -> u64 {
+        if !params.is_cold || !self.spec().is_enabled_in(SpecId::Berlin) {
+            return 0;
+        }
+        let schedule = &self.schedule;
+        let cold_account_surcharge = schedule
+            .cold_account_access_cost
+            .saturating_sub(schedule.warm_storage_read_cost);
+        match opcode {
+            Opcode::Balance
+            | Opcode::Extcodesize
+            | Opcode::Extcodecopy
+            | Opcode::Extcodehash
+            | Opcode::Call
+            | Opcode::Callcode
+            | Opcode::Delegatecall
+            | Opcode::Staticcall => cold_account_surcharge,
+            Opcode::Sload => schedule
+                .cold_sload_cost
+                .saturating_sub(schedule.warm_storage_read_cost),
+            Opcode::Sstore => schedule.cold_sload_cost,
+            Opcode::Selfdestruct => schedule.cold_account_access_cost,
+            _ => 0,
+        }
+    }
+
+    /// Calculates the parameter-dependent cost of the operation itself.
+    fn operation_cost(&self, opcode: Opcode, params: &DynamicGasParams) -> u64 {
+        let spec = self.spec();
+        let schedule = &self.schedule;
         match opcode {
             // Data copying operations
             Opcode::Calldatacopy | Opcode::Codecopy | Opcode::Returndatacopy => {
                 // 3 gas per word copied
-                let words = (params.size + 31) / 32;
-                3 * words as u64
+                let words = params.size.div_ceil(32);
+                schedule.copy_word_cost.saturating_mul(words as u64)
             }
 
             // External code operations
             Opcode::Extcodecopy => {
-                // Base cost (2600) + copying cost
-                let words = (params.size + 31) / 32;
-                3 * words as u64
+                // Access cost + copying cost
+                let words = params.size.div_ceil(32);
+                schedule.copy_word_cost.saturating_mul(words as u64)
             }
 
             // Memory copy operation
             Opcode::Mcopy => {
                 // 3 gas per word copied
-                let words = (params.size + 31) / 32;
-                3 * words as u64
+                let words = params.size.div_ceil(32);
+                schedule.copy_word_cost.saturating_mul(words as u64)
             }
 
             // Cryptographic operations
             Opcode::Keccak256 => {
                 // 6 gas per word hashed
-                let words = (params.size + 31) / 32;
-                6 * words as u64
+                let words = params.size.div_ceil(32);
+                schedule.keccak_word_cost.saturating_mul(words as u64)
             }
 
             // Exponentiation
             Opcode::Exp => {
-                // Additional cost based on exponent byte length
-                if params.exponent.is_zero() {
-                    0
-                } else {
-                    let byte_length = (params.exponent.bit_len() + 7) / 8;
-                    50 * byte_length as u64
-                }
+                // Additional cost based on exponent byte length (repriced by EIP-160)
+                let byte_length = params.exponent.bit_len().div_ceil(8);
+                schedule.exp_byte_cost.saturating_mul(byte_length as u64)
             }
 
             // Logging operations
             Opcode::Log0 | Opcode::Log1 | Opcode::Log2 | Opcode::Log3 | Opcode::Log4 => {
                 // 8 gas per byte logged
-                8 * params.size as u64
+                schedule.log_byte_cost.saturating_mul(params.size as u64)
             }
 
             // Storage operations
@@ -432,13 +615,15 @@ impl GasMeter {
 
             // Contract creation
             Opcode::Create | Opcode::Create2 => {
-                // 2 gas per byte of init code
-                let init_code_cost = 2 * params.size as u64;
+                let words = params.size.div_ceil(32) as u64;
+
+                // 2 gas per word of init code (EIP-3860)
+                let init_code_cost = schedule.init_code_word_cost.saturating_mul(words);
 
                 // CREATE2 has additional cost for address calculation
                 if opcode == Opcode::Create2 {
-                    let hash_cost = 6 * ((params.size + 31) / 32) as u64;
-                    init_code_cost + hash_cost
+                    let hash_cost = schedule.keccak_word_cost.saturating_mul(words);
+                    init_code_cost.saturating_add(hash_cost)
                 } else {
                     init_code_cost
                 }
@@ -446,37 +631,45 @@ impl GasMeter {
 
             // Call operations
             Opcode::Call | Opcode::Callcode | Opcode::Delegatecall | Opcode::Staticcall => {
+                let transfers_value =
+                    matches!(opcode, Opcode::Call | Opcode::Callcode) && !params.value.is_zero();
                 let mut cost = 0u64;
 
                 // Value transfer cost
-                if opcode == Opcode::Call && !params.value.is_zero() {
-                    cost += 9000;
-
-                    // New account creation cost
-                    if params.is_account_empty {
-                        cost += 25000;
-                    }
+                if transfers_value {
+                    cost = cost.saturating_add(schedule.call_value_cost);
                 }
 
-                // Memory expansion cost for call data and return data
-                if params.size > 0 {
-                    let words = (params.size + 31) / 32;
-                    cost += words as u64;
+                // New account creation cost: only CALL can create the target, and
+                // since EIP-161 only when it actually transfers value
+                let creates_account = opcode == Opcode::Call
+                    && params.is_account_empty
+                    && (transfers_value || !spec.is_enabled_in(SpecId::SpuriousDragon));
+                if creates_account {
+                    cost = cost.saturating_add(schedule.new_account_cost);
                 }
 
-                cost
+                // Memory expansion cost for call data and return data
+                cost.saturating_add(
+                    self.memory_expansion_cost(params.memory_size, params.new_memory_size),
+                )
             }
 
             // Self-destruct
             Opcode::Selfdestruct => {
-                let mut cost = 0u64;
+                // Transfer to new account: EIP-150 introduced the charge and
+                // EIP-161 limited it to transfers of a non-zero balance
+                let creates_account = if spec.is_enabled_in(SpecId::SpuriousDragon) {
+                    params.is_account_empty && !params.balance.is_zero()
+                } else {
+                    spec.is_enabled_in(SpecId::TangerineWhistle) && params.is_account_empty
+                };
 
-                // Transfer to new account
-                if params.is_account_empty && !params.balance.is_zero() {
-                    cost += 25000;
+                if creates_account {
+                    schedule.new_account_cost
+                } else {
+                    0
                 }
-
-                cost
             }
 
             // Operations without dynamic costs
@@ -484,39 +677,177 @@ impl GasMeter {
         }
     }
 
-    /// Calculates the gas cost for SSTORE operations based on EIP-2200.
-    /// This implements the complex gas pricing for storage operations.
+    /// Charges an SSTORE, including any cold slot surcharge, and records its refund.
+    ///
+    /// The base cost from [`opcode_cost`](Self::opcode_cost) is not included; it is
+    /// charged with every other opcode's.
+    ///
+    /// # Errors
+    /// Returns `GasError::OutOfGas` if, from Istanbul, the remaining gas is at or below the
+    /// call stipend (EIP-2200), so that a stipend-funded call can never write storage.
+    /// Returns `GasError::GasLimitExceeded` if the write itself cannot be paid for.
+    pub fn charge_sstore(&mut self, params: &DynamicGasParams) -> Result<(), GasError> {
+        if self.metered
+            && self.spec().is_enabled_in(SpecId::Istanbul)
+            && self.remaining_gas() <= self.schedule.call_stipend
+        {
+            return Err(GasError::OutOfGas);
+        }
+        self.consume_gas(self.dynamic_gas_cost(Opcode::Sstore, params))?;
+        self.record_refund(self.calculate_sstore_refund(
+            params.current_value,
+            params.original_value,
+            params.new_value,
+        ));
+        Ok(())
+    }
+
+    /// Returns `true` if the fork uses net gas metering for SSTORE: Constantinople's
+    /// EIP-1283, then Istanbul's EIP-2200 onwards.
+    fn is_net_metered(&self) -> bool {
+        let spec = self.spec();
+        spec == SpecId::Constantinople || spec.is_enabled_in(SpecId::Istanbul)
+    }
+
+    /// Calculates the full gas breakdown of a CALL-family operation.
+    ///
+    /// From Tangerine Whistle the callee receives at most all but one 64th of the gas left
+    /// after the call is charged (EIP-150); before it, the requested gas is forwarded as-is
+    /// and must be affordable. Value-transferring CALL and CALLCODE add a 2300 gas stipend.
+    ///
+    /// # Errors
+    /// Returns `GasError::OutOfGas` if the caller cannot pay for the call.
+    pub fn call_gas(
+        &self,
+        opcode: Opcode,
+        params: &DynamicGasParams,
+    ) -> Result<CallGasCost, GasError> {
+        let charged = self
+            .opcode_cost(opcode)
+            .saturating_add(self.dynamic_gas_cost(opcode, params));
+        let available = self
+            .remaining_gas()
+            .checked_sub(charged)
+            .ok_or(GasError::OutOfGas)?;
+        let requested = u64::try_from(params.requested_gas).unwrap_or(u64::MAX);
+
+        let forwarded = if !self.metered || self.spec().is_enabled_in(SpecId::TangerineWhistle) {
+            requested.min(available - available / 64)
+        } else if requested <= available {
+            requested
+        } else {
+            return Err(GasError::OutOfGas);
+        };
+
+        let transfers_value =
+            matches!(opcode, Opcode::Call | Opcode::Callcode) && !params.value.is_zero();
+        Ok(CallGasCost {
+            charged,
+            forwarded,
+            stipend: if transfers_value {
+                self.schedule.call_stipend
+            } else {
+                0
+            },
+        })
+    }
+
+    /// Calculates the gas cost for SSTORE operations, excluding any cold slot surcharge.
+    ///
+    /// Forks with net gas metering (Constantinople's EIP-1283, Istanbul's EIP-2200 and
+    /// later) price no-op and dirty writes at the fork's SLOAD cost; the others use the
+    /// original set/reset pricing. From Berlin the reset cost excludes the cold SLOAD
+    /// cost, which is charged separately (EIP-2929).
     fn calculate_sstore_cost(
         &self,
         current_value: U256,
         original_value: U256,
         new_value: U256,
     ) -> u64 {
-        // Gas costs as per EIP-2200
-        const SLOAD_GAS: u64 = 800;
-        const SSTORE_SET_GAS: u64 = 20000;
-        const SSTORE_RESET_GAS: u64 = 5000;
-        const _SSTORE_CLEAR_REFUND: u64 = 15000;
+        let schedule = &self.schedule;
+        if !self.is_net_metered() {
+            // No net metering: only the zero-ness of the slot matters
+            return if current_value.is_zero() && !new_value.is_zero() {
+                schedule.sstore_set_gas
+            } else {
+                schedule.sstore_reset_gas
+            };
+        }
 
         if new_value == current_value {
             // No change
-            SLOAD_GAS
+            schedule.sstore_noop_gas
         } else if original_value == current_value {
             // First change in transaction
             if original_value.is_zero() {
                 // Setting from zero
-                SSTORE_SET_GAS
+                schedule.sstore_set_gas
             } else {
                 // Modifying existing value
-                SSTORE_RESET_GAS
+                schedule.sstore_reset_gas
             }
         } else {
             // Subsequent change in transaction
-            SLOAD_GAS
+            schedule.sstore_noop_gas
+        }
+    }
+
+    /// Calculates the refund counter change for an SSTORE.
+    ///
+    /// Under net gas metering the change can be negative: a refund granted for clearing
+    /// a slot earlier in the transaction is taken back when the slot is written again.
+    fn calculate_sstore_refund(
+        &self,
+        current_value: U256,
+        original_value: U256,
+        new_value: U256,
+    ) -> i64 {
+        let schedule = &self.schedule;
+        let clear_refund = schedule.sstore_clear_refund as i64;
+        if !self.is_net_metered() {
+            return if !current_value.is_zero() && new_value.is_zero() {
+                clear_refund
+            } else {
+                0
+            };
+        }
+
+        if new_value == current_value {
+            return 0;
+        }
+
+        if original_value == current_value {
+            // First change in transaction: only clearing earns a refund
+            return if !original_value.is_zero() && new_value.is_zero() {
+                clear_refund
+            } else {
+                0
+            };
+        }
+
+        // Subsequent change to a dirty slot
+        let mut refund = 0;
+        if !original_value.is_zero() {
+            if current_value.is_zero() {
+                // The slot was cleared earlier; undo that refund
+                refund -= clear_refund;
+            } else if new_value.is_zero() {
+                refund += clear_refund;
+            }
+        }
+        if original_value == new_value {
+            // Restoring the original value: refund all but the SLOAD cost
+            let restored_cost = if original_value.is_zero() {
+                schedule.sstore_set_gas
+            } else {
+                schedule.sstore_reset_gas
+            };
+            refund += restored_cost.saturating_sub(schedule.sstore_noop_gas) as i64;
         }
+        refund
     }
 
-    /// Resets the gas meter for a new execution context.
+    /// Resets the gas meter for a new execution context, keeping the selected fork.
     pub fn reset(&mut self, gas_limit: u64) {
         self.gas_used = 0;
         self.gas_limit = gas_limit;
@@ -529,7 +860,7 @@ impl GasMeter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::U256;
+    use crate::types::{AccessListItem, Address, StorageKey, U256};
 
     #[test]
     fn test_dynamic_gas_cost_data_copy() {
@@ -597,7 +928,7 @@ mod tests {
 
     #[test]
     fn test_sstore_gas_calculation() {
-        let gas_meter = GasMeter::new(1000000);
+        let gas_meter = GasMeter::with_spec(1000000, SpecId::Istanbul);
 
         // Setting a new value (from zero)
         let cost = gas_meter.calculate_sstore_cost(
@@ -624,6 +955,360 @@ mod tests {
         assert_eq!(cost, 800); // SLOAD_GAS
     }
 
+    #[test]
+    fn test_opcode_cost_per_fork() {
+        let cost = |spec, opcode| GasMeter::with_spec(1000000, spec).opcode_cost(opcode);
+
+        assert_eq!(cost(SpecId::Frontier, Opcode::Sload), 50);
+        assert_eq!(cost(SpecId::TangerineWhistle, Opcode::Sload), 200);
+        assert_eq!(cost(SpecId::Istanbul, Opcode::Sload), 800);
+        assert_eq!(cost(SpecId::Berlin, Opcode::Sload), 100);
+
+        assert_eq!(cost(SpecId::Homestead, Opcode::Balance), 20);
+        assert_eq!(cost(SpecId::Byzantium, Opcode::Balance), 400);
+        assert_eq!(cost(SpecId::Istanbul, Opcode::Balance), 700);
+        assert_eq!(cost(SpecId::Cancun, Opcode::Balance), 100);
+
+        assert_eq!(cost(SpecId::Frontier, Opcode::Call), 40);
+        assert_eq!(cost(SpecId::Frontier, Opcode::Selfdestruct), 0);
+        assert_eq!(cost(SpecId::London, Opcode::Selfdestruct), 5000);
+    }
+
+    #[test]
+    fn test_dynamic_gas_cost_per_fork() {
+        let params = DynamicGasParams::new().with_exponent(U256::from(256));
+        let frontier = GasMeter::with_spec(1000000, SpecId::Frontier);
+        assert_eq!(frontier.dynamic_gas_cost(Opcode::Exp, &params), 20); // 10 gas per byte
+
+        // SSTORE no-op is priced at the fork's SLOAD cost under net metering
+        let params = DynamicGasParams::new().with_storage_values(
+            U256::from(1),
+            U256::from(1),
+            U256::from(1),
+        );
+        let gas_meter = |spec| GasMeter::with_spec(1000000, spec);
+        assert_eq!(
+            gas_meter(SpecId::Byzantium).dynamic_gas_cost(Opcode::Sstore, &params),
+            5000
+        );
+        assert_eq!(
+            gas_meter(SpecId::Constantinople).dynamic_gas_cost(Opcode::Sstore, &params),
+            200
+        );
+        assert_eq!(
+            gas_meter(SpecId::Petersburg).dynamic_gas_cost(Opcode::Sstore, &params),
+            5000
+        );
+        assert_eq!(
+            gas_meter(SpecId::Istanbul).dynamic_gas_cost(Opcode::Sstore, &params),
+            800
+        );
+        assert_eq!(
+            gas_meter(SpecId::Berlin).dynamic_gas_cost(Opcode::Sstore, &params),
+            100
+        );
+
+        // Init code is only charged per word from Shanghai (EIP-3860)
+        let params = DynamicGasParams::new().with_size(64);
+        assert_eq!(
+            gas_meter(SpecId::London).dynamic_gas_cost(Opcode::Create, &params),
+            0
+        );
+        assert_eq!(
+            gas_meter(SpecId::Shanghai).dynamic_gas_cost(Opcode::Create, &params),
+            4
+        );
+    }
+
+    #[test]
+    fn test_cold_access_surcharge() {
+        let gas_meter = GasMeter::with_spec(1000000, SpecId::Cancun);
+        let warm = DynamicGasParams::new();
+        let cold = DynamicGasParams::new().with_cold_access(true);
+        let total = |opcode, params| {
+            gas_meter.opcode_cost(opcode) + gas_meter.dynamic_gas_cost(opcode, params)
+        };
+
+        assert_eq!(total(Opcode::Balance, &warm), 100);
+        assert_eq!(total(Opcode::Balance, &cold), 2600);
+        assert_eq!(total(Opcode::Sload, &warm), 100);
+        assert_eq!(total(Opcode::Sload, &cold), 2100);
+        assert_eq!(total(Opcode::Staticcall, &cold), 2600);
+        assert_eq!(total(Opcode::Selfdestruct, &cold), 7600);
+
+        // Cold reset of a slot costs 2900 + 2100, matching the pre-Berlin 5000
+        let reset = DynamicGasParams::new()
+            .with_storage_values(U256::from(1), U256::from(1), U256::from(2))
+            .with_cold_access(true);
+        assert_eq!(total(Opcode::Sstore, &reset), 5000);
+
+        // Warmth is irrelevant before Berlin
+        let istanbul = GasMeter::with_spec(1000000, SpecId::Istanbul);
+        assert_eq!(istanbul.dynamic_gas_cost(Opcode::Sload, &cold), 0);
+    }
+
+    #[test]
+    fn test_sstore_refunds() {
+        let refund = |spec, current: u64, original: u64, new: u64| {
+            GasMeter::with_spec(1000000, spec).calculate_sstore_refund(
+                U256::from(current),
+                U256::from(original),
+                U256::from(new),
+            )
+        };
+
+        // Clearing a clean slot
+        assert_eq!(refund(SpecId::Byzantium, 1, 1, 0), 15000);
+        assert_eq!(refund(SpecId::Istanbul, 1, 1, 0), 15000);
+        assert_eq!(refund(SpecId::London, 1, 1, 0), 4800);
+
+        // Legacy pricing refunds every clear, even of a slot set in this transaction
+        assert_eq!(refund(SpecId::Petersburg, 1, 0, 0), 15000);
+
+        // Re-setting a slot cleared earlier takes the clear refund back
+        assert_eq!(refund(SpecId::Istanbul, 0, 1, 2), -15000);
+        assert_eq!(refund(SpecId::London, 0, 1, 2), -4800);
+
+        // Restoring the original value refunds all but the SLOAD cost
+        assert_eq!(refund(SpecId::Istanbul, 2, 0, 0), 19200);
+        assert_eq!(refund(SpecId::Berlin, 2, 1, 1), 2800);
+        assert_eq!(refund(SpecId::London, 0, 1, 1), -4800 + 2800);
+        assert_eq!(refund(SpecId::Constantinople, 2, 1, 1), 4800);
+
+        // No-op writes never change the counter
+        assert_eq!(refund(SpecId::London, 1, 1, 1), 0);
+    }
+
+    #[test]
+    fn test_charge_sstore() {
+        let params = DynamicGasParams::new()
+            .with_storage_values(U256::from(1), U256::from(1), U256::ZERO)
+            .with_cold_access(true);
+        let mut gas_meter = GasMeter::with_spec(100000, SpecId::London);
+        gas_meter.charge_sstore(&params).unwrap();
+        assert_eq!(gas_meter.total_gas_used(), 5000);
+        assert_eq!(gas_meter.gas_refund(), 4800);
+
+        // EIP-2200: SSTORE fails when only the stipend is left
+        let mut gas_meter = GasMeter::with_spec(2300, SpecId::Istanbul);
+        assert_eq!(gas_meter.charge_sstore(&params), Err(GasError::OutOfGas));
+        let mut gas_meter = GasMeter::with_spec(2300, SpecId::Constantinople);
+        assert!(gas_meter.charge_sstore(&params).is_err());
+        assert_eq!(gas_meter.total_gas_used(), 0);
+    }
+
+    #[test]
+    fn test_refund_cap() {
+        let mut gas_meter = GasMeter::with_spec(100000, SpecId::Berlin);
+        gas_meter.consume_gas(50000).unwrap();
+        gas_meter.record_refund(40000);
+        let settlement = gas_meter.settle(0);
+        assert_eq!(settlement.gas_refund, 25000); // gas_used / 2
+        assert_eq!(settlement.gas_used, 25000);
+        assert_eq!(settlement.gas_returned, 75000);
+
+        let mut gas_meter = GasMeter::with_spec(100000, SpecId::London);
+        gas_meter.consume_gas(50000).unwrap();
+        gas_meter.record_refund(40000);
+        assert_eq!(gas_meter.settle(0).gas_refund, 10000); // gas_used / 5
+        assert_eq!(gas_meter.effective_gas_used(), 40000);
+
+        // A negative refund counter never adds gas
+        let mut gas_meter = GasMeter::with_spec(100000, SpecId::London);
+        gas_meter.consume_gas(50000).unwrap();
+        gas_meter.record_refund(-4800);
+        assert_eq!(gas_meter.settle(0).gas_used, 50000);
+    }
+
+    #[test]
+    fn test_calldata_floor() {
+        assert_eq!(calldata_floor_gas(&[]), 21000);
+        assert_eq!(calldata_floor_gas(&[0, 1, 0, 2]), 21000 + 10 * 10);
+
+        let mut gas_meter = GasMeter::with_spec(100000, SpecId::Prague);
+        gas_meter.consume_gas(21064).unwrap();
+        let floor = calldata_floor_gas(&[0xff; 100]);
+        assert_eq!(gas_meter.settle(floor).gas_used, 25000);
+        assert_eq!(gas_meter.settle(floor).gas_returned, 75000);
+
+        // The floor only applies from Prague
+        let mut gas_meter = GasMeter::with_spec(100000, SpecId::Cancun);
+        gas_meter.consume_gas(21064).unwrap();
+        assert_eq!(gas_meter.settle(floor).gas_used, 21064);
+    }
+
+    #[test]
+    fn test_intrinsic_gas() {
+        let no_access_list = AccessList::new();
+        let data = [0u8, 0, 1, 2];
+
+        let gas = intrinsic_gas(SpecId::Byzantium, &data, false, &no_access_list, 0);
+        assert_eq!(gas.initial_gas, 21000 + 2 * 4 + 2 * 68);
+        let gas = intrinsic_gas(SpecId::Istanbul, &data, false, &no_access_list, 0);
+        assert_eq!(gas.initial_gas, 21000 + 2 * 4 + 2 * 16);
+        assert_eq!(gas.floor_gas, 0);
+
+        // Creation adds 32000 plus, from Shanghai, 2 gas per init code word
+        let init_code = [0xffu8; 33];
+        let gas = intrinsic_gas(SpecId::Frontier, &init_code, true, &no_access_list, 0);
+        assert_eq!(gas.initial_gas, 21000 + 33 * 68);
+        let gas = intrinsic_gas(SpecId::London, &init_code, true, &no_access_list, 0);
+        assert_eq!(gas.initial_gas, 53000 + 33 * 16);
+        let gas = intrinsic_gas(SpecId::Shanghai, &init_code, true, &no_access_list, 0);
+        assert_eq!(gas.initial_gas, 53000 + 33 * 16 + 4);
+
+        let access_list = vec![AccessListItem {
+            address: Address::with_last_byte(1),
+            storage_keys: vec![StorageKey::ZERO, StorageKey::with_last_byte(1)],
+        }];
+        let gas = intrinsic_gas(SpecId::Istanbul, &[], false, &access_list, 0);
+        assert_eq!(gas.initial_gas, 21000);
+        let gas = intrinsic_gas(SpecId::Berlin, &[], false, &access_list, 0);
+        assert_eq!(gas.initial_gas, 21000 + 2400 + 2 * 1900);
+
+        let gas = intrinsic_gas(SpecId::Prague, &[], false, &no_access_list, 2);
+        assert_eq!(gas.initial_gas, 21000 + 2 * 25000);
+    }
+
+    #[test]
+    fn test_intrinsic_gas_floor() {
+        let data = [0xffu8; 100];
+        let gas = intrinsic_gas(SpecId::Prague, &data, false, &AccessList::new(), 0);
+        assert_eq!(gas.initial_gas, 21000 + 100 * 16);
+        assert_eq!(gas.floor_gas, 21000 + 100 * 40);
+        assert_eq!(gas.minimum_gas_limit(), 25000);
+    }
+
+    #[test]
+    fn test_call_dynamic_cost() {
+        let gas_meter = GasMeter::with_spec(1000000, SpecId::Cancun);
+        let value = DynamicGasParams::new().with_call_params(U256::from(1), false);
+        assert_eq!(gas_meter.dynamic_gas_cost(Opcode::Call, &value), 9000);
+        assert_eq!(gas_meter.dynamic_gas_cost(Opcode::Callcode, &value), 9000);
+        assert_eq!(gas_meter.dynamic_gas_cost(Opcode::Delegatecall, &value), 0);
+
+        // EIP-161: calling an empty account only costs 25000 when value is sent
+        let empty = DynamicGasParams::new().with_call_params(U256::ZERO, true);
+        assert_eq!(gas_meter.dynamic_gas_cost(Opcode::Call, &empty), 0);
+        let homestead = GasMeter::with_spec(1000000, SpecId::Homestead);
+        assert_eq!(homestead.dynamic_gas_cost(Opcode::Call, &empty), 25000);
+        let funded = DynamicGasParams::new().with_call_params(U256::from(1), true);
+        assert_eq!(gas_meter.dynamic_gas_cost(Opcode::Call, &funded), 34000);
+
+        // Memory expansion covers the furthest of the argument and return ranges
+        let memory = DynamicGasParams::new().with_call_memory(32, (0, 64), (64, 32));
+        assert_eq!(memory.new_memory_size, 96);
+        assert_eq!(gas_meter.dynamic_gas_cost(Opcode::Staticcall, &memory), 6);
+        let empty_ranges = DynamicGasParams::new().with_call_memory(0, (1000, 0), (2000, 0));
+        assert_eq!(empty_ranges.new_memory_size, 0);
+    }
+
+    #[test]
+    fn test_call_gas_forwarding() {
+        // All but one 64th of the gas left after charging is forwarded
+        let gas_meter = GasMeter::with_spec(100000, SpecId::Cancun);
+        let params = DynamicGasParams::new()
+            .with_requested_gas(U256::MAX)
+            .with_cold_access(true);
+        let cost = gas_meter.call_gas(Opcode::Call, &params).unwrap();
+        assert_eq!(cost.charged, 2600);
+        assert_eq!(cost.forwarded, 97400 - 97400 / 64);
+        assert_eq!(cost.stipend, 0);
+
+        // Smaller requests are honoured; value transfers add the stipend
+        let params = DynamicGasParams::new()
+            .with_requested_gas(U256::from(5000))
+            .with_call_params(U256::from(1), false);
+        let cost = gas_meter.call_gas(Opcode::Call, &params).unwrap();
+        assert_eq!(cost.charged, 100 + 9000);
+        assert_eq!(cost.forwarded, 5000);
+        assert_eq!(cost.callee_gas_limit(), 7300);
+
+        // Before EIP-150 the requested gas must be affordable
+        let frontier = GasMeter::with_spec(10000, SpecId::Frontier);
+        let params = DynamicGasParams::new().with_requested_gas(U256::from(9960));
+        assert_eq!(
+            frontier.call_gas(Opcode::Call, &params).unwrap().forwarded,
+            9960
+        );
+        let params = DynamicGasParams::new().with_requested_gas(U256::from(9961));
+        assert_eq!(
+            frontier.call_gas(Opcode::Call, &params),
+            Err(GasError::OutOfGas)
+        );
+
+        let gas_meter = GasMeter::with_spec(2000, SpecId::Cancun);
+        let params = DynamicGasParams::new().with_cold_access(true);
+        assert_eq!(
+            gas_meter.call_gas(Opcode::Call, &params),
+            Err(GasError::OutOfGas)
+        );
+    }
+
+    #[test]
+    fn test_charge_memory_expansion() {
+        let mut gas_meter = GasMeter::new(1000000);
+        assert_eq!(gas_meter.charge_memory_expansion(64).unwrap(), 6);
+        assert_eq!(gas_meter.charge_memory_expansion(32).unwrap(), 0);
+        assert_eq!(gas_meter.charge_memory_expansion(96).unwrap(), 3);
+        assert_eq!(gas_meter.memory_gas_cost(), 9);
+        assert_eq!(gas_meter.total_gas_used(), 9);
+
+        let mut gas_meter = GasMeter::new(5);
+        assert!(gas_meter.charge_memory_expansion(64).is_err());
+        assert_eq!(gas_meter.memory_gas_cost(), 0);
+    }
+
+    #[test]
+    fn test_consume_gas_near_u64_max() {
+        let mut gas_meter = GasMeter::new(100);
+        gas_meter.consume_gas(1).unwrap();
+        assert_eq!(
+            gas_meter.consume_gas(u64::MAX),
+            Err(GasError::GasLimitExceeded)
+        );
+        gas_meter.consume_gas(99).unwrap();
+        assert_eq!(gas_meter.remaining_gas(), 0);
+    }
+
+    #[test]
+    fn test_extreme_schedule_saturates() {
+        let schedule = GasSchedule::from_json(&format!(
+            r#"{{"spec": "Cancun", "copy_word_cost": {max}, "keccak_word_cost": {max},
+                "exp_byte_cost": {max}, "log_byte_cost": {max}, "memory_word_cost": {max},
+                "init_code_word_cost": {max}, "call_value_cost": {max},
+                "new_account_cost": {max}}}"#,
+            max = u64::MAX
+        ))
+        .unwrap();
+        let mut gas_meter = GasMeter::with_schedule(1000000, schedule);
+
+        let params = DynamicGasParams::new()
+            .with_size(64)
+            .with_exponent(U256::MAX)
+            .with_call_params(U256::from(1), true)
+            .with_call_memory(0, (0, 64), (0, 0));
+        for opcode in [
+            Opcode::Calldatacopy,
+            Opcode::Keccak256,
+            Opcode::Exp,
+            Opcode::Log0,
+            Opcode::Create2,
+            Opcode::Call,
+        ] {
+            assert_eq!(gas_meter.dynamic_gas_cost(opcode, &params), u64::MAX);
+        }
+        assert_eq!(
+            gas_meter.call_gas(Opcode::Call, &params),
+            Err(GasError::OutOfGas)
+        );
+        assert_eq!(
+            gas_meter.charge_memory_expansion(64),
+            Err(GasError::GasLimitExceeded)
+        );
+        assert_eq!(gas_meter.total_gas_used(), 0);
+    }
+
     #[test]
     fn test_dynamic_gas_params_builder() {
         let params = DynamicGasParams::new()
@@ -640,4 +1325,18 @@ mod tests {
         assert_eq!(params.balance, U256::from(5000));
         assert!(params.is_account_empty);
     }
+
+    #[test]
+    fn test_unmetered() {
+        let mut meter = GasMeter::unmetered(100, GasSchedule::for_spec(SpecId::Cancun));
+        assert!(!meter.is_metered());
+        assert_eq!(meter.opcode_cost(Opcode::Sload), 0);
+        assert_eq!(meter.charge_memory_expansion(1 << 20), Ok(0));
+        assert!(meter.consume_gas(1_000_000).is_ok());
+        meter.record_refund(4800);
+        let params =
+            DynamicGasParams::new().with_storage_values(U256::ZERO, U256::ZERO, U256::from(1));
+        assert!(meter.charge_sstore(&params).is_ok());
+        assert_eq!((meter.remaining_gas(), meter.gas_refund()), (100, 0));
+    }
 }
@@ -0,0 +1,239 @@
+//! Inspector
+//!
+//! Hooks the interpreter calls while it executes: around every instruction, around every
+//! message call and contract creation, and when code emits a log or self-destructs.
+//! Tracers, profilers and debuggers implement [`Inspector`] and run a transaction with
+//! [`Evm::inspect_transact`].
+//!
+//! # Design
+//! - Every hook has an empty default, so an inspector implements only what it needs
+//! - Hooks get the interpreter read-only: the executing [`Frame`] exposes its stack,
+//!   memory and gas meter, and [`Evm::journal`] the state
+//! - Messages include the transaction's own: `call`/`create` fire for it too when the
+//!   transaction is begun with [`Evm::inspect_begin`]
+//! - `call` and `create` can answer a message themselves, e.g. to mock a contract; the
+//!   message then neither runs nor transfers value. `call_end` and `create_end` can
+//!   rewrite the result the caller sees, but not the state changes already made or
+//!   reverted
+
+use super::execution::{Evm, ExitReason, StepReport};
+use super::frame::{CallContext, CallScheme, CreateScheme, Frame};
+use super::opcodes::Opcode;
+use crate::types::{Address, Bytes, Log, U256};
+
+/// A message call about to run, from a `CALL`-family instruction or the transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallInputs {
+    pub scheme: CallScheme,
+    pub context: CallContext,
+    /// Gas given to the callee, including any stipend.
+    pub gas_limit: u64,
+}
+
+impl CallInputs {
+    /// Returns the value moved from the caller to the callee; only `CALL` transfers.
+    pub fn transfer(&self) -> U256 {
+        match self.scheme {
+            CallScheme::Call => self.context.value,
+            CallScheme::Callcode | CallScheme::Delegatecall | CallScheme::Staticcall => U256::ZERO,
+        }
+    }
+}
+
+/// A contract creation about to run, from `CREATE`/`CREATE2` or the transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreateInputs {
+    pub scheme: CreateScheme,
+    /// The creating account.
+    pub caller: Address,
+    /// Endowment of the new contract.
+    pub value: U256,
+    pub init_code: Bytes,
+    /// Gas given to the init code.
+    pub gas_limit: u64,
+}
+
+/// The result of a message call, as its caller sees it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallOutcome {
+    pub reason: ExitReason,
+    pub output: Bytes,
+    /// Gas returned to the caller, at most the call's gas limit.
+    pub gas_remaining: u64,
+}
+
+/// The result of a contract creation, as its creator sees it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreateOutcome {
+    pub reason: ExitReason,
+    /// Revert data, or the deployed code on success.
+    pub output: Bytes,
+    /// Gas returned to the creator, at most the creation's gas limit.
+    pub gas_remaining: u64,
+    /// Address of the new contract, `None` unless the creation succeeded.
+    pub address: Option<Address>,
+}
+
+/// Hooks into execution. See the [module documentation](self).
+#[allow(unused_variables)]
+pub trait Inspector {
+    /// Called before an instruction runs, with `frame` about to run it. `opcode` is
+    /// `None` if the byte at the program counter is not an opcode in the active fork.
+    fn step(&mut self, evm: &Evm, frame: &Frame, opcode: Option<Opcode>) {}
+
+    /// Called after an instruction ran, including any message it opened that finished
+    /// immediately.
+    fn step_end(&mut self, evm: &Evm, step: &StepReport) {}
+
+    /// Called before a message call runs. Returning an outcome skips the call.
+    fn call(&mut self, evm: &Evm, inputs: &CallInputs) -> Option<CallOutcome> {
+        None
+    }
+
+    /// Called when a message call finished, before its caller sees `outcome`.
+    fn call_end(&mut self, evm: &Evm, inputs: &CallInputs, outcome: &mut CallOutcome) {}
+
+    /// Called before a contract creation runs. Returning an outcome skips the creation.
+    fn create(&mut self, evm: &Evm, inputs: &CreateInputs) -> Option<CreateOutcome> {
+        None
+    }
+
+    /// Called when a contract creation finished, before its creator sees `outcome`.
+    fn create_end(&mut self, evm: &Evm, inputs: &CreateInputs, outcome: &mut CreateOutcome) {}
+
+    /// Called when code emits a log.
+    fn log(&mut self, evm: &Evm, log: &Log) {}
+
+    /// Called when `address` self-destructs, sending `value` to `target`.
+    fn selfdestruct(&mut self, evm: &Evm, address: Address, target: Address, value: U256) {}
+}
+
+/// An inspector that does nothing; plain execution uses it.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopInspector;
+
+impl Inspector for NoopInspector {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::assembler::assemble;
+    use crate::evm::env::TxEnv;
+    use crate::evm::storage::Account;
+    use crate::evm::test_utils::{self, call, CONTRACT};
+    use crate::types::B256;
+
+    const MOCKED: Address = Address::with_last_byte(0xc1);
+
+    /// Records hooks as text and answers calls to `MOCKED` with 42.
+    #[derive(Default)]
+    struct Recorder {
+        steps: u64,
+        events: Vec<String>,
+    }
+
+    impl Inspector for Recorder {
+        fn step(&mut self, _evm: &Evm, frame: &Frame, opcode: Option<Opcode>) {
+            self.steps += 1;
+            if opcode == Some(Opcode::Sstore) {
+                // Key on top, value below it
+                let stack = frame.stack().as_slice();
+                let value = stack[stack.len() - 2];
+                self.events.push(format!("sstore {value}"));
+            }
+        }
+
+        fn call(&mut self, _evm: &Evm, inputs: &CallInputs) -> Option<CallOutcome> {
+            self.events.push(format!("call {}", inputs.context.address));
+            (inputs.context.address == MOCKED).then(|| CallOutcome {
+                reason: ExitReason::Return,
+                output: B256::with_last_byte(42).into(),
+                gas_remaining: inputs.gas_limit,
+            })
+        }
+
+        fn call_end(&mut self, _evm: &Evm, _inputs: &CallInputs, outcome: &mut CallOutcome) {
+            self.events.push(format!("call_end {:?}", outcome.reason));
+        }
+
+        fn create(&mut self, _evm: &Evm, inputs: &CreateInputs) -> Option<CreateOutcome> {
+            self.events.push(format!("create {}", inputs.init_code));
+            Some(CreateOutcome {
+                reason: ExitReason::Return,
+                output: Bytes::new(),
+                gas_remaining: inputs.gas_limit,
+                address: Some(MOCKED),
+            })
+        }
+
+        fn log(&mut self, _evm: &Evm, log: &Log) {
+            self.events.push(format!("log {}", log.address));
+        }
+
+        fn selfdestruct(&mut self, _evm: &Evm, address: Address, target: Address, value: U256) {
+            self.events
+                .push(format!("selfdestruct {address} {target} {value}"));
+        }
+    }
+
+    fn evm() -> Evm {
+        // Call MOCKED for a word, store it, log, then self-destruct to 0xbe
+        let code = assemble(
+            "PUSH1 32 PUSH0 PUSH0 PUSH0 PUSH0 PUSH1 0xc1 GAS CALL
+             PUSH0 MLOAD PUSH0 SSTORE
+             PUSH0 PUSH0 LOG0
+             PUSH1 0xbe SELFDESTRUCT",
+        )
+        .unwrap();
+        let mut contract = Account::with_code(code.into());
+        contract.balance = U256::from(5);
+        Evm::new().with_state(test_utils::state(contract))
+    }
+
+    #[test]
+    fn test_hooks_and_mocked_call() {
+        let mut evm = evm();
+        let mut recorder = Recorder::default();
+        let result = evm.inspect_transact(call(), &mut recorder).unwrap();
+        assert_eq!(result.exit_reason, ExitReason::SelfDestruct);
+        assert_eq!(recorder.steps, evm.steps());
+        assert_eq!(
+            recorder.events,
+            [
+                format!("call {CONTRACT}"),
+                format!("call {MOCKED}"),
+                "call_end Return".to_string(),
+                "sstore 42".to_string(),
+                format!("log {CONTRACT}"),
+                format!(
+                    "selfdestruct {CONTRACT} {} 5",
+                    Address::with_last_byte(0xbe)
+                ),
+                "call_end SelfDestruct".to_string(),
+            ]
+        );
+        assert_eq!(evm.state().storage(&CONTRACT, &B256::ZERO), U256::from(42));
+
+        // Without the inspector the call finds no code and returns nothing
+        let mut evm = self::evm();
+        evm.transact(call()).unwrap();
+        assert_eq!(evm.state().storage(&CONTRACT, &B256::ZERO), U256::ZERO);
+    }
+
+    #[test]
+    fn test_mocked_create() {
+        let mut evm = evm();
+        let mut recorder = Recorder::default();
+        let tx = TxEnv {
+            to: None,
+            data: Bytes::from_static(&[0x00]),
+            ..call()
+        };
+        let result = evm.inspect_transact(tx, &mut recorder).unwrap();
+        assert!(result.is_success());
+        assert_eq!(result.created_address, Some(MOCKED));
+        assert_eq!(recorder.steps, 0);
+        assert_eq!(recorder.events, ["create 0x00"]);
+        assert!(evm.state().account(&MOCKED).is_none());
+    }
+}
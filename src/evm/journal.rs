@@ -0,0 +1,579 @@
+//! EVM State Journal
+//!
+//! Records every revertible change made during a transaction so that a failing call frame
+//! can roll back exactly the changes it made. Checkpoints are taken when a frame starts and
+//! either kept (frame succeeded) or reverted (frame failed).
+//!
+//! # Design
+//! - An append-only log of [`JournalEntry`] values, undone in reverse order on revert
+//! - Tracks the EIP-2929 access set: warm addresses and warm (address, slot) pairs
+//! - Pre-warmed entries are not journaled, so they survive any revert
+//! - Owns the [`WorldState`]; balance, nonce, code, storage, transient storage and log
+//!   changes are journaled with their previous value
+//! - Original storage values (EIP-2200) are recorded on first access and kept until
+//!   [`Journal::finalize`]
+//!
+//! # References
+//! - [EIP-2929: Gas cost increases for state access opcodes](https://eips.ethereum.org/EIPS/eip-2929)
+//! - [EIP-2930: Optional access lists](https://eips.ethereum.org/EIPS/eip-2930)
+//! - [EIP-1153: Transient storage opcodes](https://eips.ethereum.org/EIPS/eip-1153)
+//! - [EIP-6780: SELFDESTRUCT only in same transaction](https://eips.ethereum.org/EIPS/eip-6780)
+
+use std::collections::{HashMap, HashSet};
+
+use super::spec::SpecId;
+use super::storage::{Account, WorldState};
+use crate::types::{AccessList, Address, Bytes, Log, StorageKey, StorageValue, B256, U256};
+
+/// A single revertible change recorded by the journal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JournalEntry {
+    /// An address was added to the warm set.
+    AccountWarmed(Address),
+    /// A storage slot was added to the warm set.
+    SlotWarmed(Address, StorageKey),
+    /// An account that did not exist was created.
+    AccountCreated(Address),
+    /// An account was touched (EIP-161).
+    AccountTouched(Address),
+    /// An account's balance changed from the given value.
+    BalanceChanged(Address, U256),
+    /// An account's nonce changed from the given value.
+    NonceChanged(Address, u64),
+    /// An account's code changed from the given value.
+    CodeChanged(Address, Bytes),
+    /// A storage slot changed from the given value.
+    StorageChanged(Address, StorageKey, StorageValue),
+    /// A transient storage slot changed from the given value.
+    TransientStorageChanged(Address, StorageKey, StorageValue),
+    /// A contract was created at the address in this transaction.
+    ContractCreated(Address),
+    /// The account was marked for self-destruction.
+    SelfDestructed(Address),
+    /// A log was emitted.
+    LogAdded,
+}
+
+/// A position in the journal that can later be reverted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JournalCheckpoint {
+    entries: usize,
+}
+
+/// The transaction journal, including the EIP-2929 access set and the world state.
+///
+/// # Invariants
+/// - Every journaled change appears exactly once in `entries`, in the order it was made.
+/// - Reverting to a checkpoint leaves the state exactly as it was when it was taken.
+#[derive(Debug, Clone, Default)]
+pub struct Journal {
+    /// Log of revertible changes, oldest first.
+    entries: Vec<JournalEntry>,
+    /// Addresses accessed so far in the transaction.
+    warm_addresses: HashSet<Address>,
+    /// Storage slots accessed so far in the transaction.
+    warm_slots: HashSet<(Address, StorageKey)>,
+    /// The world state the transaction operates on.
+    state: WorldState,
+    /// Storage values as they were at the start of the transaction.
+    original_storage: HashMap<(Address, StorageKey), StorageValue>,
+    /// Transient storage (EIP-1153), cleared at the end of every transaction.
+    transient_storage: HashMap<(Address, StorageKey), StorageValue>,
+    /// Accounts touched in the transaction (EIP-161).
+    touched: HashSet<Address>,
+    /// Contracts created in the transaction (EIP-6780).
+    created: HashSet<Address>,
+    /// Accounts marked for self-destruction.
+    destroyed: HashSet<Address>,
+    /// Logs emitted so far in the transaction.
+    logs: Vec<Log>,
+}
+
+impl Journal {
+    /// Creates an empty journal with no warm entries.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a journal operating on the given world state.
+    pub fn with_state(state: WorldState) -> Self {
+        Self {
+            state,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the world state.
+    pub fn state(&self) -> &WorldState {
+        &self.state
+    }
+
+    /// Consumes the journal, returning the world state.
+    pub fn into_state(self) -> WorldState {
+        self.state
+    }
+
+    /// Pre-warms the access set at the start of a transaction.
+    ///
+    /// Per EIP-2929 the sender, the target and the precompiles start warm; EIP-3651 adds the
+    /// block's coinbase from Shanghai, and any EIP-2930 access list entries are added too.
+    /// Nothing is warmed before Berlin, where access costs do not depend on warmth.
+    pub fn prewarm(
+        &mut self,
+        spec: SpecId,
+        origin: Address,
+        target: Address,
+        coinbase: Address,
+        access_list: &AccessList,
+    ) {
+        if !spec.is_enabled_in(SpecId::Berlin) {
+            return;
+        }
+        self.warm_addresses.insert(origin);
+        self.warm_addresses.insert(target);
+        self.warm_addresses.extend(spec.precompiles());
+        if spec.is_enabled_in(SpecId::Shanghai) {
+            self.warm_addresses.insert(coinbase);
+        }
+        for item in access_list {
+            self.warm_addresses.insert(item.address);
+            for key in &item.storage_keys {
+                self.warm_slots.insert((item.address, *key));
+            }
+        }
+    }
+
+    /// Marks an address as accessed.
+    ///
+    /// Returns `true` if the address was cold, i.e. this is its first access.
+    pub fn warm_account(&mut self, address: Address) -> bool {
+        let was_cold = self.warm_addresses.insert(address);
+        if was_cold {
+            self.entries.push(JournalEntry::AccountWarmed(address));
+        }
+        was_cold
+    }
+
+    /// Marks a storage slot as accessed.
+    ///
+    /// Returns `true` if the slot was cold, i.e. this is its first access.
+    pub fn warm_slot(&mut self, address: Address, key: StorageKey) -> bool {
+        let was_cold = self.warm_slots.insert((address, key));
+        if was_cold {
+            self.entries.push(JournalEntry::SlotWarmed(address, key));
+        }
+        was_cold
+    }
+
+    /// Returns `true` if the address has been accessed in this transaction.
+    pub fn is_warm_account(&self, address: &Address) -> bool {
+        self.warm_addresses.contains(address)
+    }
+
+    /// Returns `true` if the storage slot has been accessed in this transaction.
+    pub fn is_warm_slot(&self, address: &Address, key: &StorageKey) -> bool {
+        self.warm_slots.contains(&(*address, *key))
+    }
+
+    /// Returns a checkpoint for the current state of the journal.
+    pub fn checkpoint(&self) -> JournalCheckpoint {
+        JournalCheckpoint {
+            entries: self.entries.len(),
+        }
+    }
+
+    /// Undoes every change made since the checkpoint was taken.
+    pub fn revert(&mut self, checkpoint: JournalCheckpoint) {
+        while self.entries.len() > checkpoint.entries {
+            match self.entries.pop() {
+                Some(JournalEntry::AccountWarmed(address)) => {
+                    self.warm_addresses.remove(&address);
+                }
+                Some(JournalEntry::SlotWarmed(address, key)) => {
+                    self.warm_slots.remove(&(address, key));
+                }
+                Some(JournalEntry::AccountCreated(address)) => {
+                    self.state.remove_account(&address);
+                }
+                Some(JournalEntry::AccountTouched(address)) => {
+                    self.touched.remove(&address);
+                }
+                Some(JournalEntry::BalanceChanged(address, balance)) => {
+                    self.state.account_or_default(address).balance = balance;
+                }
+                Some(JournalEntry::NonceChanged(address, nonce)) => {
+                    self.state.account_or_default(address).nonce = nonce;
+                }
+                Some(JournalEntry::CodeChanged(address, code)) => {
+                    self.state.account_or_default(address).code = code;
+                }
+                Some(JournalEntry::StorageChanged(address, key, value)) => {
+                    self.state.set_storage(address, key, value);
+                }
+                Some(JournalEntry::TransientStorageChanged(address, key, value)) => {
+                    self.transient_storage.insert((address, key), value);
+                }
+                Some(JournalEntry::ContractCreated(address)) => {
+                    self.created.remove(&address);
+                }
+                Some(JournalEntry::SelfDestructed(address)) => {
+                    self.destroyed.remove(&address);
+                }
+                Some(JournalEntry::LogAdded) => {
+                    self.logs.pop();
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Returns the account at `address`, if it exists.
+    pub fn account(&self, address: &Address) -> Option<&Account> {
+        self.state.account(address)
+    }
+
+    /// Returns `true` if the account does not exist or, from Spurious Dragon, is empty
+    /// (EIP-161). Such accounts are "dead" for the purpose of new-account gas charges.
+    pub fn is_dead(&self, spec: SpecId, address: &Address) -> bool {
+        match self.state.account(address) {
+            None => true,
+            Some(account) => spec.is_enabled_in(SpecId::SpuriousDragon) && account.is_empty(),
+        }
+    }
+
+    /// Returns the balance of `address`, which is zero for missing accounts.
+    pub fn balance(&self, address: &Address) -> U256 {
+        self.account(address)
+            .map(|account| account.balance)
+            .unwrap_or_default()
+    }
+
+    /// Returns the nonce of `address`, which is zero for missing accounts.
+    pub fn nonce(&self, address: &Address) -> u64 {
+        self.account(address)
+            .map(|account| account.nonce)
+            .unwrap_or_default()
+    }
+
+    /// Returns the code of `address`, which is empty for missing accounts.
+    pub fn code(&self, address: &Address) -> Bytes {
+        self.account(address)
+            .map(|account| account.code.clone())
+            .unwrap_or_default()
+    }
+
+    /// Returns the code hash of `address` as seen by `EXTCODEHASH`: zero for accounts
+    /// that do not exist or are empty (EIP-1052, EIP-161).
+    pub fn code_hash(&self, address: &Address) -> B256 {
+        match self.account(address) {
+            Some(account) if !account.is_empty() => account.code_hash(),
+            _ => B256::ZERO,
+        }
+    }
+
+    /// Returns the account at `address` for modification, creating it if needed.
+    fn account_mut(&mut self, address: Address) -> &mut Account {
+        if !self.state.contains(&address) {
+            self.entries.push(JournalEntry::AccountCreated(address));
+        }
+        self.state.account_or_default(address)
+    }
+
+    /// Marks an account as touched, creating it if needed.
+    ///
+    /// From Spurious Dragon, touched accounts that end the transaction empty are deleted
+    /// (EIP-161).
+    pub fn touch(&mut self, address: Address) {
+        self.account_mut(address);
+        if self.touched.insert(address) {
+            self.entries.push(JournalEntry::AccountTouched(address));
+        }
+    }
+
+    /// Sets the balance of `address`.
+    pub fn set_balance(&mut self, address: Address, balance: U256) {
+        let account = self.account_mut(address);
+        let previous = std::mem::replace(&mut account.balance, balance);
+        self.entries
+            .push(JournalEntry::BalanceChanged(address, previous));
+    }
+
+    /// Moves `value` wei from `from` to `to`, touching both accounts.
+    ///
+    /// Returns `false`, changing nothing, if `from` cannot afford the transfer.
+    pub fn transfer(&mut self, from: Address, to: Address, value: U256) -> bool {
+        let from_balance = self.balance(&from);
+        if from_balance < value {
+            return false;
+        }
+        self.touch(from);
+        self.touch(to);
+        if from != to && !value.is_zero() {
+            self.set_balance(from, from_balance - value);
+            let to_balance = self.balance(&to);
+            self.set_balance(to, to_balance.saturating_add(value));
+        }
+        true
+    }
+
+    /// Increments the nonce of `address`, returning the previous nonce.
+    ///
+    /// Returns `None`, changing nothing, if the nonce is already at its maximum.
+    pub fn increment_nonce(&mut self, address: Address) -> Option<u64> {
+        let previous = self.nonce(&address);
+        let next = previous.checked_add(1)?;
+        self.set_nonce(address, next);
+        Some(previous)
+    }
+
+    /// Sets the nonce of `address`.
+    pub fn set_nonce(&mut self, address: Address, nonce: u64) {
+        let account = self.account_mut(address);
+        let previous = std::mem::replace(&mut account.nonce, nonce);
+        self.entries
+            .push(JournalEntry::NonceChanged(address, previous));
+    }
+
+    /// Sets the code of `address`.
+    pub fn set_code(&mut self, address: Address, code: Bytes) {
+        let account = self.account_mut(address);
+        let previous = std::mem::replace(&mut account.code, code);
+        self.entries
+            .push(JournalEntry::CodeChanged(address, previous));
+    }
+
+    /// Reads a storage slot, recording its original value on first access.
+    pub fn sload(&mut self, address: Address, key: StorageKey) -> StorageValue {
+        let value = self.state.storage(&address, &key);
+        self.original_storage.entry((address, key)).or_insert(value);
+        value
+    }
+
+    /// Returns the value the slot had at the start of the transaction.
+    pub fn original_storage(&self, address: &Address, key: &StorageKey) -> StorageValue {
+        self.original_storage
+            .get(&(*address, *key))
+            .copied()
+            .unwrap_or_else(|| self.state.storage(address, key))
+    }
+
+    /// Writes a storage slot.
+    pub fn sstore(&mut self, address: Address, key: StorageKey, value: StorageValue) {
+        let previous = self.sload(address, key);
+        self.account_mut(address);
+        self.state.set_storage(address, key, value);
+        self.entries
+            .push(JournalEntry::StorageChanged(address, key, previous));
+    }
+
+    /// Reads a transient storage slot (EIP-1153).
+    pub fn tload(&self, address: &Address, key: &StorageKey) -> StorageValue {
+        self.transient_storage
+            .get(&(*address, *key))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Writes a transient storage slot (EIP-1153).
+    pub fn tstore(&mut self, address: Address, key: StorageKey, value: StorageValue) {
+        let previous = self
+            .transient_storage
+            .insert((address, key), value)
+            .unwrap_or_default();
+        self.entries.push(JournalEntry::TransientStorageChanged(
+            address, key, previous,
+        ));
+    }
+
+    /// Records that a contract was created at `address` in this transaction.
+    pub fn mark_created(&mut self, address: Address) {
+        if self.created.insert(address) {
+            self.entries.push(JournalEntry::ContractCreated(address));
+        }
+    }
+
+    /// Returns `true` if a contract was created at `address` in this transaction.
+    pub fn is_created(&self, address: &Address) -> bool {
+        self.created.contains(address)
+    }
+
+    /// Marks `address` for deletion at the end of the transaction.
+    ///
+    /// Returns `true` if the account was not already marked.
+    pub fn mark_destroyed(&mut self, address: Address) -> bool {
+        let newly_destroyed = self.destroyed.insert(address);
+        if newly_destroyed {
+            self.entries.push(JournalEntry::SelfDestructed(address));
+        }
+        newly_destroyed
+    }
+
+    /// Returns `true` if `address` is marked for self-destruction.
+    pub fn is_destroyed(&self, address: &Address) -> bool {
+        self.destroyed.contains(address)
+    }
+
+    /// Records an emitted log.
+    pub fn log(&mut self, log: Log) {
+        self.logs.push(log);
+        self.entries.push(JournalEntry::LogAdded);
+    }
+
+    /// Returns the logs emitted so far.
+    pub fn logs(&self) -> &[Log] {
+        &self.logs
+    }
+
+    /// Ends the transaction: deletes self-destructed accounts and, from Spurious Dragon,
+    /// touched empty accounts, then clears all per-transaction data.
+    ///
+    /// Returns the logs emitted by the transaction.
+    pub fn finalize(&mut self, spec: SpecId) -> Vec<Log> {
+        for address in self.destroyed.drain() {
+            self.state.remove_account(&address);
+        }
+        if spec.is_enabled_in(SpecId::SpuriousDragon) {
+            for address in self.touched.drain() {
+                if self
+                    .state
+                    .account(&address)
+                    .is_some_and(|account| account.is_empty())
+                {
+                    self.state.remove_account(&address);
+                }
+            }
+        }
+        self.entries.clear();
+        self.warm_addresses.clear();
+        self.warm_slots.clear();
+        self.original_storage.clear();
+        self.transient_storage.clear();
+        self.touched.clear();
+        self.created.clear();
+        std::mem::take(&mut self.logs)
+    }
+
+    /// Returns the number of journaled changes.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no changes have been journaled.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AccessListItem;
+
+    #[test]
+    fn test_first_access_is_cold() {
+        let mut journal = Journal::new();
+        let address = Address::with_last_byte(0xaa);
+        assert!(journal.warm_account(address));
+        assert!(!journal.warm_account(address));
+        assert!(journal.is_warm_account(&address));
+
+        let key = StorageKey::with_last_byte(1);
+        assert!(journal.warm_slot(address, key));
+        assert!(!journal.warm_slot(address, key));
+    }
+
+    #[test]
+    fn test_revert_cools_entries() {
+        let mut journal = Journal::new();
+        let address = Address::with_last_byte(0xaa);
+        let key = StorageKey::with_last_byte(1);
+        journal.warm_account(address);
+
+        let checkpoint = journal.checkpoint();
+        journal.warm_slot(address, key);
+        journal.warm_account(Address::with_last_byte(0xbb));
+        journal.revert(checkpoint);
+
+        assert!(journal.is_warm_account(&address));
+        assert!(!journal.is_warm_slot(&address, &key));
+        assert!(!journal.is_warm_account(&Address::with_last_byte(0xbb)));
+        assert_eq!(journal.len(), 1);
+    }
+
+    #[test]
+    fn test_prewarm() {
+        let origin = Address::with_last_byte(0x10);
+        let target = Address::with_last_byte(0x20);
+        let coinbase = Address::with_last_byte(0x30);
+        let listed = Address::with_last_byte(0x40);
+        let key = StorageKey::with_last_byte(7);
+        let access_list = vec![AccessListItem {
+            address: listed,
+            storage_keys: vec![key],
+        }];
+
+        let mut journal = Journal::new();
+        journal.prewarm(SpecId::Shanghai, origin, target, coinbase, &access_list);
+        for address in [origin, target, coinbase, listed, Address::with_last_byte(1)] {
+            assert!(journal.is_warm_account(&address));
+        }
+        assert!(journal.is_warm_slot(&listed, &key));
+
+        // Pre-warmed entries survive a revert to the very first checkpoint
+        journal.revert(JournalCheckpoint { entries: 0 });
+        assert!(journal.is_warm_account(&origin));
+
+        // Coinbase is only warm from Shanghai (EIP-3651)
+        let mut journal = Journal::new();
+        journal.prewarm(SpecId::London, origin, target, coinbase, &access_list);
+        assert!(!journal.is_warm_account(&coinbase));
+    }
+
+    #[test]
+    fn test_revert_state_changes() {
+        let alice = Address::with_last_byte(0xa1);
+        let bob = Address::with_last_byte(0xb0);
+        let key = StorageKey::with_last_byte(1);
+        let mut state = WorldState::new();
+        state.insert_account(alice, Account::with_balance(U256::from(100)));
+        let mut journal = Journal::with_state(state);
+
+        let checkpoint = journal.checkpoint();
+        assert!(journal.transfer(alice, bob, U256::from(40)));
+        assert!(!journal.transfer(alice, bob, U256::from(1000)));
+        journal.increment_nonce(alice);
+        journal.sstore(alice, key, U256::from(5));
+        journal.tstore(alice, key, U256::from(6));
+        journal.log(Log::default());
+        assert_eq!(journal.balance(&bob), U256::from(40));
+        assert_eq!(journal.original_storage(&alice, &key), U256::ZERO);
+
+        journal.revert(checkpoint);
+        assert_eq!(journal.balance(&alice), U256::from(100));
+        assert_eq!(journal.nonce(&alice), 0);
+        assert!(journal.account(&bob).is_none());
+        assert_eq!(journal.sload(alice, key), U256::ZERO);
+        assert_eq!(journal.tload(&alice, &key), U256::ZERO);
+        assert!(journal.logs().is_empty());
+    }
+
+    #[test]
+    fn test_finalize() {
+        let alice = Address::with_last_byte(0xa1);
+        let empty = Address::with_last_byte(0xee);
+        let key = StorageKey::with_last_byte(1);
+        let mut journal = Journal::new();
+        journal.set_balance(alice, U256::from(1));
+        journal.sstore(alice, key, U256::from(1));
+        journal.tstore(alice, key, U256::from(1));
+        journal.touch(empty);
+        journal.log(Log::default());
+
+        let logs = journal.finalize(SpecId::Cancun);
+        assert_eq!(logs.len(), 1);
+        assert!(journal.is_empty());
+        assert!(journal.account(&empty).is_none());
+        assert_eq!(journal.original_storage(&alice, &key), U256::from(1));
+        assert_eq!(journal.tload(&alice, &key), U256::ZERO);
+    }
+}
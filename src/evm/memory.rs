@@ -35,6 +35,7 @@ pub enum MemoryError {
 /// - Memory is dynamically sized and grows as needed, up to `MEMORY_MAX_SIZE` words.
 /// - All elements are 256-bit unsigned integers ([`U256`]).
 /// - Provides byte-level and word-level access methods.
+#[derive(Debug, Clone)]
 pub struct Memory {
     /// The underlying memory storage, organized as 256-bit words.
     memory: Vec<U256>,
@@ -42,6 +43,12 @@ pub struct Memory {
     size: usize, // Current size in bytes
 }
 
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Memory {
     /// Creates a new, empty EVM memory.
     pub fn new() -> Self {
@@ -114,7 +121,7 @@ impl Memory {
         if address >= self.size {
             return Err(MemoryError::OutOfBounds);
         }
-        if address % 32 != 0 {
+        if !address.is_multiple_of(32) {
             return Err(MemoryError::InvalidAddress);
         }
         let word_index = address / 32;
@@ -138,7 +145,7 @@ impl Memory {
         if address >= MEMORY_MAX_SIZE {
             return Err(MemoryError::OutOfBounds);
         }
-        if address % 32 != 0 {
+        if !address.is_multiple_of(32) {
             return Err(MemoryError::InvalidAddress);
         }
         let word_index = address / 32;
@@ -165,7 +172,7 @@ impl Memory {
         if new_size > MEMORY_MAX_SIZE {
             return Err(MemoryError::ExpansionLimit);
         }
-        let new_word_len = (new_size + 31) / 32;
+        let new_word_len = new_size.div_ceil(32);
         if new_word_len > self.memory.len() {
             self.memory.resize(new_word_len, U256::ZERO);
         }
@@ -175,6 +182,58 @@ impl Memory {
         Ok(())
     }
 
+    /// Reads `length` bytes starting at `offset`.
+    ///
+    /// # Errors
+    /// Returns `MemoryError::OutOfBounds` if the range extends beyond the current memory size.
+    pub fn read_bytes(&self, offset: usize, length: usize) -> Result<Vec<u8>, MemoryError> {
+        if length == 0 {
+            return Ok(Vec::new());
+        }
+        let end = offset.checked_add(length).ok_or(MemoryError::OutOfBounds)?;
+        if end > self.size {
+            return Err(MemoryError::OutOfBounds);
+        }
+        (offset..end)
+            .map(|address| self.read_byte(address))
+            .collect()
+    }
+
+    /// Returns a copy of the whole memory, `size()` bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.read_bytes(0, self.size)
+            .expect("memory always holds its size in bytes")
+    }
+
+    /// Writes `data` starting at `offset`, growing memory as needed.
+    ///
+    /// # Errors
+    /// Returns `MemoryError::OutOfBounds` if the range extends beyond the maximum memory size.
+    pub fn write_bytes(&mut self, offset: usize, data: &[u8]) -> Result<(), MemoryError> {
+        for (i, byte) in data.iter().enumerate() {
+            self.write_byte(offset + i, *byte)?;
+        }
+        Ok(())
+    }
+
+    /// Loads the big-endian 256-bit word at `offset`, which need not be word-aligned (`MLOAD`).
+    ///
+    /// # Errors
+    /// Returns `MemoryError::OutOfBounds` if the word extends beyond the current memory size.
+    pub fn load_word(&self, offset: usize) -> Result<U256, MemoryError> {
+        let bytes = self.read_bytes(offset, 32)?;
+        Ok(U256::from_be_slice(&bytes))
+    }
+
+    /// Stores `value` as a big-endian 256-bit word at `offset`, which need not be
+    /// word-aligned (`MSTORE`).
+    ///
+    /// # Errors
+    /// Returns `MemoryError::OutOfBounds` if the word extends beyond the maximum memory size.
+    pub fn store_word(&mut self, offset: usize, value: U256) -> Result<(), MemoryError> {
+        self.write_bytes(offset, &value.to_be_bytes::<32>())
+    }
+
     /// Returns the current size of memory in bytes.
     pub fn size(&self) -> usize {
         self.size
@@ -187,7 +246,7 @@ impl Memory {
     /// G_memory is typically 3
     pub fn gas_cost(&self) -> u64 {
         let g_memory: u64 = 3;
-        let a = ((self.size + 31) / 32) as u64;
+        let a = self.size.div_ceil(32) as u64;
         g_memory * a + (a * a) / 512
     }
 }
@@ -232,6 +291,34 @@ mod tests {
         }
     }
 
+    /// Tests for big-endian, unaligned access as used by the interpreter.
+    mod evm_access {
+        use super::*;
+
+        /// Verifies that words are stored big-endian: the most significant byte comes first.
+        #[test]
+        fn test_store_word_is_big_endian() {
+            let mut memory = Memory::new();
+            memory.store_word(0, U256::from(0x1234)).unwrap();
+            assert_eq!(memory.read_byte(30).unwrap(), 0x12);
+            assert_eq!(memory.read_byte(31).unwrap(), 0x34);
+            assert_eq!(memory.load_word(0).unwrap(), U256::from(0x1234));
+            let bytes = memory.to_bytes();
+            assert_eq!((bytes.len(), &bytes[29..]), (32, &[0, 0x12, 0x34][..]));
+        }
+
+        /// Verifies that words can be loaded and stored at unaligned offsets.
+        #[test]
+        fn test_unaligned_word_access() {
+            let mut memory = Memory::new();
+            memory.store_word(5, U256::MAX).unwrap();
+            assert_eq!(memory.size(), 37);
+            assert_eq!(memory.read_bytes(4, 2).unwrap(), vec![0x00, 0xff]);
+            assert_eq!(memory.load_word(5).unwrap(), U256::MAX);
+            assert_eq!(memory.read_bytes(36, 2), Err(MemoryError::OutOfBounds));
+        }
+    }
+
     /// Tests for edge cases and error conditions to ensure robust error handling.
     mod edge_cases {
         use super::*;
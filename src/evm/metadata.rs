@@ -0,0 +1,368 @@
+//! Contract Metadata
+//!
+//! solc and Vyper append a CBOR-encoded trailer to deployed code: a hash of the
+//! contract's metadata file (IPFS or Swarm) and the compiler version. The trailer is
+//! never executed, and it changes whenever sources, comments or settings do, so it gets
+//! in the way of disassembling code and of comparing two builds of the same contract.
+//!
+//! # Design
+//! - The trailer is found from its length, stored big-endian in the code's last two
+//!   bytes. solc and older Vyper count only the CBOR data; Vyper 0.3.10 and later count
+//!   the two length bytes too, and both readings are tried
+//! - Only trailers that decode completely into a CBOR map (solc), or an array ending in
+//!   one (Vyper), with at least one of the keys compilers write (`ipfs`, `bzzr0`,
+//!   `bzzr1`, `solc`, `vyper`) count; this keeps code that happens to end in plausible
+//!   bytes intact
+//! - The CBOR decoder covers what compilers emit: integers, byte and text strings,
+//!   arrays, maps and simple values, with definite lengths only
+//! - solc encodes release versions as three bytes and nightly builds as text; Vyper as
+//!   an array of numbers
+//!
+//! # References
+//! - [Solidity docs: Encoding of the Metadata Hash in the Bytecode](https://docs.soliditylang.org/en/latest/metadata.html#encoding-of-the-metadata-hash-in-the-bytecode)
+//! - [RFC 8949: Concise Binary Object Representation (CBOR)](https://www.rfc-editor.org/rfc/rfc8949)
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::types::Bytes;
+
+/// Deepest nesting of CBOR arrays and maps decoded.
+const MAX_CBOR_DEPTH: usize = 8;
+
+/// Keys of which a metadata map must contain at least one.
+const KNOWN_KEYS: [&str; 5] = ["ipfs", "bzzr0", "bzzr1", "solc", "vyper"];
+
+/// Where the metadata file a hash points to is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashKind {
+    Ipfs,
+    /// Swarm, before solc 0.5.12.
+    Bzzr0,
+    /// Swarm, from solc 0.5.12.
+    Bzzr1,
+}
+
+impl HashKind {
+    /// Returns the key of the hash in the trailer.
+    pub fn name(self) -> &'static str {
+        match self {
+            HashKind::Ipfs => "ipfs",
+            HashKind::Bzzr0 => "bzzr0",
+            HashKind::Bzzr1 => "bzzr1",
+        }
+    }
+}
+
+impl fmt::Display for HashKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Hash of the contract's metadata file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MetadataHash {
+    pub kind: HashKind,
+    pub digest: Bytes,
+}
+
+/// The compiler that produced the code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Compiler {
+    /// `solc` or `vyper`.
+    pub name: String,
+    pub version: String,
+}
+
+impl fmt::Display for Compiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.name, self.version)
+    }
+}
+
+/// A decoded metadata trailer.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Metadata {
+    /// Offset of the trailer in the code; it runs to the end of the code.
+    pub offset: usize,
+    /// The trailer, its length bytes included.
+    pub trailer: Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash: Option<MetadataHash>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compiler: Option<Compiler>,
+    /// `true` if solc compiled with experimental features.
+    pub experimental: bool,
+}
+
+/// Finds and decodes the metadata trailer at the end of `code`.
+///
+/// # Examples
+/// ```
+/// use smol_evm::metadata::find_metadata;
+///
+/// // STOP, then {"solc": 0x000818} of 10 bytes
+/// let code = [0x00, 0xa1, 0x64, b's', b'o', b'l', b'c', 0x43, 0x00, 0x08, 0x18, 0x00, 0x0a];
+/// let metadata = find_metadata(&code).unwrap();
+/// assert_eq!(metadata.offset, 1);
+/// assert_eq!(metadata.compiler.unwrap().to_string(), "solc 0.8.24");
+/// ```
+pub fn find_metadata(code: &[u8]) -> Option<Metadata> {
+    let length_at = code.len().checked_sub(2)?;
+    let length = usize::from(u16::from_be_bytes([code[length_at], code[length_at + 1]]));
+    // solc and Vyper before 0.3.10, then Vyper counting the length bytes
+    [
+        length_at.checked_sub(length),
+        code.len().checked_sub(length),
+    ]
+    .into_iter()
+    .flatten()
+    .find_map(|offset| {
+        let mut decoder = Decoder {
+            data: code.get(offset..length_at)?,
+            depth: 0,
+        };
+        let value = decoder.value()?;
+        if !decoder.data.is_empty() {
+            return None;
+        }
+        let entries = match value {
+            Cbor::Map(entries) => entries,
+            Cbor::Array(mut items) => match items.pop()? {
+                Cbor::Map(entries) => entries,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let is_known =
+            |key: &Cbor| matches!(key, Cbor::Text(key) if KNOWN_KEYS.contains(&key.as_str()));
+        if !entries.iter().any(|(key, _)| is_known(key)) {
+            return None;
+        }
+        let mut metadata = Metadata {
+            offset,
+            trailer: Bytes::copy_from_slice(&code[offset..]),
+            hash: None,
+            compiler: None,
+            experimental: false,
+        };
+        for (key, value) in entries {
+            let Cbor::Text(key) = key else {
+                return None;
+            };
+            metadata.read_entry(&key, value);
+        }
+        Some(metadata)
+    })
+}
+
+/// Returns `code` without its metadata trailer, if it has one.
+pub fn strip_metadata(code: &[u8]) -> &[u8] {
+    &code[..find_metadata(code).map_or(code.len(), |metadata| metadata.offset)]
+}
+
+impl Metadata {
+    fn read_entry(&mut self, key: &str, value: Cbor) {
+        let kind = match key {
+            "ipfs" => Some(HashKind::Ipfs),
+            "bzzr0" => Some(HashKind::Bzzr0),
+            "bzzr1" => Some(HashKind::Bzzr1),
+            _ => None,
+        };
+        let version = match (key, value) {
+            (_, Cbor::Bytes(digest)) if kind.is_some() => {
+                self.hash = kind.map(|kind| MetadataHash {
+                    kind,
+                    digest: digest.into(),
+                });
+                None
+            }
+            ("solc" | "vyper", Cbor::Bytes(bytes)) => {
+                Some(join_version(bytes.into_iter().map(u64::from)))
+            }
+            ("solc" | "vyper", Cbor::Text(text)) => Some(text),
+            ("vyper", Cbor::Array(items)) => {
+                let numbers = items.into_iter().map(|item| match item {
+                    Cbor::Unsigned(number) => number,
+                    _ => 0,
+                });
+                Some(join_version(numbers))
+            }
+            ("experimental", Cbor::Bool(experimental)) => {
+                self.experimental = experimental;
+                None
+            }
+            _ => None,
+        };
+        if let Some(version) = version {
+            self.compiler = Some(Compiler {
+                name: key.to_string(),
+                version,
+            });
+        }
+    }
+}
+
+fn join_version(parts: impl Iterator<Item = u64>) -> String {
+    parts
+        .map(|part| part.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// A decoded CBOR data item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Cbor {
+    Unsigned(u64),
+    Negative(u64),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Cbor>),
+    Map(Vec<(Cbor, Cbor)>),
+    Bool(bool),
+    Null,
+}
+
+/// Reads CBOR items from the front of `data`.
+struct Decoder<'a> {
+    data: &'a [u8],
+    depth: usize,
+}
+
+impl Decoder<'_> {
+    fn take(&mut self, count: usize) -> Option<&[u8]> {
+        if count > self.data.len() {
+            return None;
+        }
+        let (taken, rest) = self.data.split_at(count);
+        self.data = rest;
+        Some(taken)
+    }
+
+    /// Reads an item's major type and its argument.
+    fn head(&mut self) -> Option<(u8, u64)> {
+        let initial = *self.take(1)?.first()?;
+        let argument = match initial & 0x1f {
+            info @ 0..=23 => u64::from(info),
+            24 => u64::from(self.take(1)?[0]),
+            25 => u64::from(u16::from_be_bytes(self.take(2)?.try_into().ok()?)),
+            26 => u64::from(u32::from_be_bytes(self.take(4)?.try_into().ok()?)),
+            27 => u64::from_be_bytes(self.take(8)?.try_into().ok()?),
+            _ => return None,
+        };
+        Some((initial >> 5, argument))
+    }
+
+    fn value(&mut self) -> Option<Cbor> {
+        let (major, argument) = self.head()?;
+        let length = usize::try_from(argument).ok();
+        Some(match major {
+            0 => Cbor::Unsigned(argument),
+            1 => Cbor::Negative(argument),
+            2 => Cbor::Bytes(self.take(length?)?.to_vec()),
+            3 => Cbor::Text(String::from_utf8(self.take(length?)?.to_vec()).ok()?),
+            4 | 5 => {
+                if self.depth == MAX_CBOR_DEPTH {
+                    return None;
+                }
+                self.depth += 1;
+                // Every item takes at least a byte, which bounds the allocation
+                let length = length?.min(self.data.len());
+                let value = if major == 4 {
+                    Cbor::Array((0..length).map(|_| self.value()).collect::<Option<_>>()?)
+                } else {
+                    let entries = (0..length)
+                        .map(|_| Some((self.value()?, self.value()?)))
+                        .collect::<Option<_>>()?;
+                    Cbor::Map(entries)
+                };
+                self.depth -= 1;
+                value
+            }
+            7 => match argument {
+                20 => Cbor::Bool(false),
+                21 => Cbor::Bool(true),
+                22 => Cbor::Null,
+                _ => return None,
+            },
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `{"ipfs": <34 bytes>, "solc": 0.8.24}` with its length, as solc appends it.
+    fn solc_trailer() -> Vec<u8> {
+        let mut cbor = vec![0xa2, 0x64, b'i', b'p', b'f', b's', 0x58, 0x22, 0x12, 0x20];
+        cbor.extend_from_slice(&[0xab; 32]);
+        cbor.extend_from_slice(&[0x64, b's', b'o', b'l', b'c', 0x43, 0x00, 0x08, 0x18]);
+        let length = cbor.len() as u16;
+        cbor.extend_from_slice(&length.to_be_bytes());
+        cbor
+    }
+
+    #[test]
+    fn test_solc_metadata() {
+        // PUSH1 0x80 STOP INVALID, then the trailer
+        let mut code = vec![0x60, 0x80, 0x00, 0xfe];
+        code.extend(solc_trailer());
+        let metadata = find_metadata(&code).unwrap();
+        assert_eq!(metadata.offset, 4);
+        assert_eq!(metadata.trailer.len(), code.len() - 4);
+        let hash = metadata.hash.as_ref().unwrap();
+        assert_eq!(hash.kind, HashKind::Ipfs);
+        assert_eq!(hash.digest.len(), 34);
+        assert_eq!(hash.digest[..2], [0x12, 0x20]);
+        assert_eq!(
+            metadata.compiler,
+            Some(Compiler {
+                name: "solc".to_string(),
+                version: "0.8.24".to_string()
+            })
+        );
+        assert!(!metadata.experimental);
+        assert_eq!(strip_metadata(&code), [0x60, 0x80, 0x00, 0xfe]);
+
+        // The same code with a different hash strips to the same bytes
+        let mut other = code.clone();
+        other[20] = 0xcd;
+        assert_ne!(other, code);
+        assert_eq!(strip_metadata(&other), strip_metadata(&code));
+
+        let json = serde_json::to_value(&metadata).unwrap();
+        assert_eq!(json["hash"]["kind"], "ipfs");
+        assert_eq!(json["compiler"]["version"], "0.8.24");
+    }
+
+    #[test]
+    fn test_vyper_and_missing_metadata() {
+        // Vyper 0.3.10: [runtime size, [], 0, {"vyper": [0, 3, 10]}], length included
+        let mut code = vec![0x00];
+        let cbor = [
+            0x84, 0x19, 0x01, 0x00, 0x80, 0x00, 0xa1, 0x65, b'v', b'y', b'p', b'e', b'r', 0x83,
+            0x00, 0x03, 0x0a,
+        ];
+        code.extend_from_slice(&cbor);
+        code.extend_from_slice(&(cbor.len() as u16 + 2).to_be_bytes());
+        let metadata = find_metadata(&code).unwrap();
+        assert_eq!(metadata.offset, 1);
+        assert_eq!(metadata.hash, None);
+        assert_eq!(metadata.compiler.unwrap().to_string(), "vyper 0.3.10");
+
+        // Plain code, a length pointing past the start, a trailer with extra bytes, an
+        // empty map and a map without a compiler's key
+        assert_eq!(find_metadata(&[0x60, 0x01, 0x60, 0x02, 0x01]), None);
+        assert_eq!(find_metadata(&[0x00, 0x00, 0xff]), None);
+        assert_eq!(find_metadata(&[0xa0, 0x00, 0x00, 0x02]), None);
+        assert_eq!(find_metadata(&[0xa0, 0x00, 0x01]), None);
+        assert_eq!(find_metadata(&[0xa1, 0x61, b'a', 0x01, 0x00, 0x04]), None);
+        assert_eq!(strip_metadata(&[0x00]), [0x00]);
+    }
+}
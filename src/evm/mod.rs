@@ -1,4 +1,28 @@
+pub mod analysis;
+pub mod assembler;
+pub mod call_tracer;
+pub mod cfg;
+pub mod coverage;
+pub mod debugger;
+pub mod disassembler;
+pub mod eip3155;
+pub mod env;
+pub mod estimate;
+pub mod execution;
+pub mod frame;
 pub mod gas;
+pub mod inspector;
+pub mod journal;
 pub mod memory;
+pub mod metadata;
 pub mod opcodes;
+pub mod prestate;
+pub mod profiler;
+pub mod schedule;
+pub mod sourcemap;
+pub mod spec;
 pub mod stack;
+pub mod stack_height;
+pub mod storage;
+#[cfg(test)]
+pub(crate) mod test_utils;
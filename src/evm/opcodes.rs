@@ -10,7 +10,12 @@
 //! The opcodes are defined in the EIPs:
 //! https://eips.ethereum.org/
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+use super::spec::SpecId;
+use serde::{Deserialize, Serialize};
+
+/// An EVM opcode. Serializes as its upper-case mnemonic, e.g. `"PUSH1"`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
 pub enum Opcode {
     // Arithmetic operations
     Stop,
@@ -68,6 +73,7 @@ pub enum Opcode {
     Coinbase,
     Timestamp,
     Number,
+    #[serde(alias = "PREVRANDAO")]
     Difficulty,
     Gaslimit,
     Chainid,
@@ -183,3 +189,636 @@ pub enum Opcode {
     Invalid,
     Selfdestruct,
 }
+
+impl std::fmt::Display for Opcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl Opcode {
+    /// Decodes a byte into an opcode, returning `None` for bytes that are not assigned.
+    pub fn from_byte(byte: u8) -> Option<Opcode> {
+        let opcode = match byte {
+            0x00 => Opcode::Stop,
+            0x01 => Opcode::Add,
+            0x02 => Opcode::Mul,
+            0x03 => Opcode::Sub,
+            0x04 => Opcode::Div,
+            0x05 => Opcode::Sdiv,
+            0x06 => Opcode::Mod,
+            0x07 => Opcode::Smod,
+            0x08 => Opcode::Addmod,
+            0x09 => Opcode::Mulmod,
+            0x0a => Opcode::Exp,
+            0x0b => Opcode::Signextend,
+            0x10 => Opcode::Lt,
+            0x11 => Opcode::Gt,
+            0x12 => Opcode::Slt,
+            0x13 => Opcode::Sgt,
+            0x14 => Opcode::Eq,
+            0x15 => Opcode::Iszero,
+            0x16 => Opcode::And,
+            0x17 => Opcode::Or,
+            0x18 => Opcode::Xor,
+            0x19 => Opcode::Not,
+            0x1a => Opcode::Byte,
+            0x1b => Opcode::Shl,
+            0x1c => Opcode::Shr,
+            0x1d => Opcode::Sar,
+            0x20 => Opcode::Keccak256,
+            0x30 => Opcode::Address,
+            0x31 => Opcode::Balance,
+            0x32 => Opcode::Origin,
+            0x33 => Opcode::Caller,
+            0x34 => Opcode::Callvalue,
+            0x35 => Opcode::Calldataload,
+            0x36 => Opcode::Calldatasize,
+            0x37 => Opcode::Calldatacopy,
+            0x38 => Opcode::Codesize,
+            0x39 => Opcode::Codecopy,
+            0x3a => Opcode::Gasprice,
+            0x3b => Opcode::Extcodesize,
+            0x3c => Opcode::Extcodecopy,
+            0x3d => Opcode::Returndatasize,
+            0x3e => Opcode::Returndatacopy,
+            0x3f => Opcode::Extcodehash,
+            0x40 => Opcode::Blockhash,
+            0x41 => Opcode::Coinbase,
+            0x42 => Opcode::Timestamp,
+            0x43 => Opcode::Number,
+            0x44 => Opcode::Difficulty,
+            0x45 => Opcode::Gaslimit,
+            0x46 => Opcode::Chainid,
+            0x47 => Opcode::Selfbalance,
+            0x48 => Opcode::Basefee,
+            0x49 => Opcode::Blobhash,
+            0x4a => Opcode::Blobbasefee,
+            0x50 => Opcode::Pop,
+            0x51 => Opcode::Mload,
+            0x52 => Opcode::Mstore,
+            0x53 => Opcode::Mstore8,
+            0x54 => Opcode::Sload,
+            0x55 => Opcode::Sstore,
+            0x56 => Opcode::Jump,
+            0x57 => Opcode::Jumpi,
+            0x58 => Opcode::Pc,
+            0x59 => Opcode::Msize,
+            0x5a => Opcode::Gas,
+            0x5b => Opcode::Jumpdest,
+            0x5c => Opcode::Tload,
+            0x5d => Opcode::Tstore,
+            0x5e => Opcode::Mcopy,
+            0x5f => Opcode::Push0,
+            0x60 => Opcode::Push1,
+            0x61 => Opcode::Push2,
+            0x62 => Opcode::Push3,
+            0x63 => Opcode::Push4,
+            0x64 => Opcode::Push5,
+            0x65 => Opcode::Push6,
+            0x66 => Opcode::Push7,
+            0x67 => Opcode::Push8,
+            0x68 => Opcode::Push9,
+            0x69 => Opcode::Push10,
+            0x6a => Opcode::Push11,
+            0x6b => Opcode::Push12,
+            0x6c => Opcode::Push13,
+            0x6d => Opcode::Push14,
+            0x6e => Opcode::Push15,
+            0x6f => Opcode::Push16,
+            0x70 => Opcode::Push17,
+            0x71 => Opcode::Push18,
+            0x72 => Opcode::Push19,
+            0x73 => Opcode::Push20,
+            0x74 => Opcode::Push21,
+            0x75 => Opcode::Push22,
+            0x76 => Opcode::Push23,
+            0x77 => Opcode::Push24,
+            0x78 => Opcode::Push25,
+            0x79 => Opcode::Push26,
+            0x7a => Opcode::Push27,
+            0x7b => Opcode::Push28,
+            0x7c => Opcode::Push29,
+            0x7d => Opcode::Push30,
+            0x7e => Opcode::Push31,
+            0x7f => Opcode::Push32,
+            0x80 => Opcode::Dup1,
+            0x81 => Opcode::Dup2,
+            0x82 => Opcode::Dup3,
+            0x83 => Opcode::Dup4,
+            0x84 => Opcode::Dup5,
+            0x85 => Opcode::Dup6,
+            0x86 => Opcode::Dup7,
+            0x87 => Opcode::Dup8,
+            0x88 => Opcode::Dup9,
+            0x89 => Opcode::Dup10,
+            0x8a => Opcode::Dup11,
+            0x8b => Opcode::Dup12,
+            0x8c => Opcode::Dup13,
+            0x8d => Opcode::Dup14,
+            0x8e => Opcode::Dup15,
+            0x8f => Opcode::Dup16,
+            0x90 => Opcode::Swap1,
+            0x91 => Opcode::Swap2,
+            0x92 => Opcode::Swap3,
+            0x93 => Opcode::Swap4,
+            0x94 => Opcode::Swap5,
+            0x95 => Opcode::Swap6,
+            0x96 => Opcode::Swap7,
+            0x97 => Opcode::Swap8,
+            0x98 => Opcode::Swap9,
+            0x99 => Opcode::Swap10,
+            0x9a => Opcode::Swap11,
+            0x9b => Opcode::Swap12,
+            0x9c => Opcode::Swap13,
+            0x9d => Opcode::Swap14,
+            0x9e => Opcode::Swap15,
+            0x9f => Opcode::Swap16,
+            0xa0 => Opcode::Log0,
+            0xa1 => Opcode::Log1,
+            0xa2 => Opcode::Log2,
+            0xa3 => Opcode::Log3,
+            0xa4 => Opcode::Log4,
+            0xf0 => Opcode::Create,
+            0xf1 => Opcode::Call,
+            0xf2 => Opcode::Callcode,
+            0xf3 => Opcode::Return,
+            0xf4 => Opcode::Delegatecall,
+            0xf5 => Opcode::Create2,
+            0xfa => Opcode::Staticcall,
+            0xfd => Opcode::Revert,
+            0xfe => Opcode::Invalid,
+            0xff => Opcode::Selfdestruct,
+            _ => return None,
+        };
+        Some(opcode)
+    }
+
+    /// Returns the byte encoding of the opcode.
+    pub fn to_byte(self) -> u8 {
+        match self {
+            Opcode::Stop => 0x00,
+            Opcode::Add => 0x01,
+            Opcode::Mul => 0x02,
+            Opcode::Sub => 0x03,
+            Opcode::Div => 0x04,
+            Opcode::Sdiv => 0x05,
+            Opcode::Mod => 0x06,
+            Opcode::Smod => 0x07,
+            Opcode::Addmod => 0x08,
+            Opcode::Mulmod => 0x09,
+            Opcode::Exp => 0x0a,
+            Opcode::Signextend => 0x0b,
+            Opcode::Lt => 0x10,
+            Opcode::Gt => 0x11,
+            Opcode::Slt => 0x12,
+            Opcode::Sgt => 0x13,
+            Opcode::Eq => 0x14,
+            Opcode::Iszero => 0x15,
+            Opcode::And => 0x16,
+            Opcode::Or => 0x17,
+            Opcode::Xor => 0x18,
+            Opcode::Not => 0x19,
+            Opcode::Byte => 0x1a,
+            Opcode::Shl => 0x1b,
+            Opcode::Shr => 0x1c,
+            Opcode::Sar => 0x1d,
+            Opcode::Keccak256 => 0x20,
+            Opcode::Address => 0x30,
+            Opcode::Balance => 0x31,
+            Opcode::Origin => 0x32,
+            Opcode::Caller => 0x33,
+            Opcode::Callvalue => 0x34,
+            Opcode::Calldataload => 0x35,
+            Opcode::Calldatasize => 0x36,
+            Opcode::Calldatacopy => 0x37,
+            Opcode::Codesize => 0x38,
+            Opcode::Codecopy => 0x39,
+            Opcode::Gasprice => 0x3a,
+            Opcode::Extcodecopy => 0x3c,
+            Opcode::Extcodesize => 0x3b,
+            Opcode::Extcodehash => 0x3f,
+            Opcode::Returndatasize => 0x3d,
+            Opcode::Returndatacopy => 0x3e,
+            Opcode::Blockhash => 0x40,
+            Opcode::Coinbase => 0x41,
+            Opcode::Timestamp => 0x42,
+            Opcode::Number => 0x43,
+            Opcode::Difficulty => 0x44,
+            Opcode::Gaslimit => 0x45,
+            Opcode::Chainid => 0x46,
+            Opcode::Selfbalance => 0x47,
+            Opcode::Basefee => 0x48,
+            Opcode::Blobhash => 0x49,
+            Opcode::Blobbasefee => 0x4a,
+            Opcode::Pop => 0x50,
+            Opcode::Mload => 0x51,
+            Opcode::Mstore => 0x52,
+            Opcode::Mstore8 => 0x53,
+            Opcode::Sload => 0x54,
+            Opcode::Sstore => 0x55,
+            Opcode::Jump => 0x56,
+            Opcode::Jumpi => 0x57,
+            Opcode::Pc => 0x58,
+            Opcode::Msize => 0x59,
+            Opcode::Gas => 0x5a,
+            Opcode::Jumpdest => 0x5b,
+            Opcode::Tload => 0x5c,
+            Opcode::Tstore => 0x5d,
+            Opcode::Mcopy => 0x5e,
+            Opcode::Push0 => 0x5f,
+            Opcode::Push1 => 0x60,
+            Opcode::Push2 => 0x61,
+            Opcode::Push3 => 0x62,
+            Opcode::Push4 => 0x63,
+            Opcode::Push5 => 0x64,
+            Opcode::Push6 => 0x65,
+            Opcode::Push7 => 0x66,
+            Opcode::Push8 => 0x67,
+            Opcode::Push9 => 0x68,
+            Opcode::Push10 => 0x69,
+            Opcode::Push11 => 0x6a,
+            Opcode::Push12 => 0x6b,
+            Opcode::Push13 => 0x6c,
+            Opcode::Push14 => 0x6d,
+            Opcode::Push15 => 0x6e,
+            Opcode::Push16 => 0x6f,
+            Opcode::Push17 => 0x70,
+            Opcode::Push18 => 0x71,
+            Opcode::Push19 => 0x72,
+            Opcode::Push20 => 0x73,
+            Opcode::Push21 => 0x74,
+            Opcode::Push22 => 0x75,
+            Opcode::Push23 => 0x76,
+            Opcode::Push24 => 0x77,
+            Opcode::Push25 => 0x78,
+            Opcode::Push26 => 0x79,
+            Opcode::Push27 => 0x7a,
+            Opcode::Push28 => 0x7b,
+            Opcode::Push29 => 0x7c,
+            Opcode::Push30 => 0x7d,
+            Opcode::Push31 => 0x7e,
+            Opcode::Push32 => 0x7f,
+            Opcode::Dup1 => 0x80,
+            Opcode::Dup2 => 0x81,
+            Opcode::Dup3 => 0x82,
+            Opcode::Dup4 => 0x83,
+            Opcode::Dup5 => 0x84,
+            Opcode::Dup6 => 0x85,
+            Opcode::Dup7 => 0x86,
+            Opcode::Dup8 => 0x87,
+            Opcode::Dup9 => 0x88,
+            Opcode::Dup10 => 0x89,
+            Opcode::Dup11 => 0x8a,
+            Opcode::Dup12 => 0x8b,
+            Opcode::Dup13 => 0x8c,
+            Opcode::Dup14 => 0x8d,
+            Opcode::Dup15 => 0x8e,
+            Opcode::Dup16 => 0x8f,
+            Opcode::Swap1 => 0x90,
+            Opcode::Swap2 => 0x91,
+            Opcode::Swap3 => 0x92,
+            Opcode::Swap4 => 0x93,
+            Opcode::Swap5 => 0x94,
+            Opcode::Swap6 => 0x95,
+            Opcode::Swap7 => 0x96,
+            Opcode::Swap8 => 0x97,
+            Opcode::Swap9 => 0x98,
+            Opcode::Swap10 => 0x99,
+            Opcode::Swap11 => 0x9a,
+            Opcode::Swap12 => 0x9b,
+            Opcode::Swap13 => 0x9c,
+            Opcode::Swap14 => 0x9d,
+            Opcode::Swap15 => 0x9e,
+            Opcode::Swap16 => 0x9f,
+            Opcode::Log0 => 0xa0,
+            Opcode::Log1 => 0xa1,
+            Opcode::Log2 => 0xa2,
+            Opcode::Log3 => 0xa3,
+            Opcode::Log4 => 0xa4,
+            Opcode::Create => 0xf0,
+            Opcode::Call => 0xf1,
+            Opcode::Callcode => 0xf2,
+            Opcode::Return => 0xf3,
+            Opcode::Delegatecall => 0xf4,
+            Opcode::Create2 => 0xf5,
+            Opcode::Staticcall => 0xfa,
+            Opcode::Revert => 0xfd,
+            Opcode::Invalid => 0xfe,
+            Opcode::Selfdestruct => 0xff,
+        }
+    }
+
+    /// Looks up an opcode by its mnemonic, ignoring case, e.g. `"push1"`.
+    pub fn from_name(name: &str) -> Option<Opcode> {
+        (0..=u8::MAX)
+            .filter_map(Opcode::from_byte)
+            .find(|opcode| opcode.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the upper-case mnemonic of the opcode, e.g. `"PUSH1"`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Opcode::Stop => "STOP",
+            Opcode::Add => "ADD",
+            Opcode::Mul => "MUL",
+            Opcode::Sub => "SUB",
+            Opcode::Div => "DIV",
+            Opcode::Sdiv => "SDIV",
+            Opcode::Mod => "MOD",
+            Opcode::Smod => "SMOD",
+            Opcode::Addmod => "ADDMOD",
+            Opcode::Mulmod => "MULMOD",
+            Opcode::Exp => "EXP",
+            Opcode::Signextend => "SIGNEXTEND",
+            Opcode::Lt => "LT",
+            Opcode::Gt => "GT",
+            Opcode::Slt => "SLT",
+            Opcode::Sgt => "SGT",
+            Opcode::Eq => "EQ",
+            Opcode::Iszero => "ISZERO",
+            Opcode::And => "AND",
+            Opcode::Or => "OR",
+            Opcode::Xor => "XOR",
+            Opcode::Not => "NOT",
+            Opcode::Byte => "BYTE",
+            Opcode::Shl => "SHL",
+            Opcode::Shr => "SHR",
+            Opcode::Sar => "SAR",
+            Opcode::Keccak256 => "KECCAK256",
+            Opcode::Address => "ADDRESS",
+            Opcode::Balance => "BALANCE",
+            Opcode::Origin => "ORIGIN",
+            Opcode::Caller => "CALLER",
+            Opcode::Callvalue => "CALLVALUE",
+            Opcode::Calldataload => "CALLDATALOAD",
+            Opcode::Calldatasize => "CALLDATASIZE",
+            Opcode::Calldatacopy => "CALLDATACOPY",
+            Opcode::Codesize => "CODESIZE",
+            Opcode::Codecopy => "CODECOPY",
+            Opcode::Gasprice => "GASPRICE",
+            Opcode::Extcodecopy => "EXTCODECOPY",
+            Opcode::Extcodesize => "EXTCODESIZE",
+            Opcode::Extcodehash => "EXTCODEHASH",
+            Opcode::Returndatasize => "RETURNDATASIZE",
+            Opcode::Returndatacopy => "RETURNDATACOPY",
+            Opcode::Blockhash => "BLOCKHASH",
+            Opcode::Coinbase => "COINBASE",
+            Opcode::Timestamp => "TIMESTAMP",
+            Opcode::Number => "NUMBER",
+            Opcode::Difficulty => "DIFFICULTY",
+            Opcode::Gaslimit => "GASLIMIT",
+            Opcode::Chainid => "CHAINID",
+            Opcode::Selfbalance => "SELFBALANCE",
+            Opcode::Basefee => "BASEFEE",
+            Opcode::Blobhash => "BLOBHASH",
+            Opcode::Blobbasefee => "BLOBBASEFEE",
+            Opcode::Pop => "POP",
+            Opcode::Mload => "MLOAD",
+            Opcode::Mstore => "MSTORE",
+            Opcode::Mstore8 => "MSTORE8",
+            Opcode::Sload => "SLOAD",
+            Opcode::Sstore => "SSTORE",
+            Opcode::Jump => "JUMP",
+            Opcode::Jumpi => "JUMPI",
+            Opcode::Pc => "PC",
+            Opcode::Msize => "MSIZE",
+            Opcode::Gas => "GAS",
+            Opcode::Jumpdest => "JUMPDEST",
+            Opcode::Tload => "TLOAD",
+            Opcode::Tstore => "TSTORE",
+            Opcode::Mcopy => "MCOPY",
+            Opcode::Push0 => "PUSH0",
+            Opcode::Push1 => "PUSH1",
+            Opcode::Push2 => "PUSH2",
+            Opcode::Push3 => "PUSH3",
+            Opcode::Push4 => "PUSH4",
+            Opcode::Push5 => "PUSH5",
+            Opcode::Push6 => "PUSH6",
+            Opcode::Push7 => "PUSH7",
+            Opcode::Push8 => "PUSH8",
+            Opcode::Push9 => "PUSH9",
+            Opcode::Push10 => "PUSH10",
+            Opcode::Push11 => "PUSH11",
+            Opcode::Push12 => "PUSH12",
+            Opcode::Push13 => "PUSH13",
+            Opcode::Push14 => "PUSH14",
+            Opcode::Push15 => "PUSH15",
+            Opcode::Push16 => "PUSH16",
+            Opcode::Push17 => "PUSH17",
+            Opcode::Push18 => "PUSH18",
+            Opcode::Push19 => "PUSH19",
+            Opcode::Push20 => "PUSH20",
+            Opcode::Push21 => "PUSH21",
+            Opcode::Push22 => "PUSH22",
+            Opcode::Push23 => "PUSH23",
+            Opcode::Push24 => "PUSH24",
+            Opcode::Push25 => "PUSH25",
+            Opcode::Push26 => "PUSH26",
+            Opcode::Push27 => "PUSH27",
+            Opcode::Push28 => "PUSH28",
+            Opcode::Push29 => "PUSH29",
+            Opcode::Push30 => "PUSH30",
+            Opcode::Push31 => "PUSH31",
+            Opcode::Push32 => "PUSH32",
+            Opcode::Dup1 => "DUP1",
+            Opcode::Dup2 => "DUP2",
+            Opcode::Dup3 => "DUP3",
+            Opcode::Dup4 => "DUP4",
+            Opcode::Dup5 => "DUP5",
+            Opcode::Dup6 => "DUP6",
+            Opcode::Dup7 => "DUP7",
+            Opcode::Dup8 => "DUP8",
+            Opcode::Dup9 => "DUP9",
+            Opcode::Dup10 => "DUP10",
+            Opcode::Dup11 => "DUP11",
+            Opcode::Dup12 => "DUP12",
+            Opcode::Dup13 => "DUP13",
+            Opcode::Dup14 => "DUP14",
+            Opcode::Dup15 => "DUP15",
+            Opcode::Dup16 => "DUP16",
+            Opcode::Swap1 => "SWAP1",
+            Opcode::Swap2 => "SWAP2",
+            Opcode::Swap3 => "SWAP3",
+            Opcode::Swap4 => "SWAP4",
+            Opcode::Swap5 => "SWAP5",
+            Opcode::Swap6 => "SWAP6",
+            Opcode::Swap7 => "SWAP7",
+            Opcode::Swap8 => "SWAP8",
+            Opcode::Swap9 => "SWAP9",
+            Opcode::Swap10 => "SWAP10",
+            Opcode::Swap11 => "SWAP11",
+            Opcode::Swap12 => "SWAP12",
+            Opcode::Swap13 => "SWAP13",
+            Opcode::Swap14 => "SWAP14",
+            Opcode::Swap15 => "SWAP15",
+            Opcode::Swap16 => "SWAP16",
+            Opcode::Log0 => "LOG0",
+            Opcode::Log1 => "LOG1",
+            Opcode::Log2 => "LOG2",
+            Opcode::Log3 => "LOG3",
+            Opcode::Log4 => "LOG4",
+            Opcode::Create => "CREATE",
+            Opcode::Call => "CALL",
+            Opcode::Callcode => "CALLCODE",
+            Opcode::Return => "RETURN",
+            Opcode::Delegatecall => "DELEGATECALL",
+            Opcode::Create2 => "CREATE2",
+            Opcode::Staticcall => "STATICCALL",
+            Opcode::Revert => "REVERT",
+            Opcode::Invalid => "INVALID",
+            Opcode::Selfdestruct => "SELFDESTRUCT",
+        }
+    }
+
+    /// Returns the number of immediate bytes following the opcode (non-zero only for `PUSH1`..`PUSH32`).
+    pub fn immediate_size(self) -> usize {
+        let byte = self.to_byte();
+        if (0x60..=0x7f).contains(&byte) {
+            (byte - 0x5f) as usize
+        } else {
+            0
+        }
+    }
+
+    /// Returns the number of stack items the opcode pops and the number it pushes.
+    /// `DUPn` and `SWAPn` count the items they reach as popped and pushed back, so the
+    /// first number is always the stack height the opcode needs.
+    pub fn stack_io(self) -> (usize, usize) {
+        let byte = self.to_byte();
+        match byte {
+            0x5f..=0x7f => return (0, 1),
+            0x80..=0x8f => {
+                let n = (byte - 0x7f) as usize;
+                return (n, n + 1);
+            }
+            0x90..=0x9f => {
+                let n = (byte - 0x8f) as usize;
+                return (n + 1, n + 1);
+            }
+            0xa0..=0xa4 => return ((byte - 0xa0) as usize + 2, 0),
+            _ => {}
+        }
+        match self {
+            Opcode::Stop | Opcode::Jumpdest | Opcode::Invalid => (0, 0),
+            Opcode::Address
+            | Opcode::Origin
+            | Opcode::Caller
+            | Opcode::Callvalue
+            | Opcode::Calldatasize
+            | Opcode::Codesize
+            | Opcode::Gasprice
+            | Opcode::Returndatasize
+            | Opcode::Coinbase
+            | Opcode::Timestamp
+            | Opcode::Number
+            | Opcode::Difficulty
+            | Opcode::Gaslimit
+            | Opcode::Chainid
+            | Opcode::Selfbalance
+            | Opcode::Basefee
+            | Opcode::Blobbasefee
+            | Opcode::Pc
+            | Opcode::Msize
+            | Opcode::Gas => (0, 1),
+            Opcode::Iszero
+            | Opcode::Not
+            | Opcode::Balance
+            | Opcode::Calldataload
+            | Opcode::Extcodesize
+            | Opcode::Extcodehash
+            | Opcode::Blockhash
+            | Opcode::Blobhash
+            | Opcode::Mload
+            | Opcode::Sload
+            | Opcode::Tload => (1, 1),
+            Opcode::Pop | Opcode::Jump | Opcode::Selfdestruct => (1, 0),
+            Opcode::Mstore
+            | Opcode::Mstore8
+            | Opcode::Sstore
+            | Opcode::Tstore
+            | Opcode::Jumpi
+            | Opcode::Return
+            | Opcode::Revert => (2, 0),
+            Opcode::Calldatacopy | Opcode::Codecopy | Opcode::Returndatacopy | Opcode::Mcopy => {
+                (3, 0)
+            }
+            Opcode::Extcodecopy => (4, 0),
+            Opcode::Addmod | Opcode::Mulmod | Opcode::Create => (3, 1),
+            Opcode::Create2 => (4, 1),
+            Opcode::Delegatecall | Opcode::Staticcall => (6, 1),
+            Opcode::Call | Opcode::Callcode => (7, 1),
+            // Binary arithmetic, comparison, bitwise and hashing operations
+            _ => (2, 1),
+        }
+    }
+
+    /// Returns the hardfork that introduced this opcode.
+    pub fn introduced_in(self) -> SpecId {
+        match self {
+            Opcode::Delegatecall => SpecId::Homestead,
+            Opcode::Returndatasize
+            | Opcode::Returndatacopy
+            | Opcode::Staticcall
+            | Opcode::Revert => SpecId::Byzantium,
+            Opcode::Shl | Opcode::Shr | Opcode::Sar | Opcode::Extcodehash | Opcode::Create2 => {
+                SpecId::Constantinople
+            }
+            Opcode::Chainid | Opcode::Selfbalance => SpecId::Istanbul,
+            Opcode::Basefee => SpecId::London,
+            Opcode::Push0 => SpecId::Shanghai,
+            Opcode::Tload
+            | Opcode::Tstore
+            | Opcode::Mcopy
+            | Opcode::Blobhash
+            | Opcode::Blobbasefee => SpecId::Cancun,
+            _ => SpecId::Frontier,
+        }
+    }
+
+    /// Returns `true` if this opcode is available under the given spec.
+    pub fn is_enabled_in(self, spec: SpecId) -> bool {
+        spec.is_enabled_in(self.introduced_in())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_round_trip() {
+        for byte in 0..=u8::MAX {
+            if let Some(opcode) = Opcode::from_byte(byte) {
+                assert_eq!(opcode.to_byte(), byte);
+            }
+        }
+        assert_eq!(Opcode::from_byte(0x60), Some(Opcode::Push1));
+        assert_eq!(Opcode::from_byte(0xfa), Some(Opcode::Staticcall));
+        assert_eq!(Opcode::from_byte(0x0c), None);
+        assert_eq!(Opcode::from_name("push1"), Some(Opcode::Push1));
+        assert_eq!(
+            Opcode::from_name("SELFDESTRUCT"),
+            Some(Opcode::Selfdestruct)
+        );
+        assert_eq!(Opcode::from_name("PUSH"), None);
+        assert_eq!(Opcode::Push32.immediate_size(), 32);
+        assert_eq!(Opcode::Push0.immediate_size(), 0);
+        assert_eq!(Opcode::Keccak256.to_string(), "KECCAK256");
+        assert_eq!(Opcode::Push32.stack_io(), (0, 1));
+        assert_eq!(Opcode::Dup16.stack_io(), (16, 17));
+        assert_eq!(Opcode::Swap1.stack_io(), (2, 2));
+        assert_eq!(Opcode::Log4.stack_io(), (6, 0));
+        assert_eq!(Opcode::Sar.stack_io(), (2, 1));
+        assert_eq!(Opcode::Call.stack_io(), (7, 1));
+    }
+
+    #[test]
+    fn test_opcode_availability() {
+        assert!(Opcode::Add.is_enabled_in(SpecId::Frontier));
+        assert!(!Opcode::Push0.is_enabled_in(SpecId::London));
+        assert!(Opcode::Push0.is_enabled_in(SpecId::Shanghai));
+        assert!(!Opcode::Shl.is_enabled_in(SpecId::Byzantium));
+        assert!(Opcode::Tstore.is_enabled_in(SpecId::Cancun));
+    }
+}
@@ -0,0 +1,335 @@
+//! Prestate Tracer
+//!
+//! Records the state a transaction depends on: every account and storage slot it
+//! touches, as they were before it ran. In diff mode it instead reports the accounts
+//! the transaction changed, before and after. Both follow the JSON layout of geth's
+//! `prestateTracer`.
+//!
+//! # Design
+//! - An [`Inspector`] that only collects *which* accounts and slots are touched: the
+//!   sender, recipient and coinbase, message callers and callees, created contracts,
+//!   and the operands of `BALANCE`, `EXTCODE*`, `SELFDESTRUCT`, `SLOAD` and `SSTORE`.
+//!   Touches inside frames that later revert count too
+//! - Values come from a copy of the state taken before the transaction and from the
+//!   state after it, so they need no reconstruction from the journal
+//! - As in geth, accounts created by the transaction are left out of the prestate;
+//!   in diff mode, unchanged accounts and slots are left out, `post` only lists fields
+//!   that changed, and slots cleared to zero or destroyed accounts do not appear in it
+//!
+//! # References
+//! - [geth built-in tracers](https://geth.ethereum.org/docs/developers/evm-tracing/built-in-tracers)
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+
+use super::env::TxEnv;
+use super::execution::{Evm, ExecutionResult, TransactionError};
+use super::frame::Frame;
+use super::inspector::{CallInputs, CallOutcome, CreateInputs, CreateOutcome, Inspector};
+use super::opcodes::Opcode;
+use super::storage::{Account, WorldState};
+use crate::types::{Address, Bytes, StorageKey, B256, U256};
+
+/// An account as the tracer reports it. Fields are `None`, or storage empty, when
+/// they are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AccountState {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<StorageKey, B256>,
+}
+
+/// Accounts by address.
+pub type StateMap = BTreeMap<Address, AccountState>;
+
+/// Changed accounts before and after the transaction.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StateDiff {
+    pub pre: StateMap,
+    pub post: StateMap,
+}
+
+/// Collects the prestate, or the state diff, of a transaction.
+#[derive(Debug, Clone, Default)]
+pub struct PrestateTracer {
+    diff_mode: bool,
+    /// Touched accounts and their touched slots.
+    touched: BTreeMap<Address, BTreeSet<StorageKey>>,
+    /// Contracts the transaction created.
+    created: BTreeSet<Address>,
+    prestate: StateMap,
+    diff: Option<StateDiff>,
+}
+
+impl PrestateTracer {
+    /// Creates a tracer reporting the prestate.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reports the state diff instead of the prestate.
+    pub fn with_diff_mode(mut self) -> Self {
+        self.diff_mode = true;
+        self
+    }
+
+    /// Executes a transaction to completion, recording the state it touches.
+    ///
+    /// # Errors
+    /// Returns a [`TransactionError`] if the transaction is invalid.
+    pub fn transact(
+        &mut self,
+        evm: &mut Evm,
+        tx: TxEnv,
+    ) -> Result<ExecutionResult, TransactionError> {
+        self.touched.clear();
+        self.created.clear();
+        let before = evm.state().clone();
+        self.touch(tx.caller);
+        if let Some(to) = tx.to {
+            self.touch(to);
+        }
+        self.touch(evm.block().coinbase);
+        let result = evm.inspect_transact(tx, self)?;
+        let after = evm.state();
+
+        self.prestate = self
+            .touched
+            .iter()
+            .filter(|&(address, _)| before.contains(address) || !self.created.contains(address))
+            .map(|(address, slots)| (*address, full_state(&before, address, slots)))
+            .collect();
+        self.diff = self.diff_mode.then(|| self.state_diff(&before, after));
+        Ok(result)
+    }
+
+    /// Returns the touched accounts as they were before the transaction.
+    pub fn prestate(&self) -> &StateMap {
+        &self.prestate
+    }
+
+    /// Returns the changed accounts, in diff mode.
+    pub fn diff(&self) -> Option<&StateDiff> {
+        self.diff.as_ref()
+    }
+
+    /// Serializes the diff in diff mode, otherwise the prestate, as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        match &self.diff {
+            Some(diff) => serde_json::to_string_pretty(diff),
+            None => serde_json::to_string_pretty(&self.prestate),
+        }
+        .expect("account states always serialize")
+    }
+
+    fn touch(&mut self, address: Address) {
+        self.touched.entry(address).or_default();
+    }
+
+    fn state_diff(&self, before: &WorldState, after: &WorldState) -> StateDiff {
+        let mut diff = StateDiff::default();
+        let empty = Account::default();
+        for (address, slots) in &self.touched {
+            let old = before.account(address).unwrap_or(&empty);
+            let new = after.account(address).unwrap_or(&empty);
+            let changed_slots: Vec<_> = slots
+                .iter()
+                .filter(|key| old.storage_value(key) != new.storage_value(key))
+                .collect();
+            let unchanged = old.balance == new.balance
+                && old.nonce == new.nonce
+                && old.code == new.code
+                && changed_slots.is_empty()
+                && before.contains(address) == after.contains(address);
+            if unchanged {
+                continue;
+            }
+
+            if before.contains(address) {
+                let mut pre = full_state(before, address, &BTreeSet::new());
+                pre.storage = changed_slots
+                    .iter()
+                    .map(|key| (**key, B256::from(old.storage_value(key))))
+                    .collect();
+                diff.pre.insert(*address, pre);
+            }
+            if after.contains(address) {
+                let post = AccountState {
+                    balance: (old.balance != new.balance).then_some(new.balance),
+                    nonce: (old.nonce != new.nonce).then_some(new.nonce),
+                    code: (old.code != new.code).then(|| new.code.clone()),
+                    storage: changed_slots
+                        .iter()
+                        .map(|key| (**key, new.storage_value(key)))
+                        .filter(|(_, value)| !value.is_zero())
+                        .map(|(key, value)| (key, B256::from(value)))
+                        .collect(),
+                };
+                diff.post.insert(*address, post);
+            }
+        }
+        diff
+    }
+}
+
+/// Reports an account with its balance, nonce and code, and the given slots.
+fn full_state(state: &WorldState, address: &Address, slots: &BTreeSet<StorageKey>) -> AccountState {
+    let empty = Account::default();
+    let account = state.account(address).unwrap_or(&empty);
+    AccountState {
+        balance: Some(account.balance),
+        nonce: (account.nonce != 0).then_some(account.nonce),
+        code: (!account.code.is_empty()).then(|| account.code.clone()),
+        storage: slots
+            .iter()
+            .map(|key| (*key, B256::from(account.storage_value(key))))
+            .collect(),
+    }
+}
+
+impl Inspector for PrestateTracer {
+    fn step(&mut self, _evm: &Evm, frame: &Frame, opcode: Option<Opcode>) {
+        let Some(top) = frame.stack().peek() else {
+            return;
+        };
+        match opcode {
+            Some(Opcode::Sload | Opcode::Sstore) => {
+                self.touched
+                    .entry(frame.context().address)
+                    .or_default()
+                    .insert(B256::from(*top));
+            }
+            Some(
+                Opcode::Balance
+                | Opcode::Extcodesize
+                | Opcode::Extcodecopy
+                | Opcode::Extcodehash
+                | Opcode::Selfdestruct,
+            ) => self.touch(Address::from_word(B256::from(*top))),
+            _ => {}
+        }
+    }
+
+    fn call(&mut self, _evm: &Evm, inputs: &CallInputs) -> Option<CallOutcome> {
+        self.touch(inputs.context.caller);
+        self.touch(inputs.context.address);
+        self.touch(inputs.context.code_address);
+        None
+    }
+
+    fn create(&mut self, _evm: &Evm, inputs: &CreateInputs) -> Option<CreateOutcome> {
+        self.touch(inputs.caller);
+        None
+    }
+
+    fn create_end(&mut self, _evm: &Evm, _inputs: &CreateInputs, outcome: &mut CreateOutcome) {
+        if let Some(address) = outcome.address {
+            self.touch(address);
+            self.created.insert(address);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::assembler::assemble;
+    use crate::evm::test_utils::{self, CALLER, CONTRACT};
+
+    const OTHER: Address = Address::with_last_byte(0xc1);
+
+    fn trace(mut tracer: PrestateTracer) -> PrestateTracer {
+        // Read the balance of OTHER, overwrite slot 1, clear slot 2, read slot 3, and
+        // create an empty contract
+        let code = assemble(
+            "PUSH1 0xc1 BALANCE POP
+             PUSH1 9 PUSH1 1 SSTORE
+             PUSH0 PUSH1 2 SSTORE
+             PUSH1 3 SLOAD POP
+             PUSH0 PUSH0 PUSH0 CREATE POP",
+        )
+        .unwrap();
+        let mut contract = Account::with_code(code.into());
+        contract
+            .storage
+            .insert(B256::with_last_byte(1), U256::from(5));
+        contract
+            .storage
+            .insert(B256::with_last_byte(2), U256::from(6));
+        contract
+            .storage
+            .insert(B256::with_last_byte(3), U256::from(7));
+        let mut state = test_utils::state(contract);
+        state.insert_account(OTHER, Account::with_balance(U256::from(1)));
+        let mut evm = Evm::new().with_state(state);
+        let tx = TxEnv {
+            gas_price: U256::from(1),
+            ..test_utils::call()
+        };
+        tracer.transact(&mut evm, tx).unwrap();
+        tracer
+    }
+
+    #[test]
+    fn test_prestate() {
+        let tracer = trace(PrestateTracer::new());
+        let prestate = tracer.prestate();
+        let created = CONTRACT.create(0);
+        assert!(!prestate.contains_key(&created));
+        assert_eq!(prestate[&OTHER].balance, Some(U256::from(1)));
+        assert_eq!(prestate[&CALLER].nonce, None);
+        let storage = &prestate[&CONTRACT].storage;
+        assert_eq!(storage.len(), 3);
+        assert_eq!(storage[&B256::with_last_byte(3)], B256::with_last_byte(7));
+        assert!(prestate[&CONTRACT].code.is_some());
+
+        let json: serde_json::Value = serde_json::from_str(&tracer.to_json()).unwrap();
+        let contract = &json[CONTRACT.to_string().to_lowercase()];
+        assert_eq!(contract["balance"], "0x0");
+        assert!(contract.get("nonce").is_none());
+        assert_eq!(
+            contract["storage"][B256::with_last_byte(1).to_string()],
+            B256::with_last_byte(5).to_string()
+        );
+        assert!(tracer.diff().is_none());
+    }
+
+    #[test]
+    fn test_diff() {
+        let tracer = trace(PrestateTracer::new().with_diff_mode());
+        let diff = tracer.diff().unwrap();
+        // OTHER was only read; the coinbase is paid nothing
+        assert!(!diff.pre.contains_key(&OTHER) && !diff.post.contains_key(&OTHER));
+        assert!(!diff.pre.contains_key(&Address::ZERO));
+
+        let pre = &diff.pre[&CONTRACT];
+        assert_eq!(
+            pre.storage.keys().collect::<Vec<_>>(),
+            [&B256::with_last_byte(1), &B256::with_last_byte(2)]
+        );
+        let post = &diff.post[&CONTRACT];
+        // The nonce went up for the creation; the cleared slot is left out
+        assert_eq!(
+            (post.balance, post.nonce, &post.code),
+            (None, Some(1), &None)
+        );
+        assert_eq!(
+            post.storage.values().collect::<Vec<_>>(),
+            [&B256::with_last_byte(9)]
+        );
+        assert!(diff.post[&CALLER].balance.is_some());
+
+        let created = CONTRACT.create(0);
+        assert!(!diff.pre.contains_key(&created));
+        assert_eq!(diff.post[&created].nonce, Some(1));
+
+        let json: serde_json::Value = serde_json::from_str(&tracer.to_json()).unwrap();
+        assert!(json["pre"].is_object() && json["post"].is_object());
+    }
+}
@@ -0,0 +1,293 @@
+//! Gas Profiler
+//!
+//! Aggregates the gas charged by each executed instruction, per opcode and per program
+//! counter, to show where a transaction spends its gas. The split between base, dynamic
+//! and memory expansion costs comes straight from the interpreter's [`StepReport`]s.
+//!
+//! # Design
+//! - Fed one [`StepReport`] at a time, so it works with anything that drives
+//!   [`Evm::step`]; as an [`Inspector`] it records every instruction of
+//!   [`Evm::inspect_transact`], which [`GasProfiler::transact`] runs
+//! - Program counters are keyed by code address, since sub-calls run other contracts
+//! - Gas forwarded to sub-calls is not attributed to the calling instruction; the
+//!   callee's instructions are profiled themselves
+//! - Reports sort by total gas, highest first, and render as a table or JSON
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+
+use super::env::TxEnv;
+use super::execution::{Evm, ExecutionResult, StepGas, StepReport, TransactionError};
+use super::inspector::Inspector;
+use super::opcodes::Opcode;
+use crate::types::Address;
+
+/// Execution count and gas of a group of instructions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasStats {
+    /// Number of times the instructions ran.
+    pub count: u64,
+    /// Total base cost.
+    pub static_gas: u64,
+    /// Total parameter-dependent cost.
+    pub dynamic_gas: u64,
+    /// Total memory expansion cost.
+    pub memory_gas: u64,
+}
+
+impl GasStats {
+    /// Returns the total gas of all three kinds.
+    pub fn total_gas(&self) -> u64 {
+        self.static_gas + self.dynamic_gas + self.memory_gas
+    }
+
+    fn record(&mut self, gas: &StepGas) {
+        self.count += 1;
+        self.static_gas += gas.static_gas;
+        self.dynamic_gas += gas.dynamic_gas;
+        self.memory_gas += gas.memory_gas;
+    }
+}
+
+/// Profile of one opcode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpcodeProfile {
+    pub opcode: Opcode,
+    #[serde(flatten)]
+    pub stats: GasStats,
+    pub total_gas: u64,
+}
+
+/// Profile of one program counter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PcProfile {
+    /// Address the code was loaded from.
+    pub address: Address,
+    pub pc: usize,
+    pub opcode: Opcode,
+    #[serde(flatten)]
+    pub stats: GasStats,
+    pub total_gas: u64,
+}
+
+/// A finished profile, sorted by total gas, highest first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasReport {
+    /// Totals over every profiled instruction.
+    pub total: GasStats,
+    pub opcodes: Vec<OpcodeProfile>,
+    pub pcs: Vec<PcProfile>,
+}
+
+impl GasReport {
+    /// Serializes the report as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("gas reports always serialize")
+    }
+}
+
+impl fmt::Display for GasReport {
+    /// Renders the report as two aligned tables: per opcode, then per program counter.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:<14} {:>8} {:>10} {:>10} {:>10} {:>10}",
+            "OPCODE", "COUNT", "STATIC", "DYNAMIC", "MEMORY", "TOTAL"
+        )?;
+        for entry in &self.opcodes {
+            write_row(f, &entry.opcode.to_string(), &entry.stats)?;
+        }
+        write_row(f, "total", &self.total)?;
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<42} {:>6} {:<14} {:>8} {:>10} {:>10} {:>10} {:>10}",
+            "ADDRESS", "PC", "OPCODE", "COUNT", "STATIC", "DYNAMIC", "MEMORY", "TOTAL"
+        )?;
+        for entry in &self.pcs {
+            write!(f, "{:<42} {:>6} ", entry.address, entry.pc)?;
+            write_row(f, &entry.opcode.to_string(), &entry.stats)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_row(f: &mut fmt::Formatter<'_>, label: &str, stats: &GasStats) -> fmt::Result {
+    writeln!(
+        f,
+        "{:<14} {:>8} {:>10} {:>10} {:>10} {:>10}",
+        label,
+        stats.count,
+        stats.static_gas,
+        stats.dynamic_gas,
+        stats.memory_gas,
+        stats.total_gas()
+    )
+}
+
+/// Collects per-opcode and per-PC gas statistics from executed instructions.
+#[derive(Debug, Clone, Default)]
+pub struct GasProfiler {
+    by_opcode: BTreeMap<Opcode, GasStats>,
+    by_pc: BTreeMap<(Address, usize), (Opcode, GasStats)>,
+}
+
+impl GasProfiler {
+    /// Creates an empty profiler.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records one executed instruction. Bytes that are not opcodes are skipped; they
+    /// halt execution without charging anything.
+    pub fn record(&mut self, step: &StepReport) {
+        let Some(opcode) = step.opcode else {
+            return;
+        };
+        self.by_opcode.entry(opcode).or_default().record(&step.gas);
+        self.by_pc
+            .entry((step.code_address, step.pc))
+            .or_insert((opcode, GasStats::default()))
+            .1
+            .record(&step.gas);
+    }
+
+    /// Executes a transaction to completion, profiling every instruction.
+    ///
+    /// # Errors
+    /// Returns a [`TransactionError`] if the transaction is invalid.
+    pub fn transact(
+        &mut self,
+        evm: &mut Evm,
+        tx: TxEnv,
+    ) -> Result<ExecutionResult, TransactionError> {
+        evm.inspect_transact(tx, self)
+    }
+
+    /// Returns the statistics of an opcode, if it was executed.
+    pub fn opcode_stats(&self, opcode: Opcode) -> Option<&GasStats> {
+        self.by_opcode.get(&opcode)
+    }
+
+    /// Returns the statistics of the instruction at `pc` in the code of `address`.
+    pub fn pc_stats(&self, address: Address, pc: usize) -> Option<&GasStats> {
+        self.by_pc.get(&(address, pc)).map(|(_, stats)| stats)
+    }
+
+    /// Returns the totals over every recorded instruction.
+    pub fn total(&self) -> GasStats {
+        self.by_opcode
+            .values()
+            .fold(GasStats::default(), |mut total, stats| {
+                total.count += stats.count;
+                total.static_gas += stats.static_gas;
+                total.dynamic_gas += stats.dynamic_gas;
+                total.memory_gas += stats.memory_gas;
+                total
+            })
+    }
+
+    /// Builds a report sorted by total gas, highest first. Ties keep opcode and
+    /// address/PC order.
+    pub fn report(&self) -> GasReport {
+        let mut opcodes: Vec<_> = self
+            .by_opcode
+            .iter()
+            .map(|(opcode, stats)| OpcodeProfile {
+                opcode: *opcode,
+                stats: *stats,
+                total_gas: stats.total_gas(),
+            })
+            .collect();
+        opcodes.sort_by_key(|entry| std::cmp::Reverse(entry.total_gas));
+
+        let mut pcs: Vec<_> = self
+            .by_pc
+            .iter()
+            .map(|((address, pc), (opcode, stats))| PcProfile {
+                address: *address,
+                pc: *pc,
+                opcode: *opcode,
+                stats: *stats,
+                total_gas: stats.total_gas(),
+            })
+            .collect();
+        pcs.sort_by_key(|entry| std::cmp::Reverse(entry.total_gas));
+
+        GasReport {
+            total: self.total(),
+            opcodes,
+            pcs,
+        }
+    }
+}
+
+impl Inspector for GasProfiler {
+    fn step_end(&mut self, _evm: &Evm, step: &StepReport) {
+        self.record(step);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::test_utils::{deploy, CONTRACT};
+    use crate::types::Bytes;
+
+    fn profile(code: &[u8]) -> (GasProfiler, ExecutionResult) {
+        let (mut evm, tx) = deploy(Bytes::copy_from_slice(code));
+        let mut profiler = GasProfiler::new();
+        let result = profiler.transact(&mut evm, tx).unwrap();
+        (profiler, result)
+    }
+
+    #[test]
+    fn test_profile_splits_gas() {
+        // PUSH1 1 PUSH1 0 SSTORE PUSH1 0 PUSH1 64 MSTORE STOP
+        let code = [0x60, 1, 0x60, 0, 0x55, 0x60, 0, 0x60, 64, 0x52, 0x00];
+        let (profiler, result) = profile(&code);
+
+        let push = profiler.opcode_stats(Opcode::Push1).unwrap();
+        assert_eq!((push.count, push.static_gas), (4, 12));
+
+        let sstore = profiler.opcode_stats(Opcode::Sstore).unwrap();
+        assert_eq!(sstore.dynamic_gas, 22100);
+
+        let mstore = profiler.pc_stats(CONTRACT, 9).unwrap();
+        assert_eq!((mstore.static_gas, mstore.memory_gas), (3, 9));
+
+        // Everything but the intrinsic gas is accounted for
+        let total = profiler.total();
+        assert_eq!(total.count, 7);
+        assert_eq!(21000 + total.total_gas(), result.gas.gas_used);
+    }
+
+    #[test]
+    fn test_report_sorted_and_serializable() {
+        // PUSH1 1 PUSH1 0 SSTORE STOP
+        let (profiler, _) = profile(&[0x60, 1, 0x60, 0, 0x55, 0x00]);
+        let report = profiler.report();
+        assert_eq!(report.opcodes[0].opcode, Opcode::Sstore);
+        assert_eq!(report.pcs[0].pc, 4);
+        assert!(report
+            .opcodes
+            .windows(2)
+            .all(|pair| pair[0].total_gas >= pair[1].total_gas));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["opcodes"][0]["opcode"], "SSTORE");
+        assert_eq!(json["opcodes"][0]["dynamicGas"], 22100);
+        assert_eq!(json["total"]["count"], 4);
+
+        let table = report.to_string();
+        assert!(table.lines().nth(1).unwrap().starts_with("SSTORE"));
+        assert_eq!(report.total.total_gas(), 22106);
+    }
+}
@@ -0,0 +1,555 @@
+//! EVM Gas Schedules
+//!
+//! A [`GasSchedule`] holds every number the gas meter prices operations with: the base cost
+//! of each opcode and the parameters of dynamic costs (copying, hashing, logging, memory
+//! expansion, storage writes, calls). Each supported fork ships as a preset, and custom
+//! schedules can be loaded from JSON or TOML to experiment with alternative pricing.
+//!
+//! # Design
+//! - Presets are built by [`GasSchedule::for_spec`]; the fork also selects which rules apply
+//!   (e.g. net gas metering, warm/cold access), while the schedule supplies the numbers
+//! - Custom files only need the fields they change; everything else comes from the preset
+//!   of the file's `spec` (the latest fork if omitted)
+//! - Opcode overrides are keyed by mnemonic, e.g. `SLOAD = 500`
+//!
+//! # Example
+//! ```toml
+//! spec = "Cancun"
+//! memory_quadratic_divisor = 1024
+//!
+//! [opcode_costs]
+//! SLOAD = 500
+//! ```
+
+use std::collections::BTreeMap;
+
+use super::gas::{CALL_STIPEND, COLD_ACCOUNT_ACCESS_COST, COLD_SLOAD_COST, WARM_STORAGE_READ_COST};
+use super::opcodes::Opcode;
+use super::spec::SpecId;
+use serde::{Deserialize, Serialize};
+
+/// Errors that can occur while loading a gas schedule.
+#[derive(Debug, PartialEq, Eq)]
+pub enum GasScheduleError {
+    /// The JSON document is malformed or has unknown fields.
+    InvalidJson(String),
+    /// The TOML document is malformed or has unknown fields.
+    InvalidToml(String),
+}
+
+/// The complete set of gas prices used by a [`GasMeter`](super::gas::GasMeter).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "PartialGasSchedule")]
+pub struct GasSchedule {
+    /// Fork whose rules apply and whose preset fills unspecified prices.
+    pub spec: SpecId,
+    /// Base costs that replace the preset cost of individual opcodes.
+    pub opcode_costs: BTreeMap<Opcode, u64>,
+    /// Cost per word copied by `*COPY` opcodes.
+    pub copy_word_cost: u64,
+    /// Cost per word hashed by `KECCAK256` and `CREATE2`.
+    pub keccak_word_cost: u64,
+    /// Cost per byte of log data.
+    pub log_byte_cost: u64,
+    /// Cost per byte of the `EXP` exponent.
+    pub exp_byte_cost: u64,
+    /// Linear cost per word of memory.
+    pub memory_word_cost: u64,
+    /// Divisor of the quadratic memory cost term (`words^2 / divisor`).
+    pub memory_quadratic_divisor: u64,
+    /// Cost per word of init code for contract creation (EIP-3860).
+    pub init_code_word_cost: u64,
+    /// Cost per byte of code deployed by contract creation.
+    pub code_deposit_cost: u64,
+    /// Cost of accessing a warm address or slot (EIP-2929).
+    pub warm_storage_read_cost: u64,
+    /// Cost of the first access to an address (EIP-2929).
+    pub cold_account_access_cost: u64,
+    /// Cost of the first access to a storage slot (EIP-2929).
+    pub cold_sload_cost: u64,
+    /// Cost of setting a storage slot from zero to non-zero.
+    pub sstore_set_gas: u64,
+    /// Cost of changing a non-zero storage slot.
+    pub sstore_reset_gas: u64,
+    /// Cost of a no-op or dirty SSTORE under net gas metering (`SLOAD_GAS`).
+    pub sstore_noop_gas: u64,
+    /// Refund for clearing a storage slot.
+    pub sstore_clear_refund: u64,
+    /// Cost of transferring value with `CALL` or `CALLCODE`.
+    pub call_value_cost: u64,
+    /// Cost of bringing a new account into existence.
+    pub new_account_cost: u64,
+    /// Free gas given to the callee of a value-transferring call.
+    pub call_stipend: u64,
+    /// The refund is capped at `gas_used / max_refund_quotient`.
+    pub max_refund_quotient: u64,
+}
+
+impl Default for GasSchedule {
+    fn default() -> Self {
+        Self::for_spec(SpecId::default())
+    }
+}
+
+impl GasSchedule {
+    /// Returns the built-in gas schedule of the given fork.
+    pub fn for_spec(spec: SpecId) -> Self {
+        let sload_cost = if spec.is_enabled_in(SpecId::Berlin) {
+            WARM_STORAGE_READ_COST
+        } else if spec.is_enabled_in(SpecId::Istanbul) {
+            800
+        } else if spec.is_enabled_in(SpecId::TangerineWhistle) {
+            200
+        } else {
+            50
+        };
+        Self {
+            spec,
+            opcode_costs: BTreeMap::new(),
+            copy_word_cost: 3,
+            keccak_word_cost: 6,
+            log_byte_cost: 8,
+            exp_byte_cost: if spec.is_enabled_in(SpecId::SpuriousDragon) {
+                50 // EIP-160
+            } else {
+                10
+            },
+            memory_word_cost: 3,
+            memory_quadratic_divisor: 512,
+            init_code_word_cost: if spec.is_enabled_in(SpecId::Shanghai) {
+                2 // EIP-3860
+            } else {
+                0
+            },
+            code_deposit_cost: 200,
+            warm_storage_read_cost: WARM_STORAGE_READ_COST,
+            cold_account_access_cost: COLD_ACCOUNT_ACCESS_COST,
+            cold_sload_cost: COLD_SLOAD_COST,
+            sstore_set_gas: 20000,
+            sstore_reset_gas: if spec.is_enabled_in(SpecId::Berlin) {
+                5000 - COLD_SLOAD_COST // EIP-2929 charges the SLOAD part separately
+            } else {
+                5000
+            },
+            sstore_noop_gas: sload_cost,
+            sstore_clear_refund: if spec.is_enabled_in(SpecId::London) {
+                4800 // EIP-3529: SSTORE_RESET_GAS + ACCESS_LIST_STORAGE_KEY_COST
+            } else {
+                15000
+            },
+            call_value_cost: 9000,
+            new_account_cost: 25000,
+            call_stipend: CALL_STIPEND,
+            max_refund_quotient: if spec.is_enabled_in(SpecId::London) {
+                5 // EIP-3529
+            } else {
+                2
+            },
+        }
+    }
+
+    /// Parses a gas schedule from JSON.
+    ///
+    /// # Errors
+    /// Returns `GasScheduleError::InvalidJson` if the document cannot be parsed.
+    pub fn from_json(json: &str) -> Result<Self, GasScheduleError> {
+        serde_json::from_str(json).map_err(|err| GasScheduleError::InvalidJson(err.to_string()))
+    }
+
+    /// Parses a gas schedule from TOML.
+    ///
+    /// # Errors
+    /// Returns `GasScheduleError::InvalidToml` if the document cannot be parsed.
+    pub fn from_toml(toml: &str) -> Result<Self, GasScheduleError> {
+        toml::from_str(toml).map_err(|err| GasScheduleError::InvalidToml(err.to_string()))
+    }
+
+    /// Returns the base cost of an opcode: the override if one is set, otherwise the
+    /// preset cost for the schedule's fork.
+    pub fn opcode_cost(&self, opcode: Opcode) -> u64 {
+        match self.opcode_costs.get(&opcode) {
+            Some(cost) => *cost,
+            None => self.preset_opcode_cost(opcode),
+        }
+    }
+
+    /// Returns the built-in base cost of an opcode for the schedule's fork.
+    fn preset_opcode_cost(&self, opcode: Opcode) -> u64 {
+        let spec = self.spec;
+        match opcode {
+            // Stop and arithmetic operations
+            Opcode::Stop => 0,
+            Opcode::Add => 3,
+            Opcode::Mul => 5,
+            Opcode::Sub => 3,
+            Opcode::Div => 5,
+            Opcode::Sdiv => 5,
+            Opcode::Mod => 5,
+            Opcode::Smod => 5,
+            Opcode::Addmod => 8,
+            Opcode::Mulmod => 8,
+            Opcode::Exp => 10, // Base cost, actual cost depends on exponent
+            Opcode::Signextend => 5,
+
+            // Comparison operations
+            Opcode::Lt => 3,
+            Opcode::Gt => 3,
+            Opcode::Slt => 3,
+            Opcode::Sgt => 3,
+            Opcode::Eq => 3,
+            Opcode::Iszero => 3,
+
+            // Bitwise operations
+            Opcode::And => 3,
+            Opcode::Or => 3,
+            Opcode::Xor => 3,
+            Opcode::Not => 3,
+            Opcode::Byte => 3,
+            Opcode::Shl => 3,
+            Opcode::Shr => 3,
+            Opcode::Sar => 3,
+
+            // Cryptographic operations
+            Opcode::Keccak256 => 30, // Base cost, actual cost depends on data size
+
+            // Environment information
+            Opcode::Address => 2,
+            Opcode::Balance => {
+                if spec.is_enabled_in(SpecId::Berlin) {
+                    self.warm_storage_read_cost // Cold surcharge is dynamic (EIP-2929)
+                } else if spec.is_enabled_in(SpecId::Istanbul) {
+                    700 // EIP-1884
+                } else if spec.is_enabled_in(SpecId::TangerineWhistle) {
+                    400 // EIP-150
+                } else {
+                    20
+                }
+            }
+            Opcode::Origin => 2,
+            Opcode::Caller => 2,
+            Opcode::Callvalue => 2,
+            Opcode::Calldataload => 3,
+            Opcode::Calldatasize => 2,
+            Opcode::Calldatacopy => 3, // Base cost, actual cost depends on data size
+            Opcode::Codesize => 2,
+            Opcode::Codecopy => 3, // Base cost, actual cost depends on data size
+            Opcode::Gasprice => 2,
+            Opcode::Extcodecopy | Opcode::Extcodesize => {
+                if spec.is_enabled_in(SpecId::Berlin) {
+                    self.warm_storage_read_cost // Cold surcharge is dynamic (EIP-2929)
+                } else if spec.is_enabled_in(SpecId::TangerineWhistle) {
+                    700 // EIP-150
+                } else {
+                    20
+                }
+            }
+            Opcode::Extcodehash => {
+                if spec.is_enabled_in(SpecId::Berlin) {
+                    self.warm_storage_read_cost // Cold surcharge is dynamic (EIP-2929)
+                } else if spec.is_enabled_in(SpecId::Istanbul) {
+                    700 // EIP-1884
+                } else {
+                    400
+                }
+            }
+            Opcode::Returndatasize => 2,
+            Opcode::Returndatacopy => 3, // Base cost, actual cost depends on data size
+            Opcode::Blockhash => 20,
+            Opcode::Coinbase => 2,
+            Opcode::Timestamp => 2,
+            Opcode::Number => 2,
+            Opcode::Difficulty => 2,
+            Opcode::Gaslimit => 2,
+            Opcode::Chainid => 2,
+            Opcode::Selfbalance => 5,
+            Opcode::Basefee => 2,
+            Opcode::Blobhash => 3,
+            Opcode::Blobbasefee => 2,
+
+            // Stack operations
+            Opcode::Pop => 2,
+            Opcode::Mload => 3,
+            Opcode::Mstore => 3,
+            Opcode::Mstore8 => 3,
+            Opcode::Sload => {
+                if spec.is_enabled_in(SpecId::Berlin) {
+                    self.warm_storage_read_cost // Cold surcharge is dynamic (EIP-2929)
+                } else if spec.is_enabled_in(SpecId::Istanbul) {
+                    800 // EIP-1884
+                } else if spec.is_enabled_in(SpecId::TangerineWhistle) {
+                    200 // EIP-150
+                } else {
+                    50
+                }
+            }
+            Opcode::Sstore => 0, // Charged entirely by `dynamic_gas_cost`
+            Opcode::Jump => 8,
+            Opcode::Jumpi => 10,
+            Opcode::Pc => 2,
+            Opcode::Msize => 2,
+            Opcode::Gas => 2,
+            Opcode::Jumpdest => 1,
+            Opcode::Tload => 100,  // Warm storage access
+            Opcode::Tstore => 100, // Warm storage write
+            Opcode::Mcopy => 3,
+
+            // Push operations (PUSH0 is a base-cost operation, EIP-3855)
+            Opcode::Push0 => 2,
+            Opcode::Push1 => 3,
+            Opcode::Push2 => 3,
+            Opcode::Push3 => 3,
+            Opcode::Push4 => 3,
+            Opcode::Push5 => 3,
+            Opcode::Push6 => 3,
+            Opcode::Push7 => 3,
+            Opcode::Push8 => 3,
+            Opcode::Push9 => 3,
+            Opcode::Push10 => 3,
+            Opcode::Push11 => 3,
+            Opcode::Push12 => 3,
+            Opcode::Push13 => 3,
+            Opcode::Push14 => 3,
+            Opcode::Push15 => 3,
+            Opcode::Push16 => 3,
+            Opcode::Push17 => 3,
+            Opcode::Push18 => 3,
+            Opcode::Push19 => 3,
+            Opcode::Push20 => 3,
+            Opcode::Push21 => 3,
+            Opcode::Push22 => 3,
+            Opcode::Push23 => 3,
+            Opcode::Push24 => 3,
+            Opcode::Push25 => 3,
+            Opcode::Push26 => 3,
+            Opcode::Push27 => 3,
+            Opcode::Push28 => 3,
+            Opcode::Push29 => 3,
+            Opcode::Push30 => 3,
+            Opcode::Push31 => 3,
+            Opcode::Push32 => 3,
+
+            // Duplicate operations
+            Opcode::Dup1 => 3,
+            Opcode::Dup2 => 3,
+            Opcode::Dup3 => 3,
+            Opcode::Dup4 => 3,
+            Opcode::Dup5 => 3,
+            Opcode::Dup6 => 3,
+            Opcode::Dup7 => 3,
+            Opcode::Dup8 => 3,
+            Opcode::Dup9 => 3,
+            Opcode::Dup10 => 3,
+            Opcode::Dup11 => 3,
+            Opcode::Dup12 => 3,
+            Opcode::Dup13 => 3,
+            Opcode::Dup14 => 3,
+            Opcode::Dup15 => 3,
+            Opcode::Dup16 => 3,
+
+            // Swap operations
+            Opcode::Swap1 => 3,
+            Opcode::Swap2 => 3,
+            Opcode::Swap3 => 3,
+            Opcode::Swap4 => 3,
+            Opcode::Swap5 => 3,
+            Opcode::Swap6 => 3,
+            Opcode::Swap7 => 3,
+            Opcode::Swap8 => 3,
+            Opcode::Swap9 => 3,
+            Opcode::Swap10 => 3,
+            Opcode::Swap11 => 3,
+            Opcode::Swap12 => 3,
+            Opcode::Swap13 => 3,
+            Opcode::Swap14 => 3,
+            Opcode::Swap15 => 3,
+            Opcode::Swap16 => 3,
+
+            // Logging operations
+            Opcode::Log0 => 375,  // Base cost, actual cost depends on data size
+            Opcode::Log1 => 750,  // Base cost, actual cost depends on data size
+            Opcode::Log2 => 1125, // Base cost, actual cost depends on data size
+            Opcode::Log3 => 1500, // Base cost, actual cost depends on data size
+            Opcode::Log4 => 1875, // Base cost, actual cost depends on data size
+
+            // Contract creation and calls
+            Opcode::Create => 32000, // Base cost for contract creation
+            Opcode::Call | Opcode::Callcode | Opcode::Delegatecall | Opcode::Staticcall => {
+                if spec.is_enabled_in(SpecId::Berlin) {
+                    self.warm_storage_read_cost // Cold surcharge is dynamic (EIP-2929)
+                } else if spec.is_enabled_in(SpecId::TangerineWhistle) {
+                    700 // EIP-150
+                } else {
+                    40
+                }
+            }
+            Opcode::Return => 0,
+            Opcode::Create2 => 32000, // Base cost for contract creation
+            Opcode::Revert => 0,
+            Opcode::Invalid => 0,
+            Opcode::Selfdestruct => {
+                if spec.is_enabled_in(SpecId::TangerineWhistle) {
+                    5000 // EIP-150
+                } else {
+                    0
+                }
+            }
+        }
+    }
+}
+
+/// A gas schedule as written in a file, where every field is optional.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PartialGasSchedule {
+    spec: Option<SpecId>,
+    #[serde(default)]
+    opcode_costs: BTreeMap<Opcode, u64>,
+    copy_word_cost: Option<u64>,
+    keccak_word_cost: Option<u64>,
+    log_byte_cost: Option<u64>,
+    exp_byte_cost: Option<u64>,
+    memory_word_cost: Option<u64>,
+    memory_quadratic_divisor: Option<u64>,
+    init_code_word_cost: Option<u64>,
+    code_deposit_cost: Option<u64>,
+    warm_storage_read_cost: Option<u64>,
+    cold_account_access_cost: Option<u64>,
+    cold_sload_cost: Option<u64>,
+    sstore_set_gas: Option<u64>,
+    sstore_reset_gas: Option<u64>,
+    sstore_noop_gas: Option<u64>,
+    sstore_clear_refund: Option<u64>,
+    call_value_cost: Option<u64>,
+    new_account_cost: Option<u64>,
+    call_stipend: Option<u64>,
+    max_refund_quotient: Option<u64>,
+}
+
+impl From<PartialGasSchedule> for GasSchedule {
+    fn from(partial: PartialGasSchedule) -> Self {
+        let preset = GasSchedule::for_spec(partial.spec.unwrap_or_default());
+        GasSchedule {
+            spec: preset.spec,
+            opcode_costs: partial.opcode_costs,
+            copy_word_cost: partial.copy_word_cost.unwrap_or(preset.copy_word_cost),
+            keccak_word_cost: partial.keccak_word_cost.unwrap_or(preset.keccak_word_cost),
+            log_byte_cost: partial.log_byte_cost.unwrap_or(preset.log_byte_cost),
+            exp_byte_cost: partial.exp_byte_cost.unwrap_or(preset.exp_byte_cost),
+            memory_word_cost: partial.memory_word_cost.unwrap_or(preset.memory_word_cost),
+            memory_quadratic_divisor: partial
+                .memory_quadratic_divisor
+                .unwrap_or(preset.memory_quadratic_divisor),
+            init_code_word_cost: partial
+                .init_code_word_cost
+                .unwrap_or(preset.init_code_word_cost),
+            code_deposit_cost: partial
+                .code_deposit_cost
+                .unwrap_or(preset.code_deposit_cost),
+            warm_storage_read_cost: partial
+                .warm_storage_read_cost
+                .unwrap_or(preset.warm_storage_read_cost),
+            cold_account_access_cost: partial
+                .cold_account_access_cost
+                .unwrap_or(preset.cold_account_access_cost),
+            cold_sload_cost: partial.cold_sload_cost.unwrap_or(preset.cold_sload_cost),
+            sstore_set_gas: partial.sstore_set_gas.unwrap_or(preset.sstore_set_gas),
+            sstore_reset_gas: partial.sstore_reset_gas.unwrap_or(preset.sstore_reset_gas),
+            sstore_noop_gas: partial.sstore_noop_gas.unwrap_or(preset.sstore_noop_gas),
+            sstore_clear_refund: partial
+                .sstore_clear_refund
+                .unwrap_or(preset.sstore_clear_refund),
+            call_value_cost: partial.call_value_cost.unwrap_or(preset.call_value_cost),
+            new_account_cost: partial.new_account_cost.unwrap_or(preset.new_account_cost),
+            call_stipend: partial.call_stipend.unwrap_or(preset.call_stipend),
+            max_refund_quotient: partial
+                .max_refund_quotient
+                .unwrap_or(preset.max_refund_quotient),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::gas::{DynamicGasParams, GasMeter};
+
+    #[test]
+    fn test_presets_match_forks() {
+        assert_eq!(GasSchedule::for_spec(SpecId::Frontier).exp_byte_cost, 10);
+        assert_eq!(
+            GasSchedule::for_spec(SpecId::SpuriousDragon).exp_byte_cost,
+            50
+        );
+        assert_eq!(GasSchedule::for_spec(SpecId::Berlin).sstore_reset_gas, 2900);
+        assert_eq!(
+            GasSchedule::for_spec(SpecId::London).sstore_clear_refund,
+            4800
+        );
+        assert_eq!(GasSchedule::default().spec, SpecId::LATEST);
+    }
+
+    #[test]
+    fn test_partial_toml_overrides_preset() {
+        let schedule = GasSchedule::from_toml(
+            r#"
+            spec = "Istanbul"
+            memory_quadratic_divisor = 1024
+
+            [opcode_costs]
+            SLOAD = 500
+            PUSH1 = 1
+            "#,
+        )
+        .unwrap();
+        assert_eq!(schedule.spec, SpecId::Istanbul);
+        assert_eq!(schedule.opcode_cost(Opcode::Sload), 500);
+        assert_eq!(schedule.opcode_cost(Opcode::Push1), 1);
+        assert_eq!(schedule.opcode_cost(Opcode::Balance), 700); // Istanbul preset
+        assert_eq!(schedule.memory_quadratic_divisor, 1024);
+        assert_eq!(schedule.copy_word_cost, 3);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut schedule = GasSchedule::for_spec(SpecId::Cancun);
+        schedule.log_byte_cost = 16;
+        schedule.opcode_costs.insert(Opcode::Keccak256, 60);
+        let json = serde_json::to_string(&schedule).unwrap();
+        assert_eq!(GasSchedule::from_json(&json).unwrap(), schedule);
+    }
+
+    #[test]
+    fn test_invalid_schedules_are_rejected() {
+        assert!(matches!(
+            GasSchedule::from_json(r#"{"copy_word_costs": 4}"#),
+            Err(GasScheduleError::InvalidJson(_))
+        ));
+        assert!(matches!(
+            GasSchedule::from_toml("[opcode_costs]\nFOO = 1"),
+            Err(GasScheduleError::InvalidToml(_))
+        ));
+    }
+
+    #[test]
+    fn test_gas_meter_uses_custom_schedule() {
+        let schedule = GasSchedule::from_json(
+            r#"{"log_byte_cost": 16, "copy_word_cost": 1, "sstore_set_gas": 10000,
+                "memory_word_cost": 1, "opcode_costs": {"ADD": 1}}"#,
+        )
+        .unwrap();
+        let gas_meter = GasMeter::with_schedule(1000000, schedule);
+
+        assert_eq!(gas_meter.opcode_cost(Opcode::Add), 1);
+        let params = DynamicGasParams::new().with_size(10);
+        assert_eq!(gas_meter.dynamic_gas_cost(Opcode::Log1, &params), 160);
+        assert_eq!(gas_meter.dynamic_gas_cost(Opcode::Calldatacopy, &params), 1);
+        assert_eq!(gas_meter.memory_expansion_cost(0, 64), 2);
+
+        let params = DynamicGasParams::new().with_storage_values(
+            crate::types::U256::ZERO,
+            crate::types::U256::ZERO,
+            crate::types::U256::from(1),
+        );
+        assert_eq!(gas_meter.dynamic_gas_cost(Opcode::Sstore, &params), 10000);
+    }
+}
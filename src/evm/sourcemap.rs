@@ -0,0 +1,572 @@
+//! Solidity Source Maps
+//!
+//! Loads the output of `solc --standard-json` and maps program counters of the compiled
+//! bytecode back to the Solidity source: file, line and column, and whether the
+//! instruction jumps into or out of a function. Traces, revert reports, coverage and
+//! the debugger can then point at source lines instead of raw offsets.
+//!
+//! # Design
+//! - solc maps *instructions*, not bytes: entry `i` of a source map describes the `i`-th
+//!   instruction of the code, so [`SourceMap`] decodes the code to key entries by
+//!   program counter
+//! - The compressed `s:l:f:j:m` encoding is expanded by [`parse_source_map`]: empty or
+//!   missing fields repeat the previous entry's value
+//! - The output lists source files by id but not their text; lines and columns need the
+//!   text, added from the compiler input with [`SolcOutput::with_input`] or file by file
+//!   with [`SolcOutput::with_source`]. Without it, locations are byte ranges
+//! - File `-1` and ids without a listed file (compiler-generated Yul) have no location
+//! - Unlinked library placeholders (`__$...$__`) decode as zero addresses, which keeps
+//!   the program counters of the rest of the code intact. [`decode_bytecode`] is public
+//!   so that every tool reading solc hex output decodes it the same way
+//!
+//! # References
+//! - [Solidity docs: Source Mappings](https://docs.soliditylang.org/en/latest/internals/source_mappings.html)
+//! - [Solidity docs: Compiler Input and Output JSON Description](https://docs.soliditylang.org/en/latest/using-the-compiler.html#compiler-input-and-output-json-description)
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Deserialize;
+
+use super::disassembler::disassemble;
+use crate::types::Bytes;
+
+/// Length, in hex characters, of an unlinked library placeholder.
+const PLACEHOLDER_LEN: usize = 40;
+
+/// Errors that can occur while loading compiler output.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SourceMapError {
+    /// The JSON document is malformed or lacks required fields.
+    InvalidJson(String),
+    /// A field of a source map entry is not a number, or not a jump type.
+    InvalidEntry { index: usize, field: &'static str },
+    /// The bytecode of a contract is not valid hex.
+    InvalidBytecode { contract: String },
+}
+
+/// Errors that can occur while decoding hex bytecode.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BytecodeError {
+    /// A `__$` does not start a `__$...$__` placeholder of 34 hex digits.
+    MalformedPlaceholder,
+    /// The text is not valid hex.
+    InvalidHex(String),
+}
+
+/// How an instruction moves between functions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum JumpType {
+    /// A jump into a function (`i`).
+    Into,
+    /// A return from a function (`o`).
+    Out,
+    /// Any other instruction (`-`).
+    #[default]
+    Regular,
+}
+
+impl fmt::Display for JumpType {
+    /// Renders the jump type as solc encodes it.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            JumpType::Into => "i",
+            JumpType::Out => "o",
+            JumpType::Regular => "-",
+        })
+    }
+}
+
+/// One decompressed source map entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SourceMapEntry {
+    /// Byte offset of the source range.
+    pub offset: usize,
+    /// Length of the source range in bytes.
+    pub length: usize,
+    /// Source file id, `None` for `-1`.
+    pub file: Option<usize>,
+    pub jump: JumpType,
+    /// Depth of modifiers the instruction is nested in.
+    pub modifier_depth: usize,
+}
+
+/// Expands a compressed source map into one entry per instruction.
+///
+/// # Errors
+/// Returns [`SourceMapError::InvalidEntry`] for a field that does not parse.
+///
+/// # Examples
+/// ```
+/// use smol_evm::sourcemap::{parse_source_map, JumpType};
+///
+/// let entries = parse_source_map("1:2:1;:9;2:1:2;;").unwrap();
+/// assert_eq!(entries.len(), 5);
+/// assert_eq!((entries[1].offset, entries[1].length, entries[1].file), (1, 9, Some(1)));
+/// assert_eq!(entries[4].jump, JumpType::Regular);
+/// ```
+pub fn parse_source_map(map: &str) -> Result<Vec<SourceMapEntry>, SourceMapError> {
+    if map.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut entries = Vec::new();
+    let mut entry = SourceMapEntry::default();
+    for (index, text) in map.split(';').enumerate() {
+        let invalid = |field| SourceMapError::InvalidEntry { index, field };
+        let fields: Vec<_> = text.split(':').collect();
+        let field = |position: usize| fields.get(position).filter(|field| !field.is_empty());
+        if let Some(offset) = field(0) {
+            entry.offset = offset.parse().map_err(|_| invalid("offset"))?;
+        }
+        if let Some(length) = field(1) {
+            entry.length = length.parse().map_err(|_| invalid("length"))?;
+        }
+        if let Some(file) = field(2) {
+            let file: i64 = file.parse().map_err(|_| invalid("file"))?;
+            entry.file = usize::try_from(file).ok();
+        }
+        if let Some(jump) = field(3) {
+            entry.jump = match *jump {
+                "i" => JumpType::Into,
+                "o" => JumpType::Out,
+                "-" => JumpType::Regular,
+                _ => return Err(invalid("jump")),
+            };
+        }
+        if let Some(depth) = field(4) {
+            entry.modifier_depth = depth.parse().map_err(|_| invalid("modifier depth"))?;
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Source map entries keyed by the program counter of their instruction.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    entries: BTreeMap<usize, SourceMapEntry>,
+}
+
+impl SourceMap {
+    /// Pairs the entries with the instructions of `code`, in order. Instructions past the
+    /// last entry stay unmapped.
+    pub fn new(code: &[u8], entries: &[SourceMapEntry]) -> Self {
+        let entries = disassemble(code)
+            .instructions
+            .iter()
+            .zip(entries)
+            .map(|(instruction, entry)| (instruction.pc, *entry))
+            .collect();
+        Self { entries }
+    }
+
+    /// Returns the entry of the instruction at `pc`, if it is mapped.
+    pub fn entry(&self, pc: usize) -> Option<&SourceMapEntry> {
+        self.entries.get(&pc)
+    }
+
+    /// Returns the number of mapped instructions.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no instruction is mapped.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Code and its source map.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MappedCode {
+    pub code: Bytes,
+    pub source_map: SourceMap,
+}
+
+/// A contract of the compiler output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompiledContract {
+    /// Path of the source file declaring the contract.
+    pub path: String,
+    pub name: String,
+    /// Init code, `evm.bytecode`.
+    pub creation: MappedCode,
+    /// Deployed code, `evm.deployedBytecode`.
+    pub runtime: MappedCode,
+}
+
+/// A source file of the compilation.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceFile {
+    pub path: String,
+    /// Source text, if it was added.
+    pub content: Option<String>,
+}
+
+impl SourceFile {
+    /// Returns the 1-based line and column of a byte offset, if the text is known.
+    pub fn line_column(&self, offset: usize) -> Option<(usize, usize)> {
+        let before = self.content.as_ref()?.as_bytes().get(..offset)?;
+        let line_start = before
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+        let line = 1 + before.iter().filter(|byte| **byte == b'\n').count();
+        Some((line, offset - line_start + 1))
+    }
+}
+
+/// Where in the source an instruction comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub path: String,
+    pub offset: usize,
+    pub length: usize,
+    /// 1-based line and column of `offset`, if the source text is known.
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub jump: JumpType,
+}
+
+impl fmt::Display for SourceLocation {
+    /// Renders `path:line:column`, or `path:offset+length` without the source text.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => write!(f, "{}:{line}:{column}", self.path),
+            _ => write!(f, "{}:{}+{}", self.path, self.offset, self.length),
+        }
+    }
+}
+
+/// The contracts and source files of a `solc --standard-json` run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SolcOutput {
+    /// Source files by id.
+    sources: BTreeMap<usize, SourceFile>,
+    /// Contracts in path and name order.
+    contracts: Vec<CompiledContract>,
+}
+
+#[derive(Deserialize)]
+struct OutputJson {
+    #[serde(default)]
+    sources: BTreeMap<String, SourceJson>,
+    #[serde(default)]
+    contracts: BTreeMap<String, BTreeMap<String, ContractJson>>,
+}
+
+#[derive(Deserialize)]
+struct SourceJson {
+    id: usize,
+}
+
+#[derive(Deserialize)]
+struct ContractJson {
+    #[serde(default)]
+    evm: EvmJson,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EvmJson {
+    #[serde(default)]
+    bytecode: BytecodeJson,
+    #[serde(default)]
+    deployed_bytecode: BytecodeJson,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BytecodeJson {
+    #[serde(default)]
+    object: String,
+    #[serde(default)]
+    source_map: String,
+}
+
+#[derive(Deserialize)]
+struct InputJson {
+    sources: BTreeMap<String, InputSourceJson>,
+}
+
+#[derive(Deserialize)]
+struct InputSourceJson {
+    content: Option<String>,
+}
+
+impl SolcOutput {
+    /// Parses the compiler's standard-JSON output. Contracts need `evm.bytecode` and
+    /// `evm.deployedBytecode`, with their `object` and `sourceMap`, selected as outputs;
+    /// missing ones load as empty code.
+    ///
+    /// # Errors
+    /// Returns a [`SourceMapError`] if the document, a bytecode or a source map is
+    /// malformed.
+    pub fn from_json(json: &str) -> Result<Self, SourceMapError> {
+        let output: OutputJson = serde_json::from_str(json)
+            .map_err(|err| SourceMapError::InvalidJson(err.to_string()))?;
+        let sources = output
+            .sources
+            .into_iter()
+            .map(|(path, source)| {
+                let file = SourceFile {
+                    path,
+                    content: None,
+                };
+                (source.id, file)
+            })
+            .collect();
+
+        let mut contracts = Vec::new();
+        for (path, by_name) in output.contracts {
+            for (name, contract) in by_name {
+                let load = |bytecode: &BytecodeJson| {
+                    let code: Bytes = decode_bytecode(&bytecode.object)
+                        .map_err(|_| SourceMapError::InvalidBytecode {
+                            contract: format!("{path}:{name}"),
+                        })?
+                        .into();
+                    let entries = parse_source_map(&bytecode.source_map)?;
+                    let source_map = SourceMap::new(&code, &entries);
+                    Ok::<_, SourceMapError>(MappedCode { code, source_map })
+                };
+                contracts.push(CompiledContract {
+                    creation: load(&contract.evm.bytecode)?,
+                    runtime: load(&contract.evm.deployed_bytecode)?,
+                    path: path.clone(),
+                    name,
+                });
+            }
+        }
+        Ok(Self { sources, contracts })
+    }
+
+    /// Adds the source texts of the compiler's standard-JSON input, for files given by
+    /// `content`.
+    ///
+    /// # Errors
+    /// Returns [`SourceMapError::InvalidJson`] if the document is malformed.
+    pub fn with_input(mut self, json: &str) -> Result<Self, SourceMapError> {
+        let input: InputJson = serde_json::from_str(json)
+            .map_err(|err| SourceMapError::InvalidJson(err.to_string()))?;
+        for (path, source) in input.sources {
+            if let Some(content) = source.content {
+                self = self.with_source(&path, content);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Adds the text of the source file at `path`; paths the output does not list are
+    /// ignored.
+    pub fn with_source(mut self, path: &str, content: impl Into<String>) -> Self {
+        if let Some(file) = self.sources.values_mut().find(|file| file.path == path) {
+            file.content = Some(content.into());
+        }
+        self
+    }
+
+    /// Returns the source files by id.
+    pub fn sources(&self) -> &BTreeMap<usize, SourceFile> {
+        &self.sources
+    }
+
+    /// Returns the contracts, in path and name order.
+    pub fn contracts(&self) -> &[CompiledContract] {
+        &self.contracts
+    }
+
+    /// Finds a contract by `name`, or by `path:name` when names are ambiguous.
+    pub fn contract(&self, name: &str) -> Option<&CompiledContract> {
+        self.contracts.iter().find(|contract| {
+            contract.name == name || format!("{}:{}", contract.path, contract.name) == name
+        })
+    }
+
+    /// Finds the init or deployed code of a contract that is exactly `code`.
+    pub fn find_code(&self, code: &[u8]) -> Option<&MappedCode> {
+        self.contracts
+            .iter()
+            .flat_map(|contract| [&contract.creation, &contract.runtime])
+            .find(|mapped| !mapped.code.is_empty() && *mapped.code == *code)
+    }
+
+    /// Locates the instruction at `pc` of `code` in the source. Returns `None` for
+    /// unmapped instructions and instructions without a source file.
+    pub fn locate(&self, code: &MappedCode, pc: usize) -> Option<SourceLocation> {
+        let entry = code.source_map.entry(pc)?;
+        let file = self.sources.get(&entry.file?)?;
+        let (line, column) = file.line_column(entry.offset).unzip();
+        Some(SourceLocation {
+            path: file.path.clone(),
+            offset: entry.offset,
+            length: entry.length,
+            line,
+            column,
+            jump: entry.jump,
+        })
+    }
+}
+
+/// Decodes hex bytecode as solc outputs it, with or without `0x`, reading unlinked
+/// library placeholders as zero addresses.
+///
+/// # Errors
+/// Returns [`BytecodeError::MalformedPlaceholder`] for a `__$` that does not start a
+/// complete placeholder, and [`BytecodeError::InvalidHex`] for anything else that is not
+/// hex.
+///
+/// # Examples
+/// ```
+/// use smol_evm::sourcemap::decode_bytecode;
+///
+/// let linked = format!("0x73{}ff", "__$0123456789abcdef0123456789abcdef01$__");
+/// let code = decode_bytecode(&linked).unwrap();
+/// assert_eq!((code.len(), code[1], code[21]), (22, 0, 0xff));
+/// ```
+pub fn decode_bytecode(object: &str) -> Result<Vec<u8>, BytecodeError> {
+    let object = object.strip_prefix("0x").unwrap_or(object);
+    let mut hex = String::with_capacity(object.len());
+    let mut rest = object;
+    while let Some(start) = rest.find("__$") {
+        // `get` also fails when the placeholder would end inside a multi-byte character
+        let placeholder = rest
+            .get(start..start + PLACEHOLDER_LEN)
+            .filter(|placeholder| {
+                placeholder.ends_with("$__")
+                    && placeholder[3..PLACEHOLDER_LEN - 3]
+                        .bytes()
+                        .all(|byte| byte.is_ascii_hexdigit())
+            })
+            .ok_or(BytecodeError::MalformedPlaceholder)?;
+        hex.push_str(&rest[..start]);
+        hex.push_str(&"0".repeat(placeholder.len()));
+        rest = &rest[start + PLACEHOLDER_LEN..];
+    }
+    hex.push_str(rest);
+    hex::decode(&hex).map_err(|err| BytecodeError::InvalidHex(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "contract C {\n    function f() public {\n        revert();\n    }\n}\n";
+
+    fn output() -> String {
+        // PUSH1 0x80 PUSH1 0x40 MSTORE <placeholder> PUSH20.. JUMP
+        let placeholder = format!("__${}$__", "ab".repeat(17));
+        serde_json::json!({
+            "sources": { "c.sol": { "id": 0 } },
+            "contracts": { "c.sol": { "C": { "evm": {
+                "bytecode": { "object": "6080604052", "sourceMap": "0:70:0:-:0;;" },
+                "deployedBytecode": {
+                    "object": format!("600073{placeholder}56fe"),
+                    "sourceMap": "47:8:0:-;:::i;::-1:o",
+                },
+            } } } },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_parse_source_map() {
+        let entries = parse_source_map("0:70:0:-:0;;17:5::i;:::o:1;8:2:-1").unwrap();
+        assert_eq!(entries.len(), 5);
+        assert_eq!(entries[0], entries[1]);
+        assert_eq!(
+            entries[2],
+            SourceMapEntry {
+                offset: 17,
+                length: 5,
+                file: Some(0),
+                jump: JumpType::Into,
+                modifier_depth: 0,
+            }
+        );
+        assert_eq!(
+            (
+                entries[3].offset,
+                entries[3].jump,
+                entries[3].modifier_depth
+            ),
+            (17, JumpType::Out, 1)
+        );
+        assert_eq!(
+            (entries[4].file, entries[4].jump, entries[4].modifier_depth),
+            (None, JumpType::Out, 1)
+        );
+        assert!(parse_source_map("").unwrap().is_empty());
+        assert_eq!(
+            parse_source_map("1:2:0;1:2:0:x"),
+            Err(SourceMapError::InvalidEntry {
+                index: 1,
+                field: "jump"
+            })
+        );
+    }
+
+    #[test]
+    fn test_decode_bytecode() {
+        let placeholder = format!("__${}$__", "ab".repeat(17));
+        let code = decode_bytecode(&format!("73{placeholder}ff")).unwrap();
+        assert_eq!(code.len(), 22);
+        assert_eq!(decode_bytecode("6080").unwrap(), [0x60, 0x80]);
+        // Placeholders must be complete and closed by `$__`
+        for object in [
+            format!("73{}", &placeholder[..39]),
+            format!("73__${}ff", "ab".repeat(18)),
+            format!("73__${}", "é".repeat(20)),
+        ] {
+            assert_eq!(
+                decode_bytecode(&object),
+                Err(BytecodeError::MalformedPlaceholder)
+            );
+        }
+        // Anything else is strict hex: no bare `__`, no sign
+        assert!(matches!(
+            decode_bytecode(&format!("73__{}", "ab".repeat(19))),
+            Err(BytecodeError::InvalidHex(_))
+        ));
+        assert!(matches!(
+            decode_bytecode("+f"),
+            Err(BytecodeError::InvalidHex(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_and_locate() {
+        let output = SolcOutput::from_json(&output()).unwrap();
+        let contract = output.contract("c.sol:C").unwrap();
+        assert_eq!(output.contract("C"), Some(contract));
+        assert_eq!(contract.creation.source_map.len(), 3);
+
+        // The placeholder decodes as 20 zero bytes, so JUMP stays at pc 23
+        let runtime = &contract.runtime;
+        assert_eq!(runtime.code.len(), 25);
+        assert_eq!(output.find_code(&runtime.code), Some(runtime));
+        let location = output.locate(runtime, 2).unwrap();
+        assert_eq!((location.offset, location.jump), (47, JumpType::Into));
+        assert_eq!(location.to_string(), "c.sol:47+8");
+        // The third instruction, JUMP, has no source file; INVALID is unmapped
+        assert_eq!(runtime.source_map.entry(23).unwrap().jump, JumpType::Out);
+        assert_eq!(output.locate(runtime, 23), None);
+        assert_eq!(runtime.source_map.entry(24), None);
+
+        let input = serde_json::json!({
+            "language": "Solidity",
+            "sources": { "c.sol": { "content": SOURCE } },
+        });
+        let output = output.with_input(&input.to_string()).unwrap();
+        let runtime = &output.contract("C").unwrap().runtime;
+        let location = output.locate(runtime, 0).unwrap();
+        assert_eq!(&SOURCE[47..55], "revert()");
+        assert_eq!((location.line, location.column), (Some(3), Some(9)));
+        assert_eq!(location.to_string(), "c.sol:3:9");
+
+        assert!(matches!(
+            SolcOutput::from_json("{\"contracts\": {\"c.sol\": {\"C\": {\"evm\": {\"bytecode\": {\"object\": \"6g\"}}}}}}"),
+            Err(SourceMapError::InvalidBytecode { contract }) if contract == "c.sol:C"
+        ));
+    }
+}
@@ -0,0 +1,149 @@
+//! Ethereum Hardfork Specifications
+//!
+//! Defines [`SpecId`], the list of Ethereum mainnet hardforks that change EVM behaviour.
+//! The gas meter and interpreter consult the selected spec to decide which opcodes are
+//! available and which gas schedule applies.
+//!
+//! # Design
+//! - Forks are ordered chronologically, so `spec >= SpecId::Berlin` means "Berlin rules apply"
+//! - Forks that only moved the difficulty bomb (Muir/Arrow/Gray Glacier) are omitted
+//!
+//! # References
+//! - [Ethereum Execution Specs](https://github.com/ethereum/execution-specs)
+
+use std::str::FromStr;
+
+use crate::types::Address;
+use serde::{Deserialize, Serialize};
+
+/// An Ethereum hardfork, in activation order.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+pub enum SpecId {
+    /// Genesis rules (July 2015).
+    Frontier,
+    /// EIP-2, EIP-7 (`DELEGATECALL`).
+    Homestead,
+    /// EIP-150 gas repricing of IO-heavy opcodes and the 63/64 call rule.
+    TangerineWhistle,
+    /// EIP-160 `EXP` repricing, EIP-161 state clearing, EIP-170 code size limit.
+    SpuriousDragon,
+    /// EIP-140 `REVERT`, EIP-211 return data, EIP-214 `STATICCALL`.
+    Byzantium,
+    /// EIP-145 shifts, EIP-1014 `CREATE2`, EIP-1052 `EXTCODEHASH`, EIP-1283 net metering.
+    Constantinople,
+    /// Constantinople with EIP-1283 removed.
+    Petersburg,
+    /// EIP-1884 repricing, EIP-2028 calldata cost, EIP-2200 net metering.
+    Istanbul,
+    /// EIP-2929 warm/cold access costs, EIP-2930 access lists.
+    Berlin,
+    /// EIP-1559 `BASEFEE`, EIP-3529 refund reduction.
+    London,
+    /// The Merge: `DIFFICULTY` becomes `PREVRANDAO`.
+    Paris,
+    /// EIP-3651 warm coinbase, EIP-3855 `PUSH0`, EIP-3860 init code limits.
+    Shanghai,
+    /// EIP-1153 transient storage, EIP-4844 blobs, EIP-5656 `MCOPY`.
+    Cancun,
+    /// EIP-7702 set-code transactions, EIP-7623 calldata floor.
+    Prague,
+    /// EIP-7825 transaction gas cap, EIP-7823/7883 `MODEXP` changes.
+    #[default]
+    Osaka,
+}
+
+impl SpecId {
+    /// The most recent hardfork supported by this implementation.
+    pub const LATEST: SpecId = SpecId::Osaka;
+
+    /// Returns `true` if the rules of `fork` are active under this spec.
+    pub fn is_enabled_in(self, fork: SpecId) -> bool {
+        self >= fork
+    }
+
+    /// Returns the addresses of the precompiled contracts available under this spec.
+    pub fn precompiles(self) -> Vec<Address> {
+        let count = if self.is_enabled_in(SpecId::Prague) {
+            0x11 // EIP-2537 BLS12-381 operations
+        } else if self.is_enabled_in(SpecId::Cancun) {
+            0x0a // EIP-4844 point evaluation
+        } else if self.is_enabled_in(SpecId::Istanbul) {
+            0x09 // EIP-152 BLAKE2 compression
+        } else if self.is_enabled_in(SpecId::Byzantium) {
+            0x08 // EIP-196/197/198 bn256 and MODEXP
+        } else {
+            0x04
+        };
+        let mut addresses: Vec<Address> = (1..=count).map(Address::with_last_byte).collect();
+        if self.is_enabled_in(SpecId::Osaka) {
+            // EIP-7951 secp256r1 verification lives at 0x100
+            let mut p256verify = [0u8; 20];
+            p256verify[18] = 0x01;
+            addresses.push(Address::from(p256verify));
+        }
+        addresses
+    }
+}
+
+impl FromStr for SpecId {
+    type Err = String;
+
+    /// Parses a fork name, ignoring case, e.g. `"cancun"` or `"TangerineWhistle"`.
+    /// `"latest"` means [`SpecId::LATEST`].
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        let spec = match name.to_ascii_lowercase().as_str() {
+            "frontier" => SpecId::Frontier,
+            "homestead" => SpecId::Homestead,
+            "tangerinewhistle" => SpecId::TangerineWhistle,
+            "spuriousdragon" => SpecId::SpuriousDragon,
+            "byzantium" => SpecId::Byzantium,
+            "constantinople" => SpecId::Constantinople,
+            "petersburg" => SpecId::Petersburg,
+            "istanbul" => SpecId::Istanbul,
+            "berlin" => SpecId::Berlin,
+            "london" => SpecId::London,
+            "paris" | "merge" => SpecId::Paris,
+            "shanghai" => SpecId::Shanghai,
+            "cancun" => SpecId::Cancun,
+            "prague" => SpecId::Prague,
+            "osaka" | "latest" => SpecId::LATEST,
+            _ => return Err(format!("unknown fork `{name}`")),
+        };
+        Ok(spec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_ordering() {
+        assert!(SpecId::Berlin.is_enabled_in(SpecId::Istanbul));
+        assert!(SpecId::Berlin.is_enabled_in(SpecId::Berlin));
+        assert!(!SpecId::Istanbul.is_enabled_in(SpecId::Berlin));
+        assert_eq!(SpecId::default(), SpecId::LATEST);
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("cancun".parse(), Ok(SpecId::Cancun));
+        assert_eq!("TangerineWhistle".parse(), Ok(SpecId::TangerineWhistle));
+        assert_eq!("latest".parse(), Ok(SpecId::LATEST));
+        assert!("bogus".parse::<SpecId>().is_err());
+    }
+
+    #[test]
+    fn test_precompiles() {
+        assert_eq!(SpecId::Frontier.precompiles().len(), 4);
+        assert_eq!(SpecId::Berlin.precompiles().len(), 9);
+        assert_eq!(SpecId::Cancun.precompiles().len(), 10);
+        assert_eq!(SpecId::Osaka.precompiles().len(), 18);
+        assert_eq!(
+            SpecId::Frontier.precompiles()[0],
+            Address::with_last_byte(1)
+        );
+    }
+}
@@ -28,11 +28,17 @@ pub enum StackError {
 /// # Invariants
 /// - The stack never grows beyond 1024 elements.
 /// - All elements are 256-bit unsigned integers ([`U256`]).
-
+#[derive(Debug, Clone)]
 pub struct Stack {
     stack: Vec<U256>,
 }
 
+impl Default for Stack {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Stack {
     /// Creates a new, empty EVM stack.
     pub fn new() -> Self {
@@ -56,6 +62,25 @@ impl Stack {
         self.stack.pop().ok_or(StackError::Underflow)
     }
 
+    /// Duplicates the `n`-th value from the top (1-based) onto the top of the stack (`DUPn`).
+    pub fn dup(&mut self, n: usize) -> Result<(), StackError> {
+        if n == 0 || n > self.stack.len() {
+            return Err(StackError::Underflow);
+        }
+        let value = self.stack[self.stack.len() - n];
+        self.push(value)
+    }
+
+    /// Swaps the top value with the `n`-th value below it (`SWAPn`).
+    pub fn swap(&mut self, n: usize) -> Result<(), StackError> {
+        if n == 0 || n >= self.stack.len() {
+            return Err(StackError::Underflow);
+        }
+        let top = self.stack.len() - 1;
+        self.stack.swap(top, top - n);
+        Ok(())
+    }
+
     /// Returns a reference to the top value on the stack, if any.
     pub fn peek(&self) -> Option<&U256> {
         self.stack.last()
@@ -75,6 +100,11 @@ impl Stack {
     pub fn len(&self) -> usize {
         self.stack.len()
     }
+
+    /// Returns the values on the stack, bottom first.
+    pub fn as_slice(&self) -> &[U256] {
+        &self.stack
+    }
 }
 
 #[cfg(test)]
@@ -106,6 +136,20 @@ mod tests {
         assert_eq!(stack.pop(), Err(StackError::Underflow));
     }
 
+    #[test]
+    fn test_stack_dup_and_swap() {
+        let mut stack = Stack::new();
+        stack.push(U256::from(1)).unwrap();
+        stack.push(U256::from(2)).unwrap();
+        stack.dup(2).unwrap();
+        assert_eq!(*stack.peek().unwrap(), U256::from(1));
+        stack.swap(2).unwrap();
+        assert_eq!(stack.pop().unwrap(), U256::from(1));
+        assert_eq!(stack.pop().unwrap(), U256::from(2));
+        assert_eq!(stack.swap(1), Err(StackError::Underflow));
+        assert_eq!(stack.dup(2), Err(StackError::Underflow));
+    }
+
     #[test]
     fn test_stack_peek() {
         let mut stack = Stack::new();
@@ -0,0 +1,342 @@
+//! Stack Height Validation
+//!
+//! Checks statically that code cannot underflow or overflow the stack, to catch
+//! generated bytecode that is broken before it is deployed. Each basic block of the
+//! [`Cfg`] is summarized by the stack height it needs on entry and the change it makes,
+//! and the heights execution can enter each block with are propagated along the edges
+//! from the start of the code, where the stack is empty.
+//!
+//! # Design
+//! - Per-opcode effects come from [`Opcode::stack_io`]
+//! - Entry heights are tracked as a range, the lowest and highest over every path of
+//!   the graph. Conditions are not evaluated, so a reported path may never run; but
+//!   when it does, it fails regardless of the values on the stack
+//! - A block entered too low underflows; one entered too high pushes past
+//!   [`STACK_MAX_SIZE`]. Only the heights it survives are passed on, so loops that grow
+//!   or shrink the stack converge
+//! - A failure is guaranteed when every entry height fails, and possible when only some
+//!   paths fail
+//! - Blocks only reachable through unresolved jumps get no entry height and are listed
+//!   as unreached instead of being checked
+//!
+//! # References
+//! - [EIP-5450: EOF - Stack Validation](https://eips.ethereum.org/EIPS/eip-5450)
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use serde::Serialize;
+
+use super::cfg::{BasicBlock, Cfg};
+use super::stack::STACK_MAX_SIZE;
+
+/// The stack effect of a block and the heights it can be entered with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockStack {
+    /// Program counter of the block's first instruction.
+    pub start: usize,
+    /// Stack height the block needs on entry.
+    pub required: usize,
+    /// Height on exit minus height on entry.
+    pub net_change: isize,
+    /// Highest the stack grows above its entry height.
+    pub max_growth: usize,
+    /// Lowest and highest entry height, `None` if the block is never reached.
+    pub min_entry: Option<usize>,
+    pub max_entry: Option<usize>,
+}
+
+impl BlockStack {
+    fn of(block: &BasicBlock) -> Self {
+        let (mut height, mut required, mut max_growth) = (0isize, 0usize, 0isize);
+        for instruction in &block.instructions {
+            let Some(opcode) = instruction.opcode else {
+                break;
+            };
+            let (pops, pushes) = opcode.stack_io();
+            required = required.max((pops as isize - height).max(0) as usize);
+            height += pushes as isize - pops as isize;
+            max_growth = max_growth.max(height);
+        }
+        Self {
+            start: block.start,
+            required,
+            net_change: height,
+            max_growth: max_growth as usize,
+            min_entry: None,
+            max_entry: None,
+        }
+    }
+
+    /// Returns the entry heights that pass through the block without failing, if any.
+    fn surviving(&self, low: usize, high: usize) -> Option<(usize, usize)> {
+        let low = low.max(self.required);
+        let high = high.min(STACK_MAX_SIZE.checked_sub(self.max_growth)?);
+        (low <= high).then_some((low, high))
+    }
+}
+
+/// A way the stack fails.
+///
+/// `guaranteed` is `true` if the block fails with every height it can be entered with,
+/// and `false` if only some paths into it fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum StackIssue {
+    /// The instruction at `pc` needs more items than the stack holds when its block is
+    /// entered with `height` items.
+    Underflow {
+        pc: usize,
+        block: usize,
+        height: usize,
+        guaranteed: bool,
+    },
+    /// The instruction at `pc` grows the stack past [`STACK_MAX_SIZE`] when its block is
+    /// entered with `height` items.
+    Overflow {
+        pc: usize,
+        block: usize,
+        height: usize,
+        guaranteed: bool,
+    },
+}
+
+impl fmt::Display for StackIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (kind, pc, block, height, guaranteed) = match *self {
+            StackIssue::Underflow {
+                pc,
+                block,
+                height,
+                guaranteed,
+            } => ("underflow", pc, block, height, guaranteed),
+            StackIssue::Overflow {
+                pc,
+                block,
+                height,
+                guaranteed,
+            } => ("overflow", pc, block, height, guaranteed),
+        };
+        let certainty = if guaranteed { "guaranteed" } else { "possible" };
+        write!(
+            f,
+            "0x{pc:04x}: {certainty} stack {kind} when the block at 0x{block:04x} is entered with {height} items"
+        )
+    }
+}
+
+/// The result of validating the stack heights of a piece of code.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StackReport {
+    /// Blocks in code order.
+    pub blocks: Vec<BlockStack>,
+    /// Failures in block order, underflow before overflow.
+    pub issues: Vec<StackIssue>,
+    /// Blocks no resolved path reaches, left unchecked.
+    pub unreached: Vec<usize>,
+}
+
+impl StackReport {
+    /// Validates the stack heights of the blocks of `cfg`.
+    pub fn analyze(cfg: &Cfg) -> Self {
+        let mut blocks: Vec<_> = cfg.blocks.iter().map(BlockStack::of).collect();
+        let mut worklist = VecDeque::new();
+        if let Some(entry) = blocks.first_mut() {
+            (entry.min_entry, entry.max_entry) = (Some(0), Some(0));
+            worklist.push_back(0);
+        }
+        while let Some(index) = worklist.pop_front() {
+            let block = blocks[index];
+            let (Some(low), Some(high)) = (block.min_entry, block.max_entry) else {
+                continue;
+            };
+            let Some((low, high)) = block.surviving(low, high) else {
+                continue;
+            };
+            // Surviving heights never drop below `required`, so the exit height is valid
+            let exit_low = (low as isize + block.net_change) as usize;
+            let exit_high = (high as isize + block.net_change) as usize;
+            for edge in cfg.successors(block.start) {
+                let Some(next) = cfg.block_index(edge.to) else {
+                    continue;
+                };
+                let target = &mut blocks[next];
+                let min_entry = target.min_entry.map_or(exit_low, |min| min.min(exit_low));
+                let max_entry = target.max_entry.map_or(exit_high, |max| max.max(exit_high));
+                if (Some(min_entry), Some(max_entry)) != (target.min_entry, target.max_entry) {
+                    (target.min_entry, target.max_entry) = (Some(min_entry), Some(max_entry));
+                    worklist.push_back(next);
+                }
+            }
+        }
+
+        let mut report = Self::default();
+        for (block, stack) in cfg.blocks.iter().zip(&blocks) {
+            let (Some(low), Some(high)) = (stack.min_entry, stack.max_entry) else {
+                report.unreached.push(block.start);
+                continue;
+            };
+            if low < stack.required {
+                report.issues.push(StackIssue::Underflow {
+                    pc: failing_pc(block, low),
+                    block: block.start,
+                    height: low,
+                    guaranteed: high < stack.required,
+                });
+            }
+            if high + stack.max_growth > STACK_MAX_SIZE {
+                report.issues.push(StackIssue::Overflow {
+                    pc: failing_pc(block, high),
+                    block: block.start,
+                    height: high,
+                    guaranteed: low + stack.max_growth > STACK_MAX_SIZE,
+                });
+            }
+        }
+        report.blocks = blocks;
+        report
+    }
+
+    /// Returns `true` if no reached block can fail.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Serializes the report as pretty-printed JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("stack reports always serialize")
+    }
+}
+
+impl fmt::Display for StackReport {
+    /// Renders one line per issue, then the unreached blocks.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{issue}")?;
+        }
+        if !self.unreached.is_empty() {
+            let starts: Vec<_> = self
+                .unreached
+                .iter()
+                .map(|pc| format!("0x{pc:04x}"))
+                .collect();
+            writeln!(f, "unreached blocks: {}", starts.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the first instruction of `block` that fails when it is entered with `height`
+/// items.
+fn failing_pc(block: &BasicBlock, height: usize) -> usize {
+    let mut height = height as isize;
+    for instruction in &block.instructions {
+        let Some(opcode) = instruction.opcode else {
+            break;
+        };
+        let (pops, pushes) = opcode.stack_io();
+        if height < pops as isize {
+            return instruction.pc;
+        }
+        height += pushes as isize - pops as isize;
+        if height > STACK_MAX_SIZE as isize {
+            return instruction.pc;
+        }
+    }
+    block.last().pc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evm::assembler::assemble;
+
+    fn analyze(source: &str) -> StackReport {
+        StackReport::analyze(&Cfg::build(&assemble(source).unwrap()))
+    }
+
+    #[test]
+    fn test_block_effects_and_valid_code() {
+        let report = analyze(
+            "PUSH1 1 PUSH1 2 PUSH @f JUMP
+             @f: JUMPDEST SWAP1 POP DUP1 ADD PUSH0 MSTORE STOP",
+        );
+        assert!(report.is_valid());
+        assert!(report.unreached.is_empty());
+        let f = report.blocks[1];
+        assert_eq!((f.required, f.net_change, f.max_growth), (2, -2, 0));
+        assert_eq!((f.min_entry, f.max_entry), (Some(2), Some(2)));
+        assert_eq!(report.blocks[0].max_growth, 3);
+        assert_eq!(report.to_string(), "");
+    }
+
+    #[test]
+    fn test_underflow_on_one_path() {
+        // Taking the jump skips the push that POP needs
+        let report = analyze(
+            "PUSH0 CALLDATALOAD PUSH @a JUMPI
+             PUSH1 1
+             @a: JUMPDEST POP STOP",
+        );
+        assert_eq!(
+            report.issues,
+            [StackIssue::Underflow {
+                pc: 8,
+                block: 7,
+                height: 0,
+                guaranteed: false
+            }]
+        );
+        assert_eq!(
+            (report.blocks[2].min_entry, report.blocks[2].max_entry),
+            (Some(0), Some(1))
+        );
+        assert_eq!(
+            report.to_string(),
+            "0x0008: possible stack underflow when the block at 0x0007 is entered with 0 items\n"
+        );
+
+        // Every path into the block fails
+        let report = analyze("PUSH1 1 PUSH0 CALLDATALOAD PUSH @a JUMPI POP @a: JUMPDEST ADD STOP");
+        assert_eq!(
+            report.issues,
+            [StackIssue::Underflow {
+                pc: 9,
+                block: 8,
+                height: 0,
+                guaranteed: true
+            }]
+        );
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["issues"][0]["guaranteed"], true);
+    }
+
+    #[test]
+    fn test_overflow_and_unreached() {
+        // Every iteration leaves one more item; the computed jump hides the last block
+        let report = analyze(
+            "@loop: JUMPDEST PUSH0 PUSH @loop JUMP
+             PUSH0 CALLDATALOAD JUMP
+             JUMPDEST STOP",
+        );
+        assert_eq!(
+            report.issues,
+            [StackIssue::Overflow {
+                pc: 2,
+                block: 0,
+                height: 1023,
+                guaranteed: false
+            }]
+        );
+        assert_eq!(report.unreached, [5, 8]);
+        assert!(report
+            .to_string()
+            .ends_with("unreached blocks: 0x0005 0x0008\n"));
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["issues"][0]["kind"], "overflow");
+        assert_eq!(json["blocks"][0]["maxEntry"], 1023);
+    }
+}
@@ -0,0 +1,162 @@
+//! EVM World State
+//!
+//! Implements the account model of the Ethereum world state (Yellow Paper, section 4.1):
+//! every address maps to an [`Account`] holding a balance, a nonce, code and a persistent
+//! storage mapping from 256-bit keys to 256-bit values.
+//!
+//! # Design
+//! - Plain in-memory maps; there is no trie and no state root
+//! - Accounts that were never touched are simply absent
+//! - Changes made during a transaction go through the `Journal` so they can be reverted
+
+use std::collections::BTreeMap;
+
+use alloy_primitives::{keccak256, KECCAK256_EMPTY};
+use serde::{Deserialize, Serialize};
+
+use crate::types::{Address, Bytes, StorageKey, StorageValue, B256, U256};
+
+/// A single account in the world state.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Account {
+    /// Balance in wei.
+    pub balance: U256,
+    /// Number of transactions sent (EOA) or contracts created (contract).
+    pub nonce: u64,
+    /// Runtime bytecode; empty for externally owned accounts.
+    pub code: Bytes,
+    /// Persistent storage. Slots holding zero are not stored.
+    pub storage: BTreeMap<StorageKey, StorageValue>,
+}
+
+impl Account {
+    /// Creates an account holding only a balance.
+    pub fn with_balance(balance: U256) -> Self {
+        Self {
+            balance,
+            ..Self::default()
+        }
+    }
+
+    /// Creates a contract account with the given runtime code.
+    pub fn with_code(code: Bytes) -> Self {
+        Self {
+            code,
+            ..Self::default()
+        }
+    }
+
+    /// Returns `true` if the account is empty as defined by EIP-161: no code, zero nonce
+    /// and zero balance.
+    pub fn is_empty(&self) -> bool {
+        self.code.is_empty() && self.nonce == 0 && self.balance.is_zero()
+    }
+
+    /// Returns the Keccak-256 hash of the account's code.
+    pub fn code_hash(&self) -> B256 {
+        if self.code.is_empty() {
+            KECCAK256_EMPTY
+        } else {
+            keccak256(&self.code)
+        }
+    }
+
+    /// Returns the value of a storage slot, which is zero if it was never written.
+    pub fn storage_value(&self, key: &StorageKey) -> StorageValue {
+        self.storage.get(key).copied().unwrap_or_default()
+    }
+}
+
+/// The world state: all accounts known to the EVM.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorldState {
+    accounts: BTreeMap<Address, Account>,
+}
+
+impl WorldState {
+    /// Creates an empty world state.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the account at `address`, if it exists.
+    pub fn account(&self, address: &Address) -> Option<&Account> {
+        self.accounts.get(address)
+    }
+
+    /// Returns a mutable reference to the account at `address`, if it exists.
+    pub fn account_mut(&mut self, address: &Address) -> Option<&mut Account> {
+        self.accounts.get_mut(address)
+    }
+
+    /// Returns a mutable reference to the account at `address`, creating an empty one
+    /// if it does not exist.
+    pub fn account_or_default(&mut self, address: Address) -> &mut Account {
+        self.accounts.entry(address).or_default()
+    }
+
+    /// Inserts or replaces the account at `address`.
+    pub fn insert_account(&mut self, address: Address, account: Account) {
+        self.accounts.insert(address, account);
+    }
+
+    /// Removes the account at `address`, returning it if it existed.
+    pub fn remove_account(&mut self, address: &Address) -> Option<Account> {
+        self.accounts.remove(address)
+    }
+
+    /// Returns `true` if an account exists at `address`.
+    pub fn contains(&self, address: &Address) -> bool {
+        self.accounts.contains_key(address)
+    }
+
+    /// Iterates over all accounts in address order.
+    pub fn accounts(&self) -> impl Iterator<Item = (&Address, &Account)> {
+        self.accounts.iter()
+    }
+
+    /// Returns the value of a storage slot, which is zero for missing accounts or slots.
+    pub fn storage(&self, address: &Address, key: &StorageKey) -> StorageValue {
+        self.account(address)
+            .map(|account| account.storage_value(key))
+            .unwrap_or_default()
+    }
+
+    /// Writes a storage slot, creating the account if needed. Writing zero removes the slot.
+    pub fn set_storage(&mut self, address: Address, key: StorageKey, value: StorageValue) {
+        let account = self.account_or_default(address);
+        if value.is_zero() {
+            account.storage.remove(&key);
+        } else {
+            account.storage.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_account() {
+        let account = Account::default();
+        assert!(account.is_empty());
+        assert_eq!(account.code_hash(), KECCAK256_EMPTY);
+        assert!(!Account::with_balance(U256::from(1)).is_empty());
+        assert!(!Account::with_code(Bytes::from_static(&[0x00])).is_empty());
+    }
+
+    #[test]
+    fn test_storage_defaults_to_zero() {
+        let mut state = WorldState::new();
+        let address = Address::with_last_byte(1);
+        let key = StorageKey::with_last_byte(2);
+        assert_eq!(state.storage(&address, &key), U256::ZERO);
+
+        state.set_storage(address, key, U256::from(7));
+        assert_eq!(state.storage(&address, &key), U256::from(7));
+
+        state.set_storage(address, key, U256::ZERO);
+        assert!(state.account(&address).unwrap().storage.is_empty());
+    }
+}
@@ -0,0 +1,38 @@
+//! Test Fixtures
+//!
+//! The accounts and transaction shared by the tests of the tools built on the
+//! interpreter: a funded sender calling a single contract.
+
+use super::env::TxEnv;
+use super::execution::Evm;
+use super::storage::{Account, WorldState};
+use crate::types::{Address, Bytes, U256};
+
+/// Sender of the test transaction, funded with one ether.
+pub(crate) const CALLER: Address = Address::with_last_byte(0xca);
+/// The contract the test transaction calls.
+pub(crate) const CONTRACT: Address = Address::with_last_byte(0xc0);
+
+/// Returns a state holding the funded [`CALLER`] and `contract` at [`CONTRACT`].
+pub(crate) fn state(contract: Account) -> WorldState {
+    let mut state = WorldState::new();
+    state.insert_account(CALLER, Account::with_balance(U256::from(10u64.pow(18))));
+    state.insert_account(CONTRACT, contract);
+    state
+}
+
+/// Returns a call from [`CALLER`] to [`CONTRACT`] with a gas limit of 100 000.
+pub(crate) fn call() -> TxEnv {
+    TxEnv {
+        caller: CALLER,
+        to: Some(CONTRACT),
+        gas_limit: 100_000,
+        ..TxEnv::default()
+    }
+}
+
+/// Returns an interpreter with `code` deployed at [`CONTRACT`], and the [`call`] to it.
+pub(crate) fn deploy(code: impl Into<Bytes>) -> (Evm, TxEnv) {
+    let evm = Evm::new().with_state(state(Account::with_code(code.into())));
+    (evm, call())
+}
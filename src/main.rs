@@ -1,3 +1,11 @@
-fn main() {
-    println!("Hello, world!");
+//! `smol-evm`: run, inspect and assemble EVM bytecode from the command line.
+
+use std::process::ExitCode;
+
+use clap::Parser;
+
+mod cli;
+
+fn main() -> ExitCode {
+    cli::Cli::parse().run()
 }
@@ -11,6 +11,25 @@ pub type Word = U256;
 pub type StorageKey = B256;
 pub type StorageValue = U256;
 
+/// An entry of an EIP-2930 access list: an address and the storage keys to pre-warm.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessListItem {
+    pub address: Address,
+    pub storage_keys: Vec<StorageKey>,
+}
+
+// EIP-2930 access list attached to a transaction.
+pub type AccessList = Vec<AccessListItem>;
+
+/// A log record emitted by the `LOG0`..`LOG4` opcodes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Log {
+    pub address: Address,
+    pub topics: Vec<B256>,
+    pub data: Bytes,
+}
+
 // Optionally, define other common types or enums here as your EVM grows.
 // For example, you might add an ExecutionResult, Error types, or enums for opcode categories.
 